@@ -0,0 +1,121 @@
+use std::io::{self, BufReader};
+
+use jetris::net::protocol::{read_message, write_message};
+use jetris::tbp::{BotMessage, FrontendMessage, Move, Orientation, Piece, PieceLocation, Spin};
+
+// Simple Tetris Bot Protocol bot for testing frontends. Drops every piece unrotated
+// where it ends up lowest, and answers with an error when the frontend's messages
+// don't add up, e.g. a move with a piece which isn't next in the queue.
+
+const WIDTH: i16 = 10;
+const HEIGHT: i16 = 40;
+
+struct Position {
+    board: Vec<Vec<Option<char>>>,
+    queue: Vec<Piece>,
+    hold: Option<Piece>,
+}
+
+impl Position {
+    fn is_free(&self, location: &PieceLocation) -> bool {
+        location.cells().iter().all(|&(x, y)| {
+            (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y)
+                && self.board.get(y as usize).is_none_or(|row| row[x as usize].is_none())
+        })
+    }
+
+    // Lowest place for the next piece in each column
+    fn suggest(&self) -> Option<Move> {
+        let piece = *self.queue.first()?;
+        let mut best: Option<PieceLocation> = None;
+        for x in 0..WIDTH {
+            let mut location = PieceLocation { piece, orientation: Orientation::North, x, y: HEIGHT - 3 };
+            if !self.is_free(&location) {
+                continue;
+            }
+            while location.y > 0 && self.is_free(&PieceLocation { y: location.y - 1, ..location }) {
+                location.y -= 1;
+            }
+            if best.is_none_or(|b| location.y < b.y) {
+                best = Some(location);
+            }
+        }
+        best.map(|location| Move { location, spin: Spin::None })
+    }
+
+    fn play(&mut self, mv: &Move) -> Result<(), String> {
+        let piece = mv.location.piece;
+        if self.queue.first() != Some(&piece) {
+            // Held piece, or the next one when nothing is held
+            let held = self.hold.or_else(|| self.queue.get(1).copied());
+            if held != Some(piece) || self.queue.is_empty() {
+                return Err(format!("{:?} is not the current or the held piece", piece));
+            }
+            if self.hold.is_none() {
+                self.queue.remove(1);
+            }
+            self.hold = Some(self.queue[0]);
+        }
+        self.queue.remove(0);
+
+        if !self.is_free(&mv.location) {
+            return Err(format!("{:?} overlaps the board", mv.location));
+        }
+        for (x, y) in mv.location.cells() {
+            self.board[y as usize][x as usize] = Some('X');
+        }
+        self.board.retain(|row| row.iter().any(|cell| cell.is_none()));
+        while self.board.len() < HEIGHT as usize {
+            self.board.push(vec![None; WIDTH as usize]);
+        }
+        Ok(())
+    }
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut reader = BufReader::new(stdin.lock());
+    let mut stdout = io::stdout();
+
+    write_message(&mut stdout, &BotMessage::Info {
+        name: "jetris-mock-bot".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        author: "jotalamp".to_string(),
+        features: Vec::new(),
+    })?;
+
+    let mut position: Option<Position> = None;
+    while let Some(message) = read_message(&mut reader)? {
+        match message {
+            FrontendMessage::Rules => write_message(&mut stdout, &BotMessage::Ready)?,
+            FrontendMessage::Start { hold, queue, board, .. } => {
+                position = Some(Position { board, queue, hold });
+            },
+            FrontendMessage::Suggest => {
+                let reply = match position.as_ref().map(|p| p.suggest()) {
+                    Some(Some(mv)) => BotMessage::Suggestion { moves: vec![mv] },
+                    Some(None) => BotMessage::Suggestion { moves: Vec::new() },
+                    None => BotMessage::Error { reason: "suggest before start".to_string() },
+                };
+                write_message(&mut stdout, &reply)?;
+            },
+            FrontendMessage::Play { mv } => {
+                let result = match position.as_mut() {
+                    Some(position) => position.play(&mv),
+                    None => Err("play before start".to_string()),
+                };
+                if let Err(reason) = result {
+                    write_message(&mut stdout, &BotMessage::Error { reason })?;
+                }
+            },
+            FrontendMessage::NewPiece { piece } => {
+                if let Some(position) = position.as_mut() {
+                    position.queue.push(piece);
+                }
+            },
+            FrontendMessage::Stop => position = None,
+            FrontendMessage::Quit => break,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use jetris::bot::{Bot, Weights};
+use jetris::engine::Rules;
+use jetris::fumen;
+use jetris::puzzle;
+use jetris::sim;
+use jetris::snapshot::{self, Snapshot, SnapshotRenderer};
+use jetris::theme::{self, Palette, Theme};
+
+// Renders boards to images without a window, for bug reports and sharing games:
+// `jetris-render --fumen v115@vhAAgH --out fumen.gif`, `jetris-render --puzzle puzzles
+// --out frames` or a replay of a seeded bot game, `jetris-render --seed 3 --pieces 100
+// --out game.gif`. Output ending in .gif is an animated GIF, in .png a single image of
+// the last frame, anything else a directory of numbered PNG frames.
+fn main() -> io::Result<()> {
+    let mut rules = Rules::default();
+    let mut fumen_text: Option<String> = None;
+    let mut puzzle_path: Option<PathBuf> = None;
+    let mut seed = 0;
+    let mut pieces = 100;
+    let mut out = PathBuf::from("frames");
+    let mut cell_size = snapshot::CELL_SIZE;
+    let mut delay = Duration::from_millis(250);
+    let mut theme = Theme::default();
+    let mut palette = Palette::Theme;
+
+    let args: Vec<String> = env::args().collect();
+    for pair in args.windows(2) {
+        let value = &pair[1];
+        match pair[0].as_str() {
+            "--fumen" => fumen_text = Some(value.clone()),
+            "--puzzle" => puzzle_path = Some(PathBuf::from(value)),
+            "--seed" => seed = value.parse().unwrap_or(seed),
+            "--pieces" => pieces = value.parse().unwrap_or(pieces),
+            "--out" => out = PathBuf::from(value),
+            "--cell" => cell_size = value.parse::<u32>().map_or(cell_size, |size| size.max(2)),
+            "--delay" => delay = value.parse().map_or(delay, Duration::from_millis),
+            "--width" => rules.board_width = value.parse().unwrap_or(rules.board_width),
+            "--height" => rules.board_height = value.parse().unwrap_or(rules.board_height),
+            "--theme" => {
+                let dir = Theme::find(value);
+                theme = Theme::load(&dir).map_err(|e| io::Error::other(format!("{}: {}", dir.display(), e)))?;
+            },
+            "--palette" => palette = Palette::from_name(value).unwrap_or(palette),
+            _ => {},
+        };
+    }
+    if let Err(e) = rules.validate() {
+        rules.clamp_board();
+        eprintln!("Invalid board size, {}. Using {}x{}", e, rules.board_width, rules.board_height);
+    }
+    palette.apply(&mut theme);
+
+    let snapshots = if let Some(text) = fumen_text.as_ref() {
+        fumen::decode(text).map_err(io::Error::other)?
+            .iter()
+            .map(|page| Snapshot::new(page.placed_field()))
+            .collect()
+    } else if let Some(path) = puzzle_path.as_ref() {
+        let loaded = puzzle::load(path)?;
+        for error in &loaded.errors {
+            eprintln!("{}", error);
+        }
+        loaded.puzzles.iter().map(|(_, puzzle)| Snapshot::from_engine(&puzzle.engine())).collect()
+    } else {
+        // The bot plays as fast as it can, one frame for each locked block
+        let mut bot = Bot::new(Weights::default(), 1000.0);
+        let mut snapshots = Vec::new();
+        sim::run_game_with(rules.clone(), &mut bot, seed, pieces, false,
+                           &mut |engine| snapshots.push(Snapshot::from_engine(engine)));
+        snapshots
+    };
+
+    let renderer = SnapshotRenderer::new(theme.tile_set(&rules.pieces), rules.pieces.clone(), theme::rgb(theme.background));
+    let renderer = SnapshotRenderer { cell_size, ..renderer };
+    let frames: Vec<_> = snapshots.iter().map(|snapshot| renderer.render(snapshot)).collect();
+    if frames.is_empty() {
+        return Err(io::Error::other("nothing to render"));
+    }
+
+    match out.extension().and_then(|extension| extension.to_str()) {
+        Some("gif") => snapshot::save_gif(&frames, delay, &out)?,
+        Some("png") => frames[frames.len() - 1].save(&out)?,
+        _ => {
+            snapshot::save_frames(&frames, &out)?;
+        },
+    }
+    println!("Rendered {} frames to {}", frames.len(), out.display());
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::env;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use jetris::net::protocol::DEFAULT_PORT;
+use jetris::net::server::{Server, RECONNECT_TIMEOUT, SPECTATOR_DELAY};
+
+// Versus server for network games, e.g. `jetris-server --port 7878 --reconnect-timeout 30
+// --spectator-delay 3000`
+fn main() -> std::io::Result<()> {
+    let mut port = DEFAULT_PORT;
+    let mut reconnect_timeout = RECONNECT_TIMEOUT;
+    let mut spectator_delay = SPECTATOR_DELAY;
+
+    let args: Vec<String> = env::args().collect();
+    for pair in args.windows(2) {
+        match pair[0].as_str() {
+            "--port" => port = pair[1].parse().unwrap_or(port),
+            "--reconnect-timeout" => {
+                if let Ok(seconds) = pair[1].parse() {
+                    reconnect_timeout = Duration::from_secs(seconds);
+                }
+            },
+            "--spectator-delay" => {
+                if let Ok(millis) = pair[1].parse() {
+                    spectator_delay = Duration::from_millis(millis);
+                }
+            },
+            _ => {},
+        };
+    }
+
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Jetris server listening on port {}", port);
+    Server::new(reconnect_timeout, spectator_delay).run(listener)
+}
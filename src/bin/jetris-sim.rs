@@ -0,0 +1,88 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use jetris::bot::{Bot, Controller, Weights};
+use jetris::engine::Rules;
+use jetris::pieces::PieceSet;
+use jetris::sim::{self, GameStats, Summary};
+use jetris::tbp::ExternalBot;
+
+// Plays seeded games without a window and prints statistics, e.g. `jetris-sim --games 20
+// --seed 1 --pieces 500 --weights weights.json --format csv`. Game i uses seed + i, so
+// runs with different weights or bots see the same blocks.
+fn main() -> io::Result<()> {
+    let mut games = 10;
+    let mut seed = 0;
+    let mut pieces = 1000;
+    let mut pps = 2.0;
+    let mut weights = Weights::default();
+    let mut tbp_command: Option<String> = None;
+    let mut rules = Rules::default();
+    let mut csv = false;
+
+    let args: Vec<String> = env::args().collect();
+    for pair in args.windows(2) {
+        let value = &pair[1];
+        match pair[0].as_str() {
+            "--games" => games = value.parse().unwrap_or(games),
+            "--seed" => seed = value.parse().unwrap_or(seed),
+            "--pieces" => pieces = value.parse().unwrap_or(pieces),
+            "--pps" => pps = value.parse().unwrap_or(pps),
+            "--weights" => {
+                let json = fs::read_to_string(value)?;
+                weights = serde_json::from_str(&json)
+                    .map_err(|e| io::Error::other(format!("{}: {}", value, e)))?;
+            },
+            "--tbp" => tbp_command = Some(value.clone()),
+            "--width" => rules.board_width = value.parse().unwrap_or(rules.board_width),
+            "--height" => rules.board_height = value.parse().unwrap_or(rules.board_height),
+            "--gravity" => rules.gravity_interval = value.parse().map_or(rules.gravity_interval, Duration::from_millis),
+            "--clear-delay" => rules.line_clear_delay = value.parse().map_or(rules.line_clear_delay, Duration::from_millis),
+            "--format" => csv = value == "csv",
+            _ => {},
+        };
+    }
+    if let Err(e) = rules.validate() {
+        rules.clamp_board();
+        eprintln!("Invalid board size, {}. Using {}x{}", e, rules.board_width, rules.board_height);
+    }
+
+    let mut controller: Box<dyn Controller> = match tbp_command.as_ref() {
+        Some(command) => {
+            let mut words = command.split_whitespace().map(String::from);
+            let program = words.next().unwrap_or_default();
+            let args: Vec<String> = words.collect();
+            Box::new(ExternalBot::launch(&program, &args, pps)?)
+        },
+        None => Box::new(Bot::new(weights, pps)),
+    };
+
+    let results: Vec<GameStats> = (0..games)
+        .map(|i| sim::run_game(rules.clone(), controller.as_mut(), seed + i, pieces, tbp_command.is_some()))
+        .collect();
+    let summary = Summary::new(&results, &rules.pieces);
+
+    if csv {
+        print_csv(&results, &summary, &rules.pieces);
+    } else {
+        let json = serde_json::json!({ "games": results, "summary": summary });
+        println!("{}", serde_json::to_string_pretty(&json).map_err(io::Error::other)?);
+    }
+    Ok(())
+}
+
+// One row per game and a last row with the averages, seed left empty
+fn print_csv(results: &[GameStats], summary: &Summary, pieces: &PieceSet) {
+    let names: Vec<&str> = (0..pieces.len()).map(|i| pieces.name(i as u8)).collect();
+    println!("seed,pieces,lines,score,attack,time,pps,app,topped_out,{}", names.join(","));
+    for game in results {
+        let counts: Vec<String> = game.piece_counts.iter().map(|c| c.to_string()).collect();
+        println!("{},{},{},{},{},{:.2},{:.3},{:.3},{},{}", game.seed, game.pieces, game.lines, game.score,
+                 game.attack, game.time, game.pps(), game.app(), game.topped_out, counts.join(","));
+    }
+    let shares: Vec<String> = summary.piece_distribution.iter().map(|(_, share)| format!("{:.3}", share)).collect();
+    println!(",{:.1},{:.1},{:.1},,,{:.3},{:.3},{:.3},{}", summary.pieces, summary.lines, summary.score,
+             summary.pps, summary.app, summary.top_out_rate, shares.join(","));
+}
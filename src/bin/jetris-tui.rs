@@ -0,0 +1,89 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+
+use std::env;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use jetris::engine::Rules;
+use jetris::pieces::PieceSet;
+use jetris::theme::{Palette, Theme};
+use jetris::tui::{self, TerminalGame};
+
+// Longest wait for a key before the game goes on
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+// Plays in the terminal instead of a window, e.g. over SSH: `jetris-tui --width 8
+// --theme midnight --palette deuteranopia`. Takes the rule options of the game too.
+fn main() -> io::Result<()> {
+    let mut rules = Rules::default();
+    let mut theme = Theme::default();
+    let mut palette = Palette::Theme;
+
+    let args: Vec<String> = env::args().collect();
+    for pair in args.windows(2) {
+        let value = &pair[1];
+        match pair[0].as_str() {
+            "--width" => rules.board_width = value.parse().unwrap_or(rules.board_width),
+            "--height" => rules.board_height = value.parse().unwrap_or(rules.board_height),
+            "--gravity" => rules.gravity_interval = value.parse().map_or(rules.gravity_interval, Duration::from_millis),
+            "--clear-delay" => rules.line_clear_delay = value.parse().map_or(rules.line_clear_delay, Duration::from_millis),
+            "--pieces" => {
+                rules.pieces = PieceSet::load(Path::new(value))
+                    .map_err(|e| io::Error::other(format!("{}: {}", value, e)))?;
+            },
+            "--theme" => {
+                let dir = Theme::find(value);
+                theme = Theme::load(&dir).map_err(|e| io::Error::other(format!("{}: {}", dir.display(), e)))?;
+            },
+            "--palette" => palette = Palette::from_name(value).unwrap_or(palette),
+            _ => {},
+        };
+    }
+    if let Err(e) = rules.validate() {
+        rules.clamp_board();
+        eprintln!("Invalid board size, {}. Using {}x{}", e, rules.board_width, rules.board_height);
+    }
+    palette.apply(&mut theme);
+    let tile_set = theme.tile_set(&rules.pieces);
+    let game = TerminalGame::new(rules, tile_set);
+
+    // The terminal is given back as it was also when the game panics
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, game);
+    ratatui::restore();
+    result
+}
+
+// Draws and updates the game until Q, Escape or Ctrl+C
+fn run(terminal: &mut DefaultTerminal, mut game: TerminalGame) -> io::Result<()> {
+    let mut last_update = Instant::now();
+    loop {
+        terminal.draw(|frame| game.draw(frame))?;
+
+        if event::poll(FRAME_TIME)? {
+            // Terminals send repeats of a held key as presses, and no releases
+            // unless asked for
+            if let Event::Key(key) = event::read()? {
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    _ if key.kind == KeyEventKind::Release => {},
+                    KeyCode::Char('c') if ctrl => return Ok(()),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('p') => game.toggle_pause(),
+                    KeyCode::Char('r') => game.restart(),
+                    code => {
+                        if let Some(input) = tui::key_input(code) {
+                            game.input(input);
+                        }
+                    },
+                }
+            }
+        }
+
+        let now = Instant::now();
+        game.update(now - last_update);
+        last_update = now;
+    }
+}
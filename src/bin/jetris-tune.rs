@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+use jetris::tune::{Checkpoint, Config};
+
+// Evolves bot weights with a genetic algorithm, e.g. `jetris-tune --generations 50
+// --population 20 --games 3 --pieces 300 --seed 1 --checkpoint tune.json --out weights.json`.
+// An existing checkpoint is continued, with the settings it was started with.
+fn main() -> io::Result<()> {
+    let mut config = Config::default();
+    let mut generations = 20;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut checkpoint_path = PathBuf::from("tune.json");
+    let mut out: Option<PathBuf> = None;
+
+    let args: Vec<String> = env::args().collect();
+    for pair in args.windows(2) {
+        let value = &pair[1];
+        match pair[0].as_str() {
+            "--generations" => generations = value.parse().unwrap_or(generations),
+            "--threads" => threads = value.parse().unwrap_or(threads),
+            "--checkpoint" => checkpoint_path = PathBuf::from(value),
+            "--out" => out = Some(PathBuf::from(value)),
+            "--seed" => config.seed = value.parse().unwrap_or(config.seed),
+            "--population" => config.population = value.parse().unwrap_or(config.population),
+            "--games" => config.games = value.parse().unwrap_or(config.games),
+            "--pieces" => config.pieces = value.parse().unwrap_or(config.pieces),
+            "--mutation-rate" => config.mutation_rate = value.parse().unwrap_or(config.mutation_rate),
+            "--mutation-size" => config.mutation_size = value.parse().unwrap_or(config.mutation_size),
+            "--width" => config.board_width = value.parse().unwrap_or(config.board_width),
+            "--height" => config.board_height = value.parse().unwrap_or(config.board_height),
+            _ => {},
+        };
+    }
+    config.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut rules = config.rules();
+    if let Err(e) = rules.validate() {
+        rules.clamp_board();
+        eprintln!("Invalid board size, {}. Using {}x{}", e, rules.board_width, rules.board_height);
+        config.board_width = rules.board_width;
+        config.board_height = rules.board_height;
+    }
+
+    let mut checkpoint = if checkpoint_path.exists() {
+        let checkpoint = Checkpoint::load(&checkpoint_path)?;
+        eprintln!("Continuing {} from generation {}", checkpoint_path.display(), checkpoint.generation);
+        checkpoint
+    } else {
+        Checkpoint::new(config)
+    };
+
+    while checkpoint.generation < generations {
+        let individuals = checkpoint.step(threads);
+        let mean = individuals.iter().map(|i| i.fitness).sum::<f32>() / individuals.len() as f32;
+        eprintln!("Generation {}: best {:.1}, mean {:.1}", checkpoint.generation, individuals[0].fitness, mean);
+        checkpoint.save(&checkpoint_path)?;
+    }
+
+    if let Some(best) = checkpoint.best() {
+        let json = serde_json::to_string_pretty(&best.weights).map_err(io::Error::other)?;
+        match out {
+            Some(path) => fs::write(path, json)?,
+            None => println!("{}", json),
+        }
+    }
+    Ok(())
+}
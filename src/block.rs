@@ -0,0 +1,156 @@
+use array2d::Array2D;
+
+use crate::pieces::{PieceShape, SHAPE_SIZE};
+use crate::tile_map::{block_tile, TileMap};
+use crate::Vector2;
+
+// Index of the T-shape in the standard piece set
+pub const T_BLOCK: u8 = 2;
+// Number of pieces in the standard piece set
+pub const BLOCK_TYPES: u8 = 7;
+// Names of the pieces in the standard piece set
+pub const BLOCK_NAMES: [&str; BLOCK_TYPES as usize] = ["O", "I", "T", "S", "Z", "L", "J"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    Cw0,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub fn next(&self) -> Rotation {
+        match self {
+            Rotation::Cw0   => Rotation::Cw90,
+            Rotation::Cw90  => Rotation::Cw180,
+            Rotation::Cw180 => Rotation::Cw270,
+            Rotation::Cw270 => Rotation::Cw0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub position: Vector2,
+    pub previous_position: Vector2,
+    pub block_type: u8,
+    shape: Array2D<u8>,
+    pub rotation: Rotation,
+    pub previous_rotation: Rotation,
+    pub down: bool,
+    pub moving_down: bool,
+}
+
+impl Block {
+    /// Block of 'block_type' with the cells of 'piece', in its spawn rotation
+    pub fn new(position: Vector2, block_type: u8, piece: &PieceShape) -> Self {
+        let shape = piece.grid().unwrap_or_else(|| Array2D::filled_with(0, SHAPE_SIZE, SHAPE_SIZE));
+        let rotation = piece.spawn_rotation();
+
+        Block { 
+            position, 
+            previous_position: position, 
+            block_type, 
+            shape, 
+            rotation, 
+            previous_rotation: rotation, 
+            down: false, 
+            moving_down: false 
+        }
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize, previous: bool) -> bool {
+        
+        let r = match previous {
+            true    => &self.previous_rotation,
+            false   => &self.rotation,
+        };
+
+        let c = match r {
+            Rotation::Cw0   => self.shape[(x,y)],
+            Rotation::Cw90  => self.shape[(y,4-x)],
+            Rotation::Cw180 => self.shape[(4-x,4-y)],
+            Rotation::Cw270 => self.shape[(4-y,x)],
+        };
+
+        c > 0
+    }
+
+    // Lowest row of the 5x5 shape which has a cell in the current rotation
+    pub fn lowest_row(&self) -> i16 {
+        let mut lowest = 0;
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) && y as i16 > lowest {
+                    lowest = y as i16;
+                }
+            }
+        }
+        lowest
+    }
+
+    // Whether the block overlaps any non-empty cell of the tile map
+    pub fn collides(&self, tile_map: &TileMap) -> bool {
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) && tile_map._get_cell(self.position.x + x as i16, self.position.y + y as i16) > 0 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Tile map positions of the cells of the block
+    pub fn cells(&self) -> Vec<Vector2> {
+        let mut cells = Vec::new();
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) {
+                    cells.push(self.position + Vector2::new(x as i16, y as i16));
+                }
+            }
+        }
+        cells
+    }
+
+    // Whether all cells of the block are above row 'row'
+    pub fn is_above(&self, row: i16) -> bool {
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) && self.position.y + y as i16 >= row {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn _mark_to_tile_map(&mut self, tile_map: &mut TileMap) {
+
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) {
+                    tile_map.set_cell(self.position.x + x as i16, self.position.y + y as i16, block_tile(self.block_type));
+                }    
+            }
+        }
+    }
+
+    pub fn _delete_from_tile_map(&mut self, tile_map: &mut TileMap) {
+
+        for x in 0..5 {
+            for y in 0..5 {
+                if self.get_cell(x,y,false) {
+                    tile_map.set_cell(self.previous_position.x + x as i16, self.previous_position.y + y as i16, 0);
+                }    
+            }
+        }
+    }
+    
+    pub fn is_down(&mut self) -> bool {
+        self.down
+    }
+
+}
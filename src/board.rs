@@ -0,0 +1,323 @@
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color};
+use ggez::{Context, GameResult};
+
+use std::time::Duration;
+
+use crate::effects::{EffectSettings, Effects};
+use crate::engine::{Engine, Event, Input, Rules, PREVIEW_LENGTH};
+use crate::finesse::{Finesse, Trainer};
+use crate::net::protocol::BoardState;
+use crate::opener::Practice;
+use crate::renderer::TileMapRenderer;
+use crate::stats::{Stat, Stats};
+use crate::theme::Skin;
+use crate::tile_map::{TileMap, TileSet};
+use crate::Vector2;
+
+// Largest cell size in pixels, cells shrink if the board doesn't fit
+const CELL_SIZE: i16 = 32;
+
+// Largest cell size for 'tile_map' to fit in 'area' with a margin of one cell, at most
+// 'scale' times CELL_SIZE
+fn fit_cell_size(tile_map: &TileMap, area: Vector2, scale: f32) -> i16 {
+    let largest = ((CELL_SIZE as f32 * scale) as i16).max(1);
+    let visible_size = Vector2::new(tile_map.size.x, tile_map.size.y - tile_map.hidden_rows);
+    largest
+        .min((area.x - 2 * largest) / visible_size.x)
+        .min((area.y - 2 * largest) / visible_size.y)
+        .max(1)
+}
+
+/// Keys which control one board.
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub rotate: KeyCode,
+    pub soft_drop: KeyCode,
+    pub hard_drop: KeyCode,
+    pub hold: KeyCode,
+}
+
+impl KeyBindings {
+    /// Arrow keys, Space and C when playing alone
+    pub fn single_player() -> Self {
+        KeyBindings {
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            rotate: KeyCode::Up,
+            soft_drop: KeyCode::Down,
+            hard_drop: KeyCode::Space,
+            hold: KeyCode::C,
+        }
+    }
+
+    /// WASD, left shift and E for the left player in versus
+    pub fn versus_left() -> Self {
+        KeyBindings {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            rotate: KeyCode::W,
+            soft_drop: KeyCode::S,
+            hard_drop: KeyCode::LShift,
+            hold: KeyCode::E,
+        }
+    }
+
+    /// Arrow keys, right shift and right control for the right player in versus
+    pub fn versus_right() -> Self {
+        KeyBindings {
+            hard_drop: KeyCode::RShift,
+            hold: KeyCode::RControl,
+            ..KeyBindings::single_player()
+        }
+    }
+}
+
+/// One player's board: the engine with the drawing and effects of its tile map.
+pub struct Board {
+    pub engine: Engine,
+    pub renderer: TileMapRenderer,
+    pub effects: Effects,
+    // None for a board played by someone over the network
+    pub keys: Option<KeyBindings>,
+    pub finesse: Finesse,
+    pub stats: Stats,
+    // Target places for each block when practicing finesse
+    pub trainer: Option<Trainer>,
+    // Opener being practiced, every attempt starts from its setup
+    pub practice: Option<Practice>,
+    // Events from key presses, returned with the next update
+    events: Vec<Event>,
+}
+
+impl Board {
+    /// Board drawn centered on 'center' and scaled to fit in an area of 'area' pixels
+    pub fn new(ctx: &mut Context, rules: Rules, tile_set: TileSet, effect_settings: EffectSettings,
+               keys: Option<KeyBindings>, center: Vector2, area: Vector2) -> Self {
+        let engine = Engine::new(rules);
+        let cell_size = fit_cell_size(&engine.tile_map, area, 1.0);
+
+        let renderer = TileMapRenderer::new(ctx, cell_size, center, tile_set);
+        let effects = Effects::new(ctx, effect_settings);
+
+        Board {
+            engine,
+            renderer,
+            effects,
+            keys,
+            finesse: Finesse::default(),
+            stats: Stats::default(),
+            trainer: None,
+            practice: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Moves the board to 'center' and fits it in 'area' again, e.g. after the window
+    /// was resized. Cells grow up to 'scale' times their usual size.
+    pub fn resize(&mut self, center: Vector2, area: Vector2, scale: f32) {
+        self.renderer.center = center;
+        self.renderer.cell_size = fit_cell_size(&self.engine.tile_map, area, scale);
+    }
+
+    // Starts a new game on this board
+    pub fn restart(&mut self, rules: Rules) {
+        self.start(Engine::new(rules));
+    }
+
+    // Starts the next attempt of the practiced opener
+    pub fn retry_practice(&mut self) {
+        if let Some(practice) = self.practice.as_mut() {
+            let engine = practice.start();
+            self.start(engine);
+        }
+    }
+
+    // Starts a game with a prepared engine, e.g. one set up for a puzzle
+    pub fn start(&mut self, engine: Engine) {
+        self.engine = engine;
+        self.effects.clear();
+        self.finesse = Finesse::default();
+        self.stats = Stats::default();
+        self.events.clear();
+        if self.trainer.is_some() {
+            self.trainer = Some(Trainer::default());
+        }
+    }
+
+    // Shows 'field' and the next blocks without playing, e.g. in the editor
+    pub fn show(&mut self, field: &TileMap, preview: &[u8]) {
+        self.engine.tile_map = field.clone();
+        self.engine.set_queue(preview, None);
+    }
+
+    // Draws the board with the look of a theme
+    pub fn set_skin(&mut self, skin: &Skin, tile_set: TileSet) {
+        self.renderer.set_skin(skin, tile_set);
+    }
+
+    // Moves the block if the key belongs to this board
+    pub fn key_down(&mut self, keycode: KeyCode) {
+        let keys = match &self.keys {
+            Some(keys) => keys,
+            None => return,
+        };
+        let input = if keycode == keys.left {
+            Input::Left
+        } else if keycode == keys.right {
+            Input::Right
+        } else if keycode == keys.rotate {
+            Input::Rotate
+        } else if keycode == keys.soft_drop {
+            Input::SoftDrop
+        } else if keycode == keys.hard_drop {
+            Input::HardDrop
+        } else if keycode == keys.hold {
+            Input::Hold
+        } else {
+            return;
+        };
+
+        self.stats.keys += 1;
+        self.finesse.input(&self.engine, input);
+        if input == Input::Hold && self.engine.can_hold() {
+            // The new block gets its own target
+            if let Some(trainer) = self.trainer.as_mut() {
+                trainer.target = None;
+            }
+        }
+        self.engine.apply(input);
+
+        // A hard drop locks right away, judge it before the next block is moved
+        let events = self.engine.drain_events();
+        self.handle_events(events);
+    }
+
+    // Shows the state of a board played over the network. States which don't
+    // make a valid tile map or have unknown blocks are ignored.
+    pub fn apply_state(&mut self, state: &BoardState) {
+        if state.preview.iter().chain(state.hold.iter()).any(|&t| t as usize >= self.engine.pieces().len()) {
+            return;
+        }
+        if let Some(tile_map) = state.to_tile_map() {
+            self.engine.tile_map = tile_map;
+            self.engine.points = state.points;
+            self.engine.pending_garbage = state.pending_garbage;
+            self.engine.set_queue(&state.preview, state.hold);
+        }
+    }
+
+    // Advances the game by 'dt', starts effects for what happened and returns
+    // the events for the caller
+    pub fn update(&mut self, dt: Duration) -> Vec<Event> {
+        self.engine.update(dt);
+        self.stats.time += dt;
+
+        let events = self.engine.drain_events();
+        self.handle_events(events);
+        std::mem::take(&mut self.events)
+    }
+
+    // Starts effects and judges finesse for events of the engine, and keeps them for
+    // the next update
+    fn handle_events(&mut self, events: Vec<Event>) {
+        let mut locked = false;
+        for event in &events {
+            self.stats.record(event);
+            match event {
+                Event::HardDrop => self.effects.add_shake(4.0, 0.15),
+                Event::Locked(block_type, cells) => {
+                    self.effects.add_lock_flash(cells);
+                    if self.keys.is_some() {
+                        self.finesse.lock(self.engine.pieces(), *block_type, cells);
+                    }
+                    if let Some(trainer) = self.trainer.as_mut() {
+                        trainer.lock(cells);
+                        locked = true;
+                    }
+                    if let Some(practice) = self.practice.as_mut() {
+                        practice.lock(cells);
+                    }
+                },
+                Event::LinesCleared(clear) => {
+                    self.effects.add_line_clear(&self.engine.tile_map, &self.renderer.tile_set, &clear.rows, self.renderer.cell_size);
+                    if let Some(practice) = self.practice.as_mut() {
+                        practice.clear(&clear.rows);
+                    }
+                    // Tetris
+                    if clear.lines() >= 4 {
+                        self.effects.add_shake(10.0, 0.35);
+                    }
+                },
+            }
+        }
+        self.events.extend(events);
+
+        // Every trained block starts on an empty field
+        if locked {
+            self.engine.clear_field();
+        }
+        // A miss or a finished setup starts the next attempt right away
+        if let Some(practice) = self.practice.as_mut().filter(|p| p.is_over()) {
+            self.engine = practice.start();
+        }
+        if self.keys.is_some() {
+            self.finesse.watch(&self.engine);
+            if let Some(trainer) = self.trainer.as_mut() {
+                trainer.watch(&self.engine);
+            }
+        }
+    }
+
+    // Updates sprites and effects, also while the game is not on
+    pub fn update_graphics(&mut self, dt: Duration) {
+        self.renderer.update_spritebatches(&self.engine.tile_map, self.engine.cleared_rows(), self.engine.clear_progress());
+        // Fixed sequences can be longer than the preview
+        let preview: Vec<u8> = self.engine.preview().iter().take(PREVIEW_LENGTH).copied().collect();
+        self.renderer.update_queue(&self.engine.tile_map, self.engine.pieces(), &preview, self.engine.held());
+        self.effects.update(dt.as_secs_f32());
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let shake = self.effects.shake_offset();
+        self.renderer.draw(ctx, &self.engine.tile_map, shake)?;
+        self.renderer.draw_garbage_meter(ctx, &self.engine.tile_map, self.engine.pending_garbage, shake)?;
+        if let Some(target) = self.trainer.as_ref().and_then(|t| t.target.as_ref()) {
+            let color = Color::new(1.0, 1.0, 1.0, 0.8);
+            self.renderer.draw_outline(ctx, &self.engine.tile_map, &target.block.cells(), color, shake)?;
+        }
+        if let Some(practice) = self.practice.as_ref() {
+            // Later blocks are fainter than the current one
+            for cells in practice.later_targets() {
+                self.renderer.draw_outline(ctx, &self.engine.tile_map, cells, Color::new(1.0, 1.0, 1.0, 0.25), shake)?;
+            }
+            if let Some(cells) = practice.target() {
+                self.renderer.draw_outline(ctx, &self.engine.tile_map, cells, Color::new(1.0, 1.0, 1.0, 0.8), shake)?;
+            }
+        }
+
+        let offset = self.renderer.get_offset(&self.engine.tile_map);
+        let offset = (offset.x as f32 + shake.0, offset.y as f32 + shake.1);
+        self.effects.draw(ctx, &self.engine.tile_map, self.renderer.cell_size, offset)?;
+
+        Ok(())
+    }
+
+    // Statistics panel left of the board, under the held block
+    pub fn draw_stats(&self, ctx: &mut Context, shown: &[Stat], skin: &Skin) -> GameResult<()> {
+        let cell = self.renderer.cell_size as f32;
+        let offset = self.renderer.get_offset(&self.engine.tile_map);
+        let right = offset.x as f32 - 0.5 * cell;
+        let mut y = offset.y as f32 + 4.0 * cell;
+        for stat in shown {
+            let text = skin.text(stat.text(&self.stats, &self.finesse), skin.hud.text, 0.6 * cell);
+            let dest_point = mint::Vector2 { x: right - text.width(ctx) as f32, y };
+            graphics::draw(ctx, &text, (dest_point,))?;
+            y += 0.8 * cell;
+        }
+
+        Ok(())
+    }
+}
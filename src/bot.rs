@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::engine::{self, Engine, Input};
+use crate::placement::{self, Placement};
+use crate::pieces::PieceSet;
+use crate::tile_map::{block_tile, TileMap};
+use crate::Vector2;
+
+// How many of the best placements are looked at again with the next block
+const LOOKAHEAD_CANDIDATES: usize = 8;
+
+/// Weights of the board evaluation. Features which are bad for the board have
+/// negative weights.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// Empty cells with a filled cell somewhere above them
+    pub holes: f32,
+    /// Sum of height differences of neighbouring columns
+    pub bumpiness: f32,
+    /// Sum of column heights
+    pub height: f32,
+    /// Height of the highest column
+    pub max_height: f32,
+    /// Sum of well depths, a well being a column lower than both of its neighbours
+    pub wells: f32,
+    /// Places where a T fits in with a spin, clearing two rows
+    pub t_slots: f32,
+    /// Reward for clearing 0, 1, 2, 3 and 4 rows
+    pub clears: [f32; 5],
+    /// Reward for each row cleared with a T-spin
+    pub t_spin: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            holes: -4.0,
+            bumpiness: -0.3,
+            height: -0.2,
+            max_height: -0.5,
+            wells: -0.3,
+            t_slots: 1.0,
+            clears: [0.0, -1.0, -0.5, 0.5, 6.0],
+            t_spin: 3.0,
+        }
+    }
+}
+
+/// Picks the best placement for the current block, or for the held block (or the first
+/// block of the preview when nothing is held) if holding is allowed. With a preview the
+/// best placements are checked against the placements of the next block too.
+pub fn best_placement(field: &TileMap, pieces: &PieceSet, current: &Block, hold: Option<u8>, can_hold: bool,
+                      preview: &[u8], weights: &Weights) -> Option<Placement> {
+    let mut options = vec![(current.clone(), Vec::new(), preview)];
+    if can_hold {
+        let (swapped, rest) = match hold {
+            Some(held) => (Some(held), preview),
+            None => (preview.first().copied(), preview.get(1..).unwrap_or(&[])),
+        };
+        if let Some(swapped) = swapped {
+            let (block, free) = engine::spawn(field, pieces, swapped);
+            if free {
+                options.push((block, vec![Input::Hold], rest));
+            }
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (block, prefix, rest) in options {
+        for mut placement in placement::placements_from(field, pieces, &block) {
+            let (after, score) = place(field, pieces, &placement, weights);
+            placement.inputs.splice(0..0, prefix.iter().copied());
+            candidates.push((placement, after, score, rest));
+        }
+    }
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    // Second look at the best ones with the next block
+    candidates.truncate(LOOKAHEAD_CANDIDATES);
+    let mut best: Option<(Placement, f32)> = None;
+    for (placement, after, score, rest) in candidates {
+        let score = match rest.first() {
+            Some(&next) => score + best_score(&after, pieces, next, weights).unwrap_or(f32::MIN / 2.0),
+            None => score,
+        };
+        if best.as_ref().is_none_or(|(_, best_score)| score > *best_score) {
+            best = Some((placement, score));
+        }
+    }
+    best.map(|(placement, _)| placement)
+}
+
+// Score of the best placement of a new block of 'block_type', None if it can't even spawn
+fn best_score(field: &TileMap, pieces: &PieceSet, block_type: u8, weights: &Weights) -> Option<f32> {
+    placement::placements(field, pieces, block_type).iter()
+        .map(|placement| place(field, pieces, placement, weights).1)
+        .fold(None, |best, score| Some(best.map_or(score, |b: f32| b.max(score))))
+}
+
+// Locks the placement on a copy of the field and clears full rows. Returns the new
+// field and its score.
+fn place(field: &TileMap, pieces: &PieceSet, placement: &Placement, weights: &Weights) -> (TileMap, f32) {
+    let mut field = field.clone();
+    let block = &placement.block;
+    for cell in block.cells() {
+        field.set_cell(cell.x, cell.y, block_tile(block.block_type));
+    }
+
+    let spin = Some(block.block_type) == pieces.t_block()
+        && placement.spin
+        && filled_corners(&field, block.position + Vector2::new(2, 2)) >= 3;
+
+    let rows = field.full_rows();
+    field.remove_rows(&rows);
+
+    let lines = rows.len().min(4);
+    let mut score = weights.clears[lines] + evaluate(&field, weights);
+    if spin {
+        score += weights.t_spin * lines as f32;
+    }
+    (field, score)
+}
+
+fn filled_corners(field: &TileMap, center: Vector2) -> usize {
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter()
+        .filter(|&&(dx, dy)| field._get_cell(center.x + dx, center.y + dy) > 0)
+        .count()
+}
+
+/// Weighted score of a field without a falling block, higher is better
+pub fn evaluate(field: &TileMap, weights: &Weights) -> f32 {
+    let bottom = field.size.y - 1;
+    let columns = 1..field.size.x - 1;
+
+    // Height of each column and holes under the tops
+    let mut heights = Vec::new();
+    let mut holes = 0;
+    for x in columns.clone() {
+        let top = (0..bottom).find(|&y| field._get_cell(x, y) > 0).unwrap_or(bottom);
+        heights.push(bottom - top);
+        holes += (top..bottom).filter(|&y| field._get_cell(x, y) == 0).count();
+    }
+
+    let bumpiness: i16 = heights.windows(2).map(|w| (w[0] - w[1]).abs()).sum();
+    let height: i16 = heights.iter().sum();
+    let max_height = heights.iter().copied().max().unwrap_or(0);
+
+    // Walls count as infinitely high neighbours
+    let mut wells = 0;
+    for i in 0..heights.len() {
+        let left = if i > 0 { heights[i - 1] } else { i16::MAX };
+        let right = heights.get(i + 1).copied().unwrap_or(i16::MAX);
+        let depth = left.min(right) - heights[i];
+        if depth > 0 && depth < i16::MAX / 2 {
+            wells += depth;
+        }
+    }
+
+    let t_slots = columns.clone()
+        .flat_map(|x| (0..bottom).map(move |y| (x, y)))
+        .filter(|&(x, y)| is_t_slot(field, x, y))
+        .count();
+
+    weights.holes * holes as f32
+        + weights.bumpiness * bumpiness as f32
+        + weights.height * height as f32
+        + weights.max_height * max_height as f32
+        + weights.wells * wells as f32
+        + weights.t_slots * t_slots as f32
+}
+
+// Whether a T pointing down with its center at (x, y) would fit in with a spin and
+// fill a hole under an overhang
+fn is_t_slot(field: &TileMap, x: i16, y: i16) -> bool {
+    let empty = |dx: i16, dy: i16| field._get_cell(x + dx, y + dy) == 0;
+    let filled = |dx: i16, dy: i16| !empty(dx, dy);
+
+    empty(-1, 0) && empty(0, 0) && empty(1, 0) && empty(0, 1)
+        && filled(-1, 1) && filled(1, 1) && filled(0, 2)
+        && (filled(-1, -1) || filled(1, -1))
+        && empty(0, -1)
+}
+
+/// Something which plays a board instead of a player.
+pub trait Controller {
+    /// Advances by 'dt' and moves the block when it is time
+    fn update(&mut self, engine: &mut Engine, dt: Duration);
+    /// Forgets the previous game, before a new one starts
+    fn new_game(&mut self);
+}
+
+/// Plays a board by itself, one block every 1/pps seconds.
+pub struct Bot {
+    pub weights: Weights,
+    /// Pieces per second
+    pub pps: f32,
+    // Time since the last block was placed
+    time: Duration,
+}
+
+impl Bot {
+    pub fn new(weights: Weights, pps: f32) -> Self {
+        Bot {
+            weights,
+            pps,
+            time: Duration::from_millis(0),
+        }
+    }
+
+    /// Places the current block right away
+    pub fn play(&self, engine: &mut Engine) {
+        let preview: Vec<u8> = engine.preview().iter().copied().collect();
+        let placement = best_placement(&engine.field(), engine.pieces(), &engine.block, engine.held(),
+                                       engine.can_hold(), &preview, &self.weights);
+        match placement {
+            Some(placement) => {
+                for input in placement.inputs {
+                    engine.apply(input);
+                }
+            },
+            // Nowhere to go, give up
+            None => engine.hard_drop(),
+        }
+    }
+}
+
+impl Controller for Bot {
+    fn update(&mut self, engine: &mut Engine, dt: Duration) {
+        self.time += dt;
+        let interval = Duration::from_secs_f32(1.0 / self.pps.max(0.01));
+        if self.time < interval || !engine.can_move() {
+            return;
+        }
+        self.time = Duration::from_millis(0);
+        self.play(engine);
+    }
+
+    fn new_game(&mut self) {
+        self.time = Duration::from_millis(0);
+    }
+}
@@ -0,0 +1,281 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::engine::{Rules, BUFFER_ROWS};
+use crate::fumen::{self, Page};
+use crate::pieces::PieceSet;
+use crate::puzzle::{Goal, Puzzle};
+use crate::tbp::Piece;
+use crate::tile_map::{block_tile, tile_block, TileMap, GARBAGE_TILE};
+use crate::Vector2;
+
+// Most steps which can be undone
+const MAX_UNDO: usize = 200;
+// Goals in the order they are cycled through
+const GOALS: [Goal; 8] = [
+    Goal::Lines { lines: 1 },
+    Goal::Lines { lines: 2 },
+    Goal::Lines { lines: 3 },
+    Goal::Lines { lines: 4 },
+    Goal::PerfectClear,
+    Goal::TSpin { lines: 1 },
+    Goal::TSpin { lines: 2 },
+    Goal::TSpin { lines: 3 },
+];
+// Start of a fumen quiz comment, which keeps the piece queue like "#Q=[](T)SZ"
+const QUIZ: &str = "#Q=";
+
+/// Everything the editor changes, one of these is kept for each undo step.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// Tile map with walls, the field of the puzzle
+    pub field: TileMap,
+    /// Letters of the blocks in order
+    pub pieces: String,
+    pub hold: bool,
+    pub goal: Goal,
+}
+
+/// Board editor: cells are painted with a brush tile, and the layout is saved as a
+/// puzzle or a fumen.
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub name: String,
+    pub layout: Layout,
+    /// Pieces whose tiles can be painted, dealt by the board of the editor
+    pub pieces: PieceSet,
+    /// Tile painted on the field
+    pub brush: i16,
+    undo: Vec<Layout>,
+    redo: Vec<Layout>,
+    // Whether the running stroke has already saved an undo step
+    stroke_saved: bool,
+}
+
+impl Editor {
+    /// Editor with an empty field of the size in 'rules'
+    pub fn new(rules: &Rules) -> Self {
+        let size = Vector2::new(rules.board_width + 2, BUFFER_ROWS + rules.board_height + 1);
+        let mut field = TileMap::new(size, BUFFER_ROWS);
+        field.add_walls();
+        let layout = Layout { field, pieces: String::new(), hold: true, goal: GOALS[0] };
+        Editor { pieces: rules.pieces.clone(), ..Editor::with_layout("Untitled", layout) }
+    }
+
+    pub fn from_puzzle(puzzle: &Puzzle) -> Self {
+        let layout = Layout {
+            field: puzzle.tile_map(),
+            pieces: puzzle.pieces.clone(),
+            hold: puzzle.hold,
+            goal: puzzle.goal,
+        };
+        Editor::with_layout(&puzzle.name, layout)
+    }
+
+    /// Editor with the field of the first page of a fumen, and the queue if the page
+    /// has a quiz comment
+    pub fn from_fumen(text: &str) -> Result<Self, String> {
+        let page = fumen::decode(text)?.into_iter().next().ok_or("fumen has no pages")?;
+        let pieces = page.comment.strip_prefix(QUIZ)
+            .map(|quiz| quiz.chars().filter(|&c| Piece::from_letter(c).is_some()).collect())
+            .unwrap_or_default();
+        let layout = Layout { field: page.field, pieces, hold: true, goal: GOALS[0] };
+        Ok(Editor::with_layout("Fumen", layout))
+    }
+
+    /// Reads a puzzle file to edit
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let puzzle: Puzzle = serde_json::from_str(&json).map_err(io::Error::other)?;
+        puzzle.validate().map_err(io::Error::other)?;
+        Ok(Editor::from_puzzle(&puzzle))
+    }
+
+    fn with_layout(name: &str, layout: Layout) -> Self {
+        Editor {
+            name: name.to_string(),
+            layout,
+            pieces: PieceSet::standard(),
+            brush: GARBAGE_TILE,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            stroke_saved: false,
+        }
+    }
+
+    /// Rules of a board showing the field
+    pub fn rules(&self) -> Rules {
+        let size = self.layout.field.size;
+        Rules {
+            board_width: size.x - 2,
+            board_height: size.y - 1 - self.layout.field.hidden_rows,
+            hold: self.layout.hold,
+            pieces: self.pieces.clone(),
+            ..Rules::default()
+        }
+    }
+
+    /// Tiles which can be painted: empty, garbage and the tile of each piece
+    pub fn brushes(&self) -> Vec<i16> {
+        let mut brushes = vec![0, GARBAGE_TILE];
+        brushes.extend((0..self.pieces.len()).map(|block_type| block_tile(block_type as u8)));
+        brushes
+    }
+
+    /// Chooses the brush, false if 'tile' can't be painted
+    pub fn set_brush(&mut self, tile: i16) -> bool {
+        let paintable = self.brushes().contains(&tile);
+        if paintable {
+            self.brush = tile;
+        }
+        paintable
+    }
+
+    /// Changes the brush to the next one of `brushes`
+    pub fn next_brush(&mut self) {
+        let brushes = self.brushes();
+        let next = brushes.iter().position(|&tile| tile == self.brush).map_or(0, |i| (i + 1) % brushes.len());
+        self.brush = brushes[next];
+    }
+
+    /// Name of the brush tile
+    pub fn brush_name(&self) -> &str {
+        match (self.brush, tile_block(self.brush)) {
+            (0, _) => "empty",
+            (_, Some(block_type)) => self.pieces.name(block_type),
+            _ => "garbage",
+        }
+    }
+
+    /// Starts a stroke of the mouse, all cells painted until the next one are
+    /// undone together
+    pub fn start_stroke(&mut self) {
+        self.stroke_saved = false;
+    }
+
+    /// Sets the cell at 'position' of the field to 'tile'. Walls and cells outside
+    /// of them are left alone. Above the visible field cells can only be erased, as
+    /// puzzles don't keep them.
+    pub fn paint(&mut self, position: Vector2, tile: i16) {
+        let field = &self.layout.field;
+        let top = if tile == 0 { 0 } else { field.hidden_rows };
+        let inside = position.x > 0 && position.x < field.size.x - 1 && position.y >= top && position.y < field.size.y - 1;
+        if !inside || field._get_cellv(position) == tile {
+            return;
+        }
+        if !self.stroke_saved {
+            self.add_undo_step();
+            self.stroke_saved = true;
+        }
+        self.layout.field.set_cell(position.x, position.y, tile);
+    }
+
+    /// Adds a block to the end of the queue
+    pub fn push_piece(&mut self, piece: Piece) {
+        self.edit().pieces.push(piece.letter());
+    }
+
+    /// Removes the last block of the queue
+    pub fn pop_piece(&mut self) {
+        if !self.layout.pieces.is_empty() {
+            self.edit().pieces.pop();
+        }
+    }
+
+    pub fn toggle_hold(&mut self) {
+        let layout = self.edit();
+        layout.hold = !layout.hold;
+    }
+
+    /// Changes the goal to the next one
+    pub fn next_goal(&mut self) {
+        let next = GOALS.iter().position(|&goal| goal == self.layout.goal).map_or(0, |i| (i + 1) % GOALS.len());
+        self.edit().goal = GOALS[next];
+    }
+
+    /// Clears the field, keeping the queue and goal
+    pub fn clear_field(&mut self) {
+        let size = self.layout.field.size;
+        let field = &mut self.edit().field;
+        for y in 0..size.y - 1 {
+            for x in 1..size.x - 1 {
+                field.set_cell(x, y, 0);
+            }
+        }
+    }
+
+    /// Goes back one step, false if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(layout) => {
+                self.redo.push(std::mem::replace(&mut self.layout, layout));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Redoes the last undone step, false if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(layout) => {
+                self.undo.push(std::mem::replace(&mut self.layout, layout));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Block types of the queue
+    pub fn blocks(&self) -> Vec<u8> {
+        self.layout.pieces.chars().filter_map(Piece::from_letter).map(|piece| piece.block_type()).collect()
+    }
+
+    /// The layout as a puzzle, an error if it isn't a playable one or has cells
+    /// above the visible field, e.g. from a fumen
+    pub fn to_puzzle(&self) -> Result<Puzzle, String> {
+        let layout = &self.layout;
+        let field = &layout.field;
+        let hidden = (0..field.hidden_rows).any(|y| (1..field.size.x - 1).any(|x| field._get_cell(x, y) != 0));
+        if hidden {
+            return Err("cells above the visible field aren't saved in puzzles".to_string());
+        }
+        let puzzle = Puzzle::from_field(&self.name, &layout.field, &layout.pieces, layout.hold, layout.goal);
+        puzzle.validate()?;
+        Ok(puzzle)
+    }
+
+    /// Writes the layout as a puzzle file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let puzzle = self.to_puzzle().map_err(io::Error::other)?;
+        let json = serde_json::to_string_pretty(&puzzle).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Fumen of the field, with the queue in a quiz comment. Only 10 wide fields fit.
+    pub fn to_fumen(&self) -> Result<String, String> {
+        let mut page = Page::new(self.layout.field.clone());
+        let mut letters = self.layout.pieces.chars();
+        if let Some(first) = letters.next() {
+            page.comment = format!("{}[]({}){}", QUIZ, first, letters.as_str());
+        }
+        fumen::encode(&[page])
+    }
+
+    // Keeps the layout for undo and returns it to be changed
+    fn edit(&mut self) -> &mut Layout {
+        self.add_undo_step();
+        self.stroke_saved = false;
+        &mut self.layout
+    }
+
+    // Adds an undo step of the current layout, anything undone is lost
+    fn add_undo_step(&mut self) {
+        if self.undo.len() >= MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(self.layout.clone());
+        self.redo.clear();
+    }
+}
@@ -0,0 +1,206 @@
+use ggez::graphics::{self, Color};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+
+use rand::Rng;
+
+use crate::tile_map::{TileMap, TileSet};
+use crate::Vector2;
+
+// Particles fall with this acceleration, pixels per second squared
+const PARTICLE_GRAVITY: f32 = 900.0;
+const PARTICLES_PER_CELL: usize = 3;
+const LOCK_FLASH_TIME: f32 = 0.15;
+
+// Which effects are shown. Players who dislike motion can turn them off.
+#[derive(Debug, Clone)]
+pub struct EffectSettings {
+    pub particles: bool,
+    pub shake: bool,
+    pub lock_flash: bool,
+}
+
+impl Default for EffectSettings {
+    fn default() -> Self {
+        EffectSettings {
+            particles: true,
+            shake: true,
+            lock_flash: true,
+        }
+    }
+}
+
+struct Particle {
+    // Position and velocity in pixels relative to the top left corner of the board
+    position: (f32, f32),
+    velocity: (f32, f32),
+    color: Color,
+    // Seconds left and seconds lived in total
+    life: f32,
+    max_life: f32,
+}
+
+struct LockFlash {
+    cell: Vector2,
+    time_left: f32,
+}
+
+// Particles, board shake and lock flashes drawn with their own spritebatch
+// on top of the TileMap spritebatch
+pub struct Effects {
+    pub settings: EffectSettings,
+    particles: Vec<Particle>,
+    flashes: Vec<LockFlash>,
+    // Seconds of shake left, total length and strength in pixels
+    shake_time: f32,
+    shake_length: f32,
+    shake_strength: f32,
+    spritebatch: graphics::spritebatch::SpriteBatch,
+    // Width of the tile image in pixels, used to scale sprites
+    image_size: u16,
+}
+
+impl Effects {
+    pub fn new(ctx: &mut Context, settings: EffectSettings) -> Self {
+
+        let image = graphics::Image::new(ctx, "/element_white_square.png").unwrap();
+        let image_size = image.width();
+        let spritebatch = graphics::spritebatch::SpriteBatch::new(image);
+
+        Effects {
+            settings,
+            particles: Vec::new(),
+            flashes: Vec::new(),
+            shake_time: 0.0,
+            shake_length: 0.0,
+            shake_strength: 0.0,
+            spritebatch,
+            image_size,
+        }
+    }
+
+    // Removes all running effects, e.g. when a new game starts
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.flashes.clear();
+        self.shake_time = 0.0;
+    }
+
+    // Bursts particles from every cell of the cleared rows
+    pub fn add_line_clear(&mut self, tile_map: &TileMap, tile_set: &TileSet, rows: &[i16], cell_size: i16) {
+        if !self.settings.particles {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        let cell = cell_size as f32;
+
+        for &y in rows {
+            for x in 1..tile_map.size.x-1 {
+                let tile = tile_map._get_cell(x, y);
+                let color = tile_set.tiles[tile as usize].color;
+                let center = (
+                    (x as f32 + 0.5) * cell,
+                    ((y - tile_map.hidden_rows) as f32 + 0.5) * cell,
+                );
+                for _ in 0..PARTICLES_PER_CELL {
+                    let life = rng.gen_range(0.4, 0.9);
+                    self.particles.push(Particle {
+                        position: center,
+                        velocity: (rng.gen_range(-6.0, 6.0) * cell, rng.gen_range(-12.0, -2.0) * cell),
+                        color,
+                        life,
+                        max_life: life,
+                    });
+                }
+            }
+        }
+    }
+
+    // Shakes the board for 'length' seconds, 'strength' pixels at most
+    pub fn add_shake(&mut self, strength: f32, length: f32) {
+        if !self.settings.shake {
+            return;
+        }
+        // A stronger shake replaces a weaker one
+        if strength >= self.shake_strength * self.shake_time / self.shake_length.max(f32::EPSILON) {
+            self.shake_strength = strength;
+            self.shake_time = length;
+            self.shake_length = length;
+        }
+    }
+
+    // Flashes the cells of a block which just locked
+    pub fn add_lock_flash(&mut self, cells: &[Vector2]) {
+        if !self.settings.lock_flash {
+            return;
+        }
+        for &cell in cells {
+            self.flashes.push(LockFlash { cell, time_left: LOCK_FLASH_TIME });
+        }
+    }
+
+    // Advances the effects by 'dt' seconds
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.velocity.1 += PARTICLE_GRAVITY * dt;
+            particle.position.0 += particle.velocity.0 * dt;
+            particle.position.1 += particle.velocity.1 * dt;
+            particle.life -= dt;
+        }
+        self.particles.retain(|p| p.life > 0.0);
+
+        for flash in self.flashes.iter_mut() {
+            flash.time_left -= dt;
+        }
+        self.flashes.retain(|f| f.time_left > 0.0);
+
+        self.shake_time = (self.shake_time - dt).max(0.0);
+    }
+
+    // Pixel offset of the board caused by shaking, fades out as the shake ends
+    pub fn shake_offset(&self) -> (f32, f32) {
+        if self.shake_time <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let mut rng = rand::thread_rng();
+        let strength = self.shake_strength * self.shake_time / self.shake_length;
+        (rng.gen_range(-0.5, 0.5) * strength, rng.gen_range(0.0, 1.0) * strength)
+    }
+
+    // Draws the effects, 'offset' is the pixel position of the top left corner of
+    // the visible board
+    pub fn draw(&mut self, ctx: &mut Context, tile_map: &TileMap, cell_size: i16, offset: (f32, f32)) -> GameResult<()> {
+
+        self.spritebatch.clear();
+
+        let cell = cell_size as f32;
+        let cell_scale = cell / self.image_size as f32;
+
+        for flash in &self.flashes {
+            let alpha = flash.time_left / LOCK_FLASH_TIME;
+            let p = graphics::DrawParam::new()
+                .dest(Point2::new(flash.cell.x as f32 * cell, (flash.cell.y - tile_map.hidden_rows) as f32 * cell))
+                .scale(mint::Vector2 { x: cell_scale, y: cell_scale })
+                .color(Color::new(1.0, 1.0, 1.0, 0.7 * alpha));
+            self.spritebatch.add(p);
+        }
+
+        let particle_scale = 0.25 * cell_scale;
+        for particle in &self.particles {
+            let mut color = particle.color;
+            color.a = particle.life / particle.max_life;
+            let p = graphics::DrawParam::new()
+                .dest(Point2::new(particle.position.0, particle.position.1))
+                .scale(mint::Vector2 { x: particle_scale, y: particle_scale })
+                .color(color);
+            self.spritebatch.add(p);
+        }
+
+        let param = graphics::DrawParam::new()
+            .dest(Point2::new(offset.0, offset.1));
+        graphics::draw(ctx, &self.spritebatch, param)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,718 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::block::{Block, Rotation};
+use crate::pieces::PieceSet;
+use crate::placement;
+use crate::tile_map::TileMap;
+use crate::Vector2;
+
+// Default visible field is 10x20 cells. Above it is a hidden buffer zone where new
+// blocks spawn, and around it are the walls.
+pub const BOARD_WIDTH: i16 = 10;
+pub const BOARD_HEIGHT: i16 = 20;
+pub const BUFFER_ROWS: i16 = 20;
+// Narrowest board where every standard block fits, other sets may need a wider one
+pub const MIN_BOARD_WIDTH: i16 = 4;
+// Largest board, the tile map with walls and buffer zone is indexed by i16
+pub const MAX_BOARD_WIDTH: i16 = 100;
+pub const MAX_BOARD_HEIGHT: i16 = 100;
+// Number of upcoming blocks shown
+pub const PREVIEW_LENGTH: usize = 5;
+
+/// Rules of a single game.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Width of the field in cells, without walls
+    pub board_width: i16,
+    /// Height of the visible field in cells, without walls and buffer zone
+    pub board_height: i16,
+    /// Time between the block falling one row
+    pub gravity_interval: Duration,
+    /// How long cleared rows are shown before they collapse
+    pub line_clear_delay: Duration,
+    /// Whether blocks can be put on hold
+    pub hold: bool,
+    /// Pieces the blocks are made of
+    pub pieces: PieceSet,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            board_width: BOARD_WIDTH,
+            board_height: BOARD_HEIGHT,
+            gravity_interval: Duration::from_millis(300),
+            line_clear_delay: Duration::from_millis(400),
+            hold: true,
+            pieces: PieceSet::standard(),
+        }
+    }
+}
+
+impl Rules {
+    /// Narrowest board every piece of the set fits on
+    pub fn min_board_width(&self) -> i16 {
+        MIN_BOARD_WIDTH.max(self.pieces.width())
+    }
+
+    /// Checks that the board is wide enough for the pieces and not too large
+    pub fn validate(&self) -> Result<(), String> {
+        let min_width = self.min_board_width();
+        if !(min_width..=MAX_BOARD_WIDTH).contains(&self.board_width) {
+            return Err(format!("board width must be {} to {}", min_width, MAX_BOARD_WIDTH));
+        }
+        if !(1..=MAX_BOARD_HEIGHT).contains(&self.board_height) {
+            return Err(format!("board height must be 1 to {}", MAX_BOARD_HEIGHT));
+        }
+        Ok(())
+    }
+
+    /// Changes the board size to the nearest one which validates
+    pub fn clamp_board(&mut self) {
+        self.board_width = self.board_width.clamp(self.min_board_width(), MAX_BOARD_WIDTH);
+        self.board_height = self.board_height.clamp(1, MAX_BOARD_HEIGHT);
+    }
+}
+
+/// Ways the game can end, each reported separately on the game over screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopOut {
+    /// New block spawned overlapping the stack
+    Block,
+    /// Block locked completely above the visible field
+    Lock,
+    /// Garbage pushed the stack above the buffer zone
+    Garbage,
+    /// Fixed sequence of blocks ran out
+    OutOfBlocks,
+}
+
+impl TopOut {
+    pub fn description(&self) -> &str {
+        match self {
+            TopOut::Block   => "Block out",
+            TopOut::Lock    => "Lock out",
+            TopOut::Garbage => "Garbage out",
+            TopOut::OutOfBlocks => "Out of blocks",
+        }
+    }
+}
+
+/// Kind of T-spin a block locked with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// A lock which cleared rows.
+#[derive(Debug, Clone)]
+pub struct Clear {
+    /// Indices of the cleared rows
+    pub rows: Vec<i16>,
+    pub t_spin: TSpin,
+    /// Clears in a row before this one, 0 for the first
+    pub combo: u32,
+    /// Whether this and the previous clear were both difficult
+    pub back_to_back: bool,
+    /// Whether the clear left the board empty
+    pub perfect_clear: bool,
+}
+
+impl Clear {
+    pub fn lines(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Tetrises and T-spin clears are difficult and keep back-to-back going
+    pub fn is_difficult(&self) -> bool {
+        self.lines() >= 4 || self.t_spin != TSpin::None
+    }
+}
+
+/// Things a player can do with the block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Left,
+    Right,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+/// Things that happened during an update, for sounds and effects
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Block was dropped straight down
+    HardDrop,
+    /// Block locked, with its type and the positions of its cells
+    Locked(u8, Vec<Vector2>),
+    /// Rows started clearing
+    LinesCleared(Clear),
+}
+
+/// Game logic of a single board, without any drawing or sounds. Time only
+/// advances when `update` is called.
+pub struct Engine {
+    pub tile_map: TileMap,
+    pub block: Block,
+    // Types of the next blocks, the first one spawns next
+    preview: VecDeque<u8>,
+    // Type of the held block and whether hold was used for the current block
+    hold: Option<u8>,
+    hold_used: bool,
+    // Blocks taken from the preview so far
+    blocks_dealt: u32,
+    // Whether the preview is a fixed sequence which isn't refilled
+    fixed_sequence: bool,
+    // For new blocks and garbage holes, seeded to replay the same game
+    rng: StdRng,
+    rules: Rules,
+    pub points: u32,
+    /// Garbage rows waiting to be added under the stack when the next block
+    /// locks without clearing rows
+    pub pending_garbage: u32,
+    top_out: Option<TopOut>,
+    // Whether the last successful move of the block was a rotation, for T-spins
+    last_move_rotation: bool,
+    // Clears in a row, -1 when the last lock didn't clear
+    combo: i32,
+    // Whether the last clear was difficult, for back-to-back
+    last_clear_difficult: bool,
+    // Time since the block last fell one row
+    gravity_time: Duration,
+    // Rows being cleared and the time since the clear started
+    cleared_rows: Vec<i16>,
+    clear_time: Duration,
+    events: Vec<Event>,
+}
+
+impl Engine {
+    pub fn new(rules: Rules) -> Self {
+        Engine::with_rng(rules, StdRng::from_entropy())
+    }
+
+    /// Engine whose blocks and garbage holes are the same every time for the same seed
+    pub fn with_seed(rules: Rules, seed: u64) -> Self {
+        Engine::with_rng(rules, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rules: Rules, rng: StdRng) -> Self {
+        // Tile map has walls around the field and the buffer zone on top
+        let size = Vector2::new(rules.board_width + 2, BUFFER_ROWS + rules.board_height + 1);
+        let mut tile_map = TileMap::new(size, BUFFER_ROWS);
+        tile_map.add_walls();
+
+        let mut engine = Engine {
+            tile_map,
+            block: rules.pieces.block(Vector2::new(0, 0), 0),
+            preview: VecDeque::new(),
+            hold: None,
+            hold_used: false,
+            blocks_dealt: 0,
+            fixed_sequence: false,
+            rng,
+            rules,
+            points: 0,
+            pending_garbage: 0,
+            top_out: None,
+            last_move_rotation: false,
+            combo: -1,
+            last_clear_difficult: false,
+            gravity_time: Duration::from_millis(0),
+            cleared_rows: Vec::new(),
+            clear_time: Duration::from_millis(0),
+            events: Vec::new(),
+        };
+
+        while engine.preview.len() < PREVIEW_LENGTH {
+            let block_type = random_block_type(&mut engine.rng, &engine.rules.pieces);
+            engine.preview.push_back(block_type);
+        }
+        let first = random_block_type(&mut engine.rng, &engine.rules.pieces);
+        engine.spawn_block(first);
+        engine
+    }
+
+    pub fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+
+    /// Rows which are being cleared, so renderers can animate them. Empty when
+    /// no clear is going on.
+    pub fn cleared_rows(&self) -> &[i16] {
+        &self.cleared_rows
+    }
+
+    /// How far the running line clear is, from 0.0 to 1.0
+    pub fn clear_progress(&self) -> f32 {
+        if self.rules.line_clear_delay.as_nanos() == 0 {
+            return 1.0;
+        }
+        (self.clear_time.as_secs_f32() / self.rules.line_clear_delay.as_secs_f32()).min(1.0)
+    }
+
+    /// Types of the next blocks, the first one spawns next
+    pub fn preview(&self) -> &VecDeque<u8> {
+        &self.preview
+    }
+
+    /// Pieces the blocks are made of
+    pub fn pieces(&self) -> &PieceSet {
+        &self.rules.pieces
+    }
+
+    /// Type of the held block
+    pub fn held(&self) -> Option<u8> {
+        self.hold
+    }
+
+    /// Number of blocks taken from the preview so far. Together with the preview
+    /// length it tells how many blocks have been shown in total.
+    pub fn blocks_dealt(&self) -> u32 {
+        self.blocks_dealt
+    }
+
+    /// Clears in a row up to now, 0 when the last lock didn't clear
+    pub fn combo(&self) -> u32 {
+        (self.combo + 1) as u32
+    }
+
+    /// Whether the last clear was difficult, so the next difficult one is back-to-back
+    pub fn back_to_back(&self) -> bool {
+        self.last_clear_difficult
+    }
+
+    /// Whether the current block can still be swapped with the held one
+    pub fn can_hold(&self) -> bool {
+        // With a fixed sequence there has to be a block to swap with
+        let has_next = self.hold.is_some() || !self.preview.is_empty();
+        self.rules.hold && self.can_move() && !self.hold_used && has_next
+    }
+
+    /// Replaces the preview and the held block, e.g. to show a board played elsewhere
+    pub fn set_queue(&mut self, preview: &[u8], hold: Option<u8>) {
+        self.preview = preview.iter().copied().collect();
+        self.hold = hold;
+    }
+
+    /// The tile map without the falling block
+    pub fn field(&self) -> TileMap {
+        let mut field = self.tile_map.clone();
+        // Once locked or blocked out the block is part of the stack or not on the map at all
+        if self.top_out.is_none() && self.cleared_rows.is_empty() {
+            for cell in self.block.cells() {
+                field.set_cell(cell.x, cell.y, 0);
+            }
+        }
+        field
+    }
+
+    /// Plays 'blocks' in order instead of random ones, starting with the first one
+    /// right away. The game ends once they have all been used. Rows being cleared are
+    /// removed first.
+    pub fn play_sequence(&mut self, blocks: &[u8]) {
+        let (first, rest) = match blocks.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        // During a clear the block has already locked into the stack
+        if !self.cleared_rows.is_empty() {
+            let rows = std::mem::take(&mut self.cleared_rows);
+            self.tile_map.remove_rows(&rows);
+        } else if self.top_out.is_none() {
+            self.block._delete_from_tile_map(&mut self.tile_map);
+        }
+        self.preview = rest.iter().copied().collect();
+        self.hold = None;
+        self.fixed_sequence = true;
+        self.top_out = None;
+        self.spawn_next_block_of_type(*first);
+    }
+
+    /// Replaces the stack, e.g. with a prepared setup. The falling block stays
+    /// where it is and blocks out if it overlaps the new stack.
+    pub fn set_field(&mut self, field: TileMap) {
+        self.tile_map = field;
+        if self.top_out.is_none() {
+            if self.block.collides(&self.tile_map) {
+                self.end_game(TopOut::Block);
+            } else {
+                self.block._mark_to_tile_map(&mut self.tile_map);
+            }
+        }
+    }
+
+    /// Empties the field, e.g. for practice where every block starts on a clean field.
+    /// Rows being cleared are removed first, the falling block stays.
+    pub fn clear_field(&mut self) {
+        if !self.cleared_rows.is_empty() {
+            self.finish_clear();
+        }
+        let mut tile_map = TileMap::new(self.tile_map.size, self.tile_map.hidden_rows);
+        tile_map.add_walls();
+        self.tile_map = tile_map;
+        if self.top_out.is_none() {
+            self.block._mark_to_tile_map(&mut self.tile_map);
+        }
+    }
+
+    /// Adds garbage rows to the queue
+    pub fn receive_garbage(&mut self, rows: u32) {
+        self.pending_garbage += rows;
+    }
+
+    /// Cancels queued garbage with an attack and returns what is left of the attack
+    pub fn cancel_garbage(&mut self, attack: u32) -> u32 {
+        let cancelled = attack.min(self.pending_garbage);
+        self.pending_garbage -= cancelled;
+        attack - cancelled
+    }
+
+    /// Events since the last call
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Block can be moved when it is falling and the game is on
+    pub fn can_move(&self) -> bool {
+        self.top_out.is_none() && self.cleared_rows.is_empty() && !self.block.down
+    }
+
+    pub fn apply(&mut self, input: Input) {
+        match input {
+            Input::Left => self.move_left(),
+            Input::Right => self.move_right(),
+            Input::Rotate => self.rotate(),
+            Input::SoftDrop => self.soft_drop(),
+            Input::HardDrop => self.hard_drop(),
+            Input::Hold => self.hold(),
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.move_sideways(-1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.move_sideways(1);
+    }
+
+    fn move_sideways(&mut self, dx: i16) {
+        if !self.can_move() {
+            return;
+        }
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        if let Some(moved) = placement::moved(&self.tile_map, &self.block, dx, 0) {
+            self.block.position = moved.position;
+            self.last_move_rotation = false;
+        }
+        self.block._mark_to_tile_map(&mut self.tile_map);
+        self.block.previous_position = self.block.position;
+    }
+
+    // Moves the block down one row right away. If it can't move, it locks on the next update.
+    pub fn soft_drop(&mut self) {
+        if !self.can_move() {
+            return;
+        }
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        match placement::moved(&self.tile_map, &self.block, 0, 1) {
+            Some(moved) => {
+                self.block.position = moved.position;
+                self.last_move_rotation = false;
+                self.gravity_time = Duration::from_millis(0);
+            },
+            None => self.block.down = true,
+        }
+        self.block._mark_to_tile_map(&mut self.tile_map);
+        self.block.previous_position = self.block.position;
+    }
+
+    // Swaps the block with the held one, or puts it on hold and takes the next one.
+    // Works once per block.
+    pub fn hold(&mut self) {
+        if !self.can_hold() {
+            return;
+        }
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        let block_type = self.block.block_type;
+        let spawned = match self.hold.replace(block_type) {
+            Some(held) => self.spawn_block(held),
+            None => self.spawn_from_preview(),
+        };
+        self.hold_used = true;
+        self.last_move_rotation = false;
+        if !spawned {
+            self.end_game(TopOut::Block);
+        }
+    }
+
+    // Drops the block straight down and locks it
+    pub fn hard_drop(&mut self) {
+        if !self.can_move() {
+            return;
+        }
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        self.block.position = placement::dropped(&self.tile_map, &self.block).position;
+        if self.block.position != self.block.previous_position {
+            self.last_move_rotation = false;
+        }
+        self.block._mark_to_tile_map(&mut self.tile_map);
+        self.block.previous_position = self.block.position;
+        self.block.down = true;
+
+        self.events.push(Event::HardDrop);
+        self.lock_block();
+    }
+
+    pub fn rotate(&mut self) {
+        if !self.can_move() {
+            return;
+        }
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        self.block.previous_rotation = self.block.rotation;
+        if let Some(rotated) = placement::rotated(&self.tile_map, &self.block) {
+            self.block.position = rotated.position;
+            self.block.rotation = rotated.rotation;
+            self.last_move_rotation = true;
+        }
+        self.block._mark_to_tile_map(&mut self.tile_map);
+        self.block.previous_position = self.block.position;
+    }
+
+    /// Advances the game by 'dt'
+    pub fn update(&mut self, dt: Duration) {
+        if self.top_out.is_some() {
+            return;
+        }
+
+        // Nothing moves while cleared rows are shown
+        if !self.cleared_rows.is_empty() {
+            self.clear_time += dt;
+            if self.clear_time >= self.rules.line_clear_delay {
+                self.finish_clear();
+            }
+            return;
+        }
+
+        self.gravity_time += dt;
+        if self.gravity_time >= self.rules.gravity_interval {
+            if !self.block.down {
+                self.block.moving_down = true;
+            }
+            self.gravity_time = Duration::from_millis(0);
+        }
+
+        self.block._delete_from_tile_map(&mut self.tile_map);
+        if self.block.moving_down {
+            match placement::moved(&self.tile_map, &self.block, 0, 1) {
+                Some(moved) => {
+                    self.block.position = moved.position;
+                    self.last_move_rotation = false;
+                },
+                None => self.block.down = true,
+            }
+            self.block.moving_down = false;
+        }
+        self.block._mark_to_tile_map(&mut self.tile_map);
+        self.block.previous_position = self.block.position;
+
+        if self.block.is_down() {
+            self.lock_block();
+        }
+    }
+
+    // Ends the game. The first way it ended is kept, so e.g. a garbage out isn't
+    // reported as the block out which would follow it.
+    fn end_game(&mut self, top_out: TopOut) {
+        if self.top_out.is_none() {
+            self.top_out = Some(top_out);
+        }
+    }
+
+    // Block has landed and is now part of the stack
+    fn lock_block(&mut self) {
+        self.events.push(Event::Locked(self.block.block_type, self.block.cells()));
+
+        if self.block.is_above(self.tile_map.hidden_rows) {
+            self.end_game(TopOut::Lock);
+            return;
+        }
+
+        let t_spin = self.t_spin();
+        let rows = self.tile_map.full_rows();
+        if rows.is_empty() {
+            self.combo = -1;
+            if !self.add_pending_garbage() {
+                self.end_game(TopOut::Garbage);
+                return;
+            }
+            self.spawn_next_block();
+            return;
+        }
+
+        self.combo += 1;
+        let mut clear = Clear {
+            perfect_clear: self.is_perfect_clear(&rows),
+            rows: rows.clone(),
+            t_spin,
+            combo: self.combo as u32,
+            back_to_back: false,
+        };
+        clear.back_to_back = clear.is_difficult() && self.last_clear_difficult;
+        self.last_clear_difficult = clear.is_difficult();
+
+        self.points += rows.len() as u32;
+        self.events.push(Event::LinesCleared(clear));
+        self.cleared_rows = rows;
+        self.clear_time = Duration::from_millis(0);
+        if self.rules.line_clear_delay.as_nanos() == 0 {
+            self.finish_clear();
+        }
+    }
+
+    // T-spin by the 3-corner rule: the last move was a rotation and at least three
+    // corners around the center of the T are filled. It is a mini T-spin unless both
+    // corners on the side the T points to are filled.
+    fn t_spin(&self) -> TSpin {
+        if Some(self.block.block_type) != self.rules.pieces.t_block() || !self.last_move_rotation {
+            return TSpin::None;
+        }
+
+        let center = self.block.position + Vector2::new(2, 2);
+        let filled = |dx: i16, dy: i16| self.tile_map._get_cell(center.x + dx, center.y + dy) > 0;
+
+        let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+        let filled_corners = corners.iter().filter(|&&(dx, dy)| filled(dx, dy)).count();
+        if filled_corners < 3 {
+            return TSpin::None;
+        }
+
+        // Direction of the stem of the T
+        let (px, py) = match self.block.rotation {
+            Rotation::Cw0   => (0, 1),
+            Rotation::Cw90  => (-1, 0),
+            Rotation::Cw180 => (0, -1),
+            Rotation::Cw270 => (1, 0),
+        };
+        let front_filled = if px == 0 {
+            filled(-1, py) && filled(1, py)
+        } else {
+            filled(px, -1) && filled(px, 1)
+        };
+
+        if front_filled { TSpin::Full } else { TSpin::Mini }
+    }
+
+    // Whether the board is empty once 'rows' are removed
+    fn is_perfect_clear(&self, rows: &[i16]) -> bool {
+        for y in 0..self.tile_map.size.y-1 {
+            if rows.contains(&y) {
+                continue;
+            }
+            for x in 1..self.tile_map.size.x-1 {
+                if self.tile_map._get_cell(x, y) > 0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // Collapses the cleared rows and continues with the next block
+    fn finish_clear(&mut self) {
+        let rows = std::mem::take(&mut self.cleared_rows);
+        self.tile_map.remove_rows(&rows);
+        self.spawn_next_block();
+    }
+
+    fn spawn_next_block(&mut self) {
+        self.hold_used = false;
+        self.last_move_rotation = false;
+        if self.fixed_sequence && self.preview.is_empty() {
+            self.end_game(TopOut::OutOfBlocks);
+        } else if !self.spawn_from_preview() {
+            self.end_game(TopOut::Block);
+        }
+    }
+
+    fn spawn_next_block_of_type(&mut self, block_type: u8) {
+        self.hold_used = false;
+        self.last_move_rotation = false;
+        self.blocks_dealt += 1;
+        if !self.spawn_block(block_type) {
+            self.end_game(TopOut::Block);
+        }
+    }
+
+    // Spawns the first block of the preview and adds a new one to the end
+    fn spawn_from_preview(&mut self) -> bool {
+        let block_type = self.preview.pop_front().unwrap_or_else(|| random_block_type(&mut self.rng, &self.rules.pieces));
+        if !self.fixed_sequence {
+            let new_block_type = random_block_type(&mut self.rng, &self.rules.pieces);
+            self.preview.push_back(new_block_type);
+        }
+        self.blocks_dealt += 1;
+        self.spawn_block(block_type)
+    }
+
+    // Spawns a new block and marks it to the tile map. Returns false if the new block
+    // overlaps the stack (block out).
+    fn spawn_block(&mut self, block_type: u8) -> bool {
+        let (mut block, free) = spawn(&self.tile_map, &self.rules.pieces, block_type);
+        if free {
+            block._mark_to_tile_map(&mut self.tile_map);
+        }
+        self.block = block;
+        free
+    }
+
+    // Adds pending garbage rows under the stack, all with the hole in the same column.
+    // Returns false if the stack was pushed above the buffer zone (garbage out).
+    fn add_pending_garbage(&mut self) -> bool {
+        let hole_x = self.rng.gen_range(1, self.tile_map.size.x-1);
+        let mut pushed_out = false;
+        while self.pending_garbage > 0 {
+            self.pending_garbage -= 1;
+            if self.tile_map.add_garbage_row(hole_x) {
+                pushed_out = true;
+            }
+        }
+        !pushed_out
+    }
+}
+
+/// New block in the buffer zone just above the visible field of 'field', dropped one
+/// row if possible. The bool is false if the block overlaps the stack (block out).
+pub fn spawn(field: &TileMap, pieces: &PieceSet, block_type: u8) -> (Block, bool) {
+    let mut block = pieces.block(Vector2::new(field.spawn_column(), 0), block_type);
+    block.position.y = field.hidden_rows - 1 - block.lowest_row();
+    block.previous_position = block.position;
+
+    if block.collides(field) {
+        return (block, false);
+    }
+
+    block.position.y += 1;
+    if block.collides(field) {
+        block.position.y -= 1;
+    }
+    block.previous_position = block.position;
+    (block, true)
+}
+
+// Any piece of the set the randomizer deals, all equally likely
+fn random_block_type(rng: &mut StdRng, pieces: &PieceSet) -> u8 {
+    let types = pieces.random_types();
+    match types.len() {
+        0 => 0,
+        n => types[rng.gen_range(0, n as u8) as usize],
+    }
+}
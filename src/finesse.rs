@@ -0,0 +1,178 @@
+use rand::seq::SliceRandom;
+
+use crate::engine::{Engine, Input};
+use crate::pieces::PieceSet;
+use crate::placement::{self, Placement};
+use crate::tile_map::TileMap;
+use crate::Vector2;
+
+/// Blocks in one round of the finesse trainer
+pub const TRAINER_PIECES: u32 = 40;
+
+// Soft drops and holds don't count, gravity could do the same as a soft drop
+fn counts(input: &Input) -> bool {
+    !matches!(input, Input::SoftDrop | Input::Hold)
+}
+
+fn count(inputs: &[Input]) -> u32 {
+    inputs.iter().filter(|input| counts(input)).count() as u32
+}
+
+/// Whether the cells are the same, in any order
+pub fn same_cells(a: &[Vector2], b: &[Vector2]) -> bool {
+    let sorted = |cells: &[Vector2]| {
+        let mut cells: Vec<(i16, i16)> = cells.iter().map(|c| (c.x, c.y)).collect();
+        cells.sort_unstable();
+        cells
+    };
+    sorted(a) == sorted(b)
+}
+
+/// Fewest inputs which place a new block of 'block_type' on 'cells', None if the
+/// cells can't be reached
+pub fn fewest_inputs(field: &TileMap, pieces: &PieceSet, block_type: u8, cells: &[Vector2]) -> Option<Vec<Input>> {
+    placement::placements(field, pieces, block_type).into_iter()
+        .filter(|p| same_cells(&p.block.cells(), cells))
+        .map(|p| p.inputs)
+        .min_by_key(|inputs| count(inputs))
+}
+
+/// How one block was placed.
+#[derive(Debug, Clone)]
+pub struct Judgement {
+    pub block_type: u8,
+    pub inputs: Vec<Input>,
+    /// Fewest inputs for the same place
+    pub best: Vec<Input>,
+    /// Inputs more than the fewest
+    pub faults: u32,
+}
+
+/// Finesse of one player: the inputs of each block compared to the fewest inputs
+/// for the place it ended up in. Moves, rotations and hard drops count as inputs.
+#[derive(Debug, Clone, Default)]
+pub struct Finesse {
+    /// Judged blocks of each type of the piece set
+    pub pieces: Vec<u32>,
+    /// Faults with each block type of the piece set
+    pub faults: Vec<u32>,
+    /// The last judged block
+    pub last: Option<Judgement>,
+    // Field the current block started on, None until it can move
+    field: Option<TileMap>,
+    inputs: Vec<Input>,
+}
+
+impl Finesse {
+    pub fn total_pieces(&self) -> u32 {
+        self.pieces.iter().sum()
+    }
+
+    pub fn total_faults(&self) -> u32 {
+        self.faults.iter().sum()
+    }
+
+    /// Counted inputs of the current block so far
+    pub fn current_inputs(&self) -> u32 {
+        count(&self.inputs)
+    }
+
+    /// Remembers the field of a new block once it can be moved
+    pub fn watch(&mut self, engine: &Engine) {
+        if self.field.is_none() && engine.can_move() {
+            self.field = Some(engine.field());
+            self.inputs.clear();
+        }
+    }
+
+    /// Records an input before the engine gets it. Inputs which do nothing right now
+    /// aren't recorded, and holding starts over with the new block.
+    pub fn input(&mut self, engine: &Engine, input: Input) {
+        if !engine.can_move() {
+            return;
+        }
+        self.watch(engine);
+        if input == Input::Hold {
+            if engine.can_hold() {
+                self.field = None;
+            }
+            return;
+        }
+        self.inputs.push(input);
+    }
+
+    /// Judges the block which locked on 'cells'
+    pub fn lock(&mut self, pieces: &PieceSet, block_type: u8, cells: &[Vector2]) -> Option<&Judgement> {
+        let field = self.field.take()?;
+        let inputs = std::mem::take(&mut self.inputs);
+        let best = fewest_inputs(&field, pieces, block_type, cells)?;
+        let faults = count(&inputs).saturating_sub(count(&best));
+
+        if self.pieces.len() < pieces.len() {
+            self.pieces.resize(pieces.len(), 0);
+            self.faults.resize(pieces.len(), 0);
+        }
+        if let Some(count) = self.pieces.get_mut(block_type as usize) {
+            *count += 1;
+            self.faults[block_type as usize] += faults;
+        }
+        self.last = Some(Judgement { block_type, inputs, best, faults });
+        self.last.as_ref()
+    }
+
+    /// Faults and blocks of each block type which was placed, e.g. "I 2/10, T 0/8",
+    /// named after the pieces of 'pieces'
+    pub fn summary(&self, pieces: &PieceSet) -> String {
+        let types: Vec<String> = (0..self.pieces.len())
+            .filter(|&i| self.pieces[i] > 0)
+            .map(|i| format!("{} {}/{}", pieces.name(i as u8), self.faults[i], self.pieces[i]))
+            .collect();
+        format!("Finesse faults per block: {}", types.join(", "))
+    }
+}
+
+/// Finesse trainer: every block gets a target place on an empty field, and each
+/// placement is checked for the place and the number of inputs.
+#[derive(Debug, Clone, Default)]
+pub struct Trainer {
+    /// Where the current block should go
+    pub target: Option<Placement>,
+    /// Blocks placed, and the ones in the target place
+    pub placed: u32,
+    pub hits: u32,
+    /// Whether the last block was placed in its target
+    pub last_hit: Option<bool>,
+}
+
+impl Trainer {
+    /// Picks a target for a new block once it can be moved. Targets can be reached
+    /// without soft drops.
+    pub fn watch(&mut self, engine: &Engine) {
+        if self.target.is_some() || !engine.can_move() {
+            return;
+        }
+        let targets: Vec<Placement> = placement::placements_from(&engine.field(), engine.pieces(), &engine.block).into_iter()
+            .filter(|p| !p.inputs.contains(&Input::SoftDrop))
+            .collect();
+        self.target = targets.choose(&mut rand::thread_rng()).cloned();
+    }
+
+    /// Checks the block which locked on 'cells' against the target
+    pub fn lock(&mut self, cells: &[Vector2]) {
+        let hit = self.target.take().is_some_and(|target| same_cells(&target.block.cells(), cells));
+        self.placed += 1;
+        if hit {
+            self.hits += 1;
+        }
+        self.last_hit = Some(hit);
+    }
+
+    /// Whether 'inputs' are already more than the target needs
+    pub fn too_many(&self, inputs: u32) -> bool {
+        self.target.as_ref().is_some_and(|target| inputs > count(&target.inputs))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.placed >= TRAINER_PIECES
+    }
+}
@@ -0,0 +1,437 @@
+// Fumen v115, the format setups are shared in as strings like "v115@vhAAgH". A fumen
+// is a list of pages, each with a 10 wide field, optionally a piece placed on it and a
+// comment. Numbers are written with 64 characters as digits, lowest digit first.
+// See https://github.com/knewjade/tetris-fumen for the reference implementation.
+
+use crate::engine::{BOARD_HEIGHT, BOARD_WIDTH, BUFFER_ROWS};
+use crate::tbp::{Orientation, Piece, PieceLocation};
+use crate::tile_map::{block_tile, TileMap, GARBAGE_TILE};
+use crate::Vector2;
+
+const PREFIX: &str = "115@";
+const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// Characters of comments, a comment digit has 96 values
+const COMMENT_CHARS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = 96;
+const MAX_COMMENT_LENGTH: usize = 4095;
+
+const WIDTH: usize = 10;
+// 23 rows of field and the row under it, from which garbage rises
+const HEIGHT: usize = 24;
+const CELLS: u32 = (WIDTH * HEIGHT) as u32;
+// The only run of a page which repeats the previous field, followed by a counter of
+// further pages repeating it
+const NO_CHANGE: u32 = 8 * CELLS + CELLS - 1;
+
+// Cell values of fumen
+const EMPTY: u8 = 0;
+const GRAY: u8 = 8;
+
+// Field rows from the bottom, the first one is the garbage row under the field
+type Field = [[u8; WIDTH]; HEIGHT];
+
+/// One page of a fumen.
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// Field before the piece is placed, as tiles of a 10 wide board
+    pub field: TileMap,
+    /// Piece placed on the field
+    pub piece: Option<PieceLocation>,
+    /// Comment, kept on the following pages until it changes
+    pub comment: String,
+    /// Whether the piece is locked and full rows are cleared for the next page
+    pub lock: bool,
+    /// Whether the garbage row rises into the field for the next page
+    pub rise: bool,
+    /// Whether the field is mirrored for the next page
+    pub mirror: bool,
+    /// Whether the pieces are shown in guideline colors
+    pub colorize: bool,
+}
+
+impl Page {
+    /// Page showing 'field', with the piece locking
+    pub fn new(field: TileMap) -> Self {
+        Page {
+            field,
+            piece: None,
+            comment: String::new(),
+            lock: true,
+            rise: false,
+            mirror: false,
+            colorize: true,
+        }
+    }
+
+    /// The field with the piece of the page on it, as the page is shown
+    pub fn placed_field(&self) -> TileMap {
+        let mut field = self.field.clone();
+        if let Some(piece) = self.piece {
+            let bottom = field.size.y - 2;
+            for (x, y) in piece.cells() {
+                if (0..WIDTH as i16).contains(&x) && (0..=bottom).contains(&y) {
+                    field.set_cell(x + 1, bottom - y, block_tile(piece.piece.block_type()));
+                }
+            }
+        }
+        field
+    }
+}
+
+/// Fumen string of the pages. Fields must be 10 cells wide, rows above the 23rd are left out.
+pub fn encode(pages: &[Page]) -> Result<String, String> {
+    let mut values = Vec::new();
+    let mut previous = [[EMPTY; WIDTH]; HEIGHT];
+    let mut previous_comment = String::new();
+    // Index of the counter of pages repeating the previous field
+    let mut repeat: Option<usize> = None;
+
+    for (i, page) in pages.iter().enumerate() {
+        let field = from_tile_map(&page.field)?;
+
+        // Runs of cells with the same change from the previous field
+        let changes = |x: usize, row: usize| field[row][x] as i32 - previous[row][x] as i32 + 8;
+        let mut runs = Vec::new();
+        let mut change = changes(0, HEIGHT - 1);
+        let mut length = 0;
+        for index in 1..CELLS as usize {
+            let (x, row) = (index % WIDTH, HEIGHT - 1 - index / WIDTH);
+            if changes(x, row) != change {
+                runs.push(change as u32 * CELLS + length);
+                change = changes(x, row);
+                length = 0;
+            } else {
+                length += 1;
+            }
+        }
+        runs.push(change as u32 * CELLS + length);
+
+        let unchanged = runs == [NO_CHANGE];
+        match repeat {
+            Some(counter) if unchanged && values[counter] < 63 => values[counter] += 1,
+            _ => {
+                for run in runs.iter() {
+                    push(&mut values, *run, 2);
+                }
+                repeat = if unchanged {
+                    values.push(0);
+                    Some(values.len() - 1)
+                } else {
+                    None
+                };
+            },
+        }
+
+        let comment = escape(&page.comment);
+        let comment_changed = page.comment != previous_comment;
+        let colorize = i == 0 && page.colorize;
+        push(&mut values, encode_action(page, comment_changed, colorize), 3);
+
+        if comment_changed {
+            let chars: Vec<u32> = comment.chars().take(MAX_COMMENT_LENGTH)
+                .map(|c| COMMENT_CHARS.find(c).unwrap_or(0) as u32)
+                .collect();
+            push(&mut values, chars.len() as u32, 2);
+            for chunk in chars.chunks(4) {
+                let value = chunk.iter().rev().fold(0, |value, &c| value * COMMENT_BASE + c);
+                push(&mut values, value, 5);
+            }
+            previous_comment = page.comment.clone();
+        }
+
+        previous = next_field(field, page);
+    }
+
+    // The reference implementation puts a '?' after the first 42 characters and then
+    // after every 47
+    let data: String = values.iter().map(|&v| DIGITS[v as usize] as char).collect();
+    let mut text = format!("v{}", PREFIX);
+    for (i, c) in data.chars().enumerate() {
+        if i >= 42 && (i - 42) % 47 == 0 {
+            text.push('?');
+        }
+        text.push(c);
+    }
+    Ok(text)
+}
+
+/// Pages of a fumen string. Anything before the version, like the address of a
+/// fumen site, is skipped.
+pub fn decode(text: &str) -> Result<Vec<Page>, String> {
+    let start = text.find(PREFIX).ok_or("not a v115 fumen")?;
+    let mut values = Vec::new();
+    for c in text[start + PREFIX.len()..].chars() {
+        match DIGITS.iter().position(|&d| d as char == c) {
+            Some(value) => values.push(value as u32),
+            None if c == '?' => {},
+            None if c.is_whitespace() => break,
+            None => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    let mut values = values.into_iter().peekable();
+
+    let mut pages = Vec::new();
+    let mut field = [[EMPTY; WIDTH]; HEIGHT];
+    let mut comment = String::new();
+    let mut repeat = 0;
+    loop {
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            while index < CELLS {
+                let run = poll(&mut values, 2)?;
+                let (change, length) = (run / CELLS, run % CELLS + 1);
+                if run == NO_CHANGE {
+                    repeat = poll(&mut values, 1)?;
+                }
+                for _ in 0..length.min(CELLS - index) {
+                    let (x, row) = (index as usize % WIDTH, HEIGHT - 1 - index as usize / WIDTH);
+                    field[row][x] = (field[row][x] as i32 + change as i32 - 8).clamp(0, GRAY as i32) as u8;
+                    index += 1;
+                }
+            }
+        }
+
+        let action = poll(&mut values, 3)?;
+        let fumen_piece = (action % 8) as u8;
+        let rotation = action / 8 % 4;
+        let position = action / 32 % CELLS;
+        let flags = action / 32 / CELLS;
+        let flag = |bit: u32| flags >> bit & 1 == 1;
+
+        if flag(3) {
+            let length = poll(&mut values, 2)? as usize;
+            let mut chars = String::new();
+            while chars.len() < length {
+                let mut value = poll(&mut values, 5)?;
+                for _ in 0..4 {
+                    if chars.len() < length {
+                        chars.push(COMMENT_CHARS.chars().nth((value % COMMENT_BASE) as usize).unwrap_or(' '));
+                    }
+                    value /= COMMENT_BASE;
+                }
+            }
+            comment = unescape(&chars);
+        }
+
+        let page = Page {
+            field: to_tile_map(&field),
+            piece: decode_piece(fumen_piece, rotation, position),
+            comment: comment.clone(),
+            lock: !flag(4),
+            rise: flag(0),
+            mirror: flag(1),
+            colorize: flag(2),
+        };
+        field = next_field(field, &page);
+        pages.push(page);
+
+        // Pages go on as long as there is data
+        if values.peek().is_none() {
+            break;
+        }
+    }
+    Ok(pages)
+}
+
+// Takes a number of 'digits' digits, lowest first
+fn poll(values: &mut impl Iterator<Item = u32>, digits: u32) -> Result<u32, String> {
+    let mut value = 0;
+    for i in 0..digits {
+        value += values.next().ok_or("fumen ends too early")? * 64u32.pow(i);
+    }
+    Ok(value)
+}
+
+// Adds 'value' as 'digits' digits, lowest first
+fn push(values: &mut Vec<u32>, mut value: u32, digits: u32) {
+    for _ in 0..digits {
+        values.push(value % 64);
+        value /= 64;
+    }
+}
+
+// Field of the next page: the piece locked, full rows cleared, garbage risen and mirrored
+fn next_field(mut field: Field, page: &Page) -> Field {
+    if !page.lock {
+        return field;
+    }
+    if let Some(piece) = page.piece {
+        for (x, y) in piece.cells() {
+            if (0..WIDTH as i16).contains(&x) && (0..HEIGHT as i16 - 1).contains(&y) {
+                field[y as usize + 1][x as usize] = fumen_color(piece.piece);
+            }
+        }
+    }
+
+    let mut rows: Vec<[u8; WIDTH]> = field[1..].iter()
+        .filter(|row| row.contains(&EMPTY))
+        .copied()
+        .collect();
+    rows.resize(HEIGHT - 1, [EMPTY; WIDTH]);
+    field[1..].copy_from_slice(&rows);
+
+    if page.rise {
+        field.copy_within(0..HEIGHT - 1, 1);
+        field[0] = [EMPTY; WIDTH];
+    }
+    if page.mirror {
+        for row in field[1..].iter_mut() {
+            row.reverse();
+        }
+    }
+    field
+}
+
+fn fumen_color(piece: Piece) -> u8 {
+    match piece {
+        Piece::I => 1,
+        Piece::L => 2,
+        Piece::O => 3,
+        Piece::Z => 4,
+        Piece::T => 5,
+        Piece::J => 6,
+        Piece::S => 7,
+    }
+}
+
+fn fumen_piece(color: u8) -> Option<Piece> {
+    [Piece::I, Piece::L, Piece::O, Piece::Z, Piece::T, Piece::J, Piece::S].iter()
+        .copied()
+        .find(|&piece| fumen_color(piece) == color)
+}
+
+// Fumen keeps the position of some pieces one cell off from their rotation center,
+// this is the rotation center minus the fumen position
+fn center_offset(piece: Piece, orientation: Orientation) -> (i16, i16) {
+    match (piece, orientation) {
+        (Piece::O, Orientation::West) => (1, -1),
+        (Piece::O, Orientation::South) => (1, 0),
+        (Piece::O, Orientation::North) => (0, -1),
+        (Piece::I, Orientation::South) => (1, 0),
+        (Piece::I, Orientation::West) => (0, -1),
+        (Piece::S, Orientation::North) => (0, -1),
+        (Piece::S, Orientation::East) => (-1, 0),
+        (Piece::Z, Orientation::North) => (0, -1),
+        (Piece::Z, Orientation::West) => (1, 0),
+        _ => (0, 0),
+    }
+}
+
+fn encode_action(page: &Page, comment: bool, colorize: bool) -> u32 {
+    let (color, rotation, position) = match page.piece {
+        Some(piece) => {
+            let rotation = match piece.orientation {
+                Orientation::South => 0,
+                Orientation::East => 1,
+                Orientation::North => 2,
+                Orientation::West => 3,
+            };
+            let (dx, dy) = center_offset(piece.piece, piece.orientation);
+            let (x, y) = (piece.x - dx, piece.y - dy);
+            (fumen_color(piece.piece) as u32, rotation, ((22 - y) * WIDTH as i16 + x).max(0) as u32)
+        },
+        None => (0, 0, 0),
+    };
+    let flags = [!page.lock, comment, colorize, page.mirror, page.rise].iter()
+        .fold(0, |flags, &flag| flags * 2 + flag as u32);
+    ((flags * CELLS + position) * 4 + rotation) * 8 + color
+}
+
+fn decode_piece(color: u8, rotation: u32, position: u32) -> Option<PieceLocation> {
+    let piece = fumen_piece(color)?;
+    let orientation = match rotation {
+        0 => Orientation::South,
+        1 => Orientation::East,
+        2 => Orientation::North,
+        _ => Orientation::West,
+    };
+    let (dx, dy) = center_offset(piece, orientation);
+    let x = (position % WIDTH as u32) as i16 + dx;
+    let y = 22 - (position / WIDTH as u32) as i16 + dy;
+    Some(PieceLocation { piece, orientation, x, y })
+}
+
+// Fumen colors of a tile map, row 0 of the field being the bottom row above the wall
+fn from_tile_map(tile_map: &TileMap) -> Result<Field, String> {
+    if tile_map.size.x != WIDTH as i16 + 2 {
+        return Err(format!("fumen fields are {} cells wide", WIDTH));
+    }
+    let mut field = [[EMPTY; WIDTH]; HEIGHT];
+    for (y, row) in field.iter_mut().skip(1).enumerate() {
+        let tile_y = tile_map.size.y - 2 - y as i16;
+        if tile_y < 0 {
+            break;
+        }
+        for (x, cell) in row.iter_mut().enumerate() {
+            *cell = match tile_map._get_cell(x as i16 + 1, tile_y) {
+                0 => EMPTY,
+                tile @ 1..=7 => fumen_color(Piece::from_block_type(tile as u8 - 1)),
+                _ => GRAY,
+            };
+        }
+    }
+    Ok(field)
+}
+
+// Tile map of the default board size with the field, the garbage row left out
+fn to_tile_map(field: &Field) -> TileMap {
+    let size = Vector2::new(BOARD_WIDTH + 2, BUFFER_ROWS + BOARD_HEIGHT + 1);
+    let mut tile_map = TileMap::new(size, BUFFER_ROWS);
+    tile_map.add_walls();
+    for (y, row) in field.iter().skip(1).enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            let tile = match fumen_piece(cell) {
+                Some(piece) => block_tile(piece.block_type()),
+                None if cell == EMPTY => continue,
+                None => GARBAGE_TILE,
+            };
+            tile_map.set_cell(x as i16 + 1, size.y - 2 - y as i16, tile);
+        }
+    }
+    tile_map
+}
+
+// Comments are stored with JavaScript's escape(), so other characters than
+// letters, digits and @*_+-./ become %XX or %uXXXX
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+            escaped.push(c);
+        } else if (c as u32) < 256 {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("%u{:04X}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut units: Vec<u16> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let hex = |digits: &str| u16::from_str_radix(digits, 16).ok();
+        let (unit, length) = if rest.starts_with("%u") {
+            match rest.get(2..6).and_then(hex) {
+                Some(unit) => (unit, 6),
+                None => (c as u16, 1),
+            }
+        } else if c == '%' {
+            match rest.get(1..3).and_then(hex) {
+                Some(unit) => (unit, 3),
+                None => (c as u16, 1),
+            }
+        } else {
+            (c as u16, c.len_utf8())
+        };
+        units.push(unit);
+        rest = &rest[length..];
+    }
+    String::from_utf16_lossy(&units)
+}
@@ -0,0 +1,32 @@
+// Game logic, drawing and networking of jetris. The engine doesn't need a window,
+// so the same code runs in the game, the server and tests.
+
+pub mod block;
+pub mod bot;
+pub mod board;
+pub mod editor;
+pub mod effects;
+pub mod engine;
+pub mod finesse;
+pub mod fumen;
+pub mod net;
+pub mod opener;
+pub mod pieces;
+pub mod placement;
+pub mod puzzle;
+pub mod renderer;
+pub mod screen;
+pub mod sim;
+pub mod snapshot;
+pub mod stats;
+pub mod tbp;
+pub mod theme;
+pub mod tile_map;
+pub mod tui;
+pub mod tune;
+pub mod versus;
+
+use ggez::nalgebra;
+
+// Making often used name easier
+pub type Vector2 = nalgebra::Vector2<i16>;
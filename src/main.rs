@@ -1,20 +1,46 @@
-use ggez;
 use ggez::audio::{self, SoundSource};
-use ggez::event::{self, KeyCode, KeyMods};
-use ggez::graphics::{self, Color, Scale, TextFragment};
-use ggez::nalgebra::{self, Point2};
+use ggez::event::{self, KeyCode, KeyMods, MouseButton};
+use ggez::input::mouse;
+use ggez::conf::FullscreenType;
+use ggez::graphics;
 use ggez::{Context, GameResult};
 
 use std::env;
 use std::path;
 use std::time::{Duration, Instant};
-use array2d::Array2D;
 
-use rand::Rng;
+use jetris::board::{Board, KeyBindings};
+use jetris::bot::{Bot, Controller, Weights};
+use jetris::editor::Editor;
+use jetris::effects::EffectSettings;
+use jetris::engine::{Event, Input, Rules, TopOut};
+use jetris::finesse::{Trainer, TRAINER_PIECES};
+use jetris::net::client::Client;
+use jetris::net::protocol::{BoardState, ClientMessage, ServerMessage};
+use jetris::opener::{Opener, Practice};
+use jetris::pieces::PieceSet;
+use jetris::puzzle::{self, Progress, Puzzle};
+use jetris::screen::{Screen, DEFAULT_SCREEN_SIZE, MIN_SCREEN_SIZE};
+use jetris::stats::Stat;
+use jetris::tbp::{ExternalBot, Piece};
+use jetris::theme::{self, Palette, Skin, Theme, THEMES_DIR};
+use jetris::tile_map::GARBAGE_TILE;
+use jetris::versus;
 
 // If on shows some debug texts
 const DEBUG_ON: bool = false;
 
+// How long GO is shown after the countdown, while the game is already on
+const GO_TIME: Duration = Duration::from_millis(600);
+
+// Puzzle file the editor saves to when no file is given
+const EDITOR_FILE: &str = "puzzle.json";
+
+// How often the own board is sent to the server in network games
+const BOARD_SEND_INTERVAL: Duration = Duration::from_millis(50);
+// How long to wait between attempts to connect to the server
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
 // Returns variable name and value in string for debugging
 /*macro_rules! debug2 {
     (x => $e:expr) => {
@@ -22,425 +48,495 @@ const DEBUG_ON: bool = false;
     };
 }*/
 
-const START_X: i16 = 4;
-const START_Y: i16 = 2;
-
-const TILEMAP_SIZE_X: i16 = 12;
-const TILEMAP_SIZE_Y: i16 = 30;
-const CELL_SIZE: i16 = 32;
-
-// Making often used name easier
-type Vector2 = nalgebra::Vector2<i16>;
-
-#[derive(Debug, Clone, Copy)]
-enum Rotation {
-    Cw0,
-    Cw90,
-    Cw180,
-    Cw270,
-}
-
-impl Rotation {
-    pub fn next(&self) -> Rotation {
-        match self {
-            Rotation::Cw0   => Rotation::Cw90,
-            Rotation::Cw90  => Rotation::Cw180,
-            Rotation::Cw180 => Rotation::Cw270,
-            Rotation::Cw270 => Rotation::Cw0,
-        }
-    }
-}
-
 // Game states
 enum GameStates {
-    GameOver,
+    Countdown,
+    GameOver(TopOut),
+    // Versus game ended, with the winning board or None for a draw
+    VersusOver(Option<usize>),
     GameOn,
     Pause,
     Restart,
+    // Network game waiting for the server or the opponent, with the reason shown
+    Waiting(String),
+    // Watching a network game
+    Spectating,
+    // Round of the finesse trainer finished
+    TrainerOver,
+    // Puzzle ended, with whether it was solved
+    PuzzleOver(bool),
+    // Painting a board in the editor
+    Editing,
 }
 
-#[derive(Debug, Clone)]
-pub struct Tile{
-    id: u16,
-    color: graphics::Color,
+/// Game settings which can be changed without touching the game logic.
+pub struct Settings {
+    /// Rules for new games
+    pub rules: Rules,
+    /// Length of the Ready-Go countdown in seconds, 0 turns the countdown off
+    pub countdown_seconds: u64,
+    /// Whether the board is hidden while the game is paused
+    pub hide_board_on_pause: bool,
+    /// Particles, board shake and lock flashes
+    pub effects: EffectSettings,
+    /// Two players on the same keyboard, sending garbage to each other
+    pub versus: bool,
+    /// Versus against the computer, which plays the right board
+    pub bot: bool,
+    /// How fast the computer plays, in pieces per second
+    pub bot_pps: f32,
+    /// Command line of an external bot speaking the Tetris Bot Protocol, used
+    /// instead of the built-in one
+    pub tbp_command: Option<String>,
+    /// Server address for playing versus over the network, e.g. `localhost:7878`
+    pub connect: Option<String>,
+    /// Server address for watching a network game
+    pub spectate: Option<String>,
+    /// Player name shown to the opponent in network games
+    pub name: String,
+    /// Practice finesse on an empty field, placing blocks on target places
+    pub finesse_trainer: bool,
+    /// Lines of the statistics panel next to each board, toggled with Tab
+    pub stats: Vec<Stat>,
+    /// Puzzle file or directory of puzzle files to play instead of a normal game
+    pub puzzle: Option<path::PathBuf>,
+    /// Opener to practice, a file or the name of one in the `openers` directory
+    pub opener: Option<String>,
+    /// Puzzle file to edit, loaded if it exists and written on save
+    pub edit: Option<path::PathBuf>,
+    /// Fumen to start the editor with
+    pub fumen: Option<String>,
+    /// Theme, a directory or the name of one in the `themes` directory
+    pub theme: Option<String>,
+    /// Piece colors used instead of the theme's, e.g. for color blindness
+    pub palette: Palette,
+    /// Whether each piece has its own pattern on its tiles
+    pub patterns: bool,
+    /// Fullscreen used at start when not windowed, and by F11: a borderless window
+    /// over the desktop or a real fullscreen mode
+    pub fullscreen: FullscreenType,
+    /// Whether the game starts in fullscreen
+    pub start_fullscreen: bool,
 }
 
-impl Tile {
-    pub fn new(id: u16, color: graphics::Color) -> Self {
-        Tile { id, color }
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            rules: Rules::default(),
+            countdown_seconds: 3,
+            hide_board_on_pause: true,
+            effects: EffectSettings::default(),
+            versus: false,
+            bot: false,
+            bot_pps: 1.5,
+            tbp_command: None,
+            connect: None,
+            spectate: None,
+            name: "Player".to_string(),
+            finesse_trainer: false,
+            stats: Stat::ALL.to_vec(),
+            puzzle: None,
+            opener: None,
+            edit: None,
+            fumen: None,
+            theme: None,
+            palette: Palette::Theme,
+            patterns: false,
+            fullscreen: FullscreenType::Desktop,
+            start_fullscreen: false,
+        }
     }
 }
 
-pub struct TileSet {
-    tiles: Vec<Tile>,
-}
-
-impl TileSet {
-    pub fn new() -> Self {
-        TileSet {
-            tiles: Vec::new(),
+impl Settings {
+    /// Reads settings from command line arguments, e.g. `--width 4 --height 40`,
+    /// `--clear-delay 0`, `--countdown 0`, `--show-board-on-pause`, `--no-shake`,
+    /// `--stats time,pps,apm`, `--puzzle puzzles`, `--opener tki`, `--pieces pieces/pentominoes.json`, `--edit puzzle.json`,
+    /// `--fumen v115@vhAAgH`, `--theme midnight`, `--palette deuteranopia`, `--patterns`,
+    /// `--fullscreen`, `--borderless` or `--connect localhost:7878`.
+    /// Missing or invalid values fall back to defaults.
+    pub fn from_args() -> Self {
+        let mut settings = Settings::default();
+        let args: Vec<String> = env::args().collect();
+
+        for arg in &args {
+            match arg.as_str() {
+                "--no-particles" => settings.effects.particles = false,
+                "--no-shake" => settings.effects.shake = false,
+                "--no-flash" => settings.effects.lock_flash = false,
+                "--versus" => settings.versus = true,
+                "--finesse-trainer" => settings.finesse_trainer = true,
+                "--no-stats" => settings.stats.clear(),
+                "--patterns" => settings.patterns = true,
+                "--show-board-on-pause" => settings.hide_board_on_pause = false,
+                "--fullscreen" => {
+                    settings.fullscreen = FullscreenType::True;
+                    settings.start_fullscreen = true;
+                },
+                "--borderless" => {
+                    settings.fullscreen = FullscreenType::Desktop;
+                    settings.start_fullscreen = true;
+                },
+                "--bot" => {
+                    settings.versus = true;
+                    settings.bot = true;
+                },
+                _ => {},
+            };
         }
-    }
 
-    pub fn add_tile(&mut self, tile: Tile) {
-        self.tiles.push(tile);
-    }
-}
+        for pair in args.windows(2) {
+            match pair[0].as_str() {
+                "--connect" => settings.connect = Some(pair[1].clone()),
+                "--spectate" => settings.spectate = Some(pair[1].clone()),
+                "--bot-pps" => settings.bot_pps = pair[1].parse().unwrap_or(settings.bot_pps),
+                "--tbp" => {
+                    settings.versus = true;
+                    settings.bot = true;
+                    settings.tbp_command = Some(pair[1].clone());
+                },
+                "--name" => settings.name = pair[1].clone(),
+                "--countdown" => settings.countdown_seconds = pair[1].parse().unwrap_or(settings.countdown_seconds),
+                "--stats" => settings.stats = pair[1].split(',').filter_map(Stat::from_name).collect(),
+                "--puzzle" => settings.puzzle = Some(path::PathBuf::from(&pair[1])),
+                "--opener" => settings.opener = Some(pair[1].clone()),
+                "--edit" => settings.edit = Some(path::PathBuf::from(&pair[1])),
+                "--fumen" => settings.fumen = Some(pair[1].clone()),
+                "--theme" => settings.theme = Some(pair[1].clone()),
+                "--palette" => settings.palette = Palette::from_name(&pair[1]).unwrap_or(settings.palette),
+                "--pieces" => match PieceSet::load(path::Path::new(&pair[1])) {
+                    Ok(pieces) => settings.rules.pieces = pieces,
+                    Err(e) => eprintln!("Could not load pieces {}: {}", pair[1], e),
+                },
+                _ => {},
+            };
 
-// Screen resolution / window size
-pub struct Screen {
-    pub size: Vector2,
-    pub center: Vector2,
-}
+            let value = pair[1].parse::<i16>().ok();
+            match (pair[0].as_str(), value) {
+                ("--width", Some(width)) => settings.rules.board_width = width,
+                ("--height", Some(height)) => settings.rules.board_height = height,
+                ("--clear-delay", Some(millis)) => settings.rules.line_clear_delay = Duration::from_millis(millis.max(0) as u64),
+                _ => {},
+            };
+        }
 
-impl Screen {
-    fn get_size() -> Vector2 {
-        return Vector2::new(1800, 1000);
-    }
+        if let Err(e) = settings.rules.validate() {
+            settings.rules.clamp_board();
+            eprintln!("Invalid board size, {}. Playing on {}x{}", e, settings.rules.board_width, settings.rules.board_height);
+        }
 
-    fn get_center() -> Vector2 {
-        return Vector2::new(900, 500);
+        settings
     }
 
-}
-
-// TileMap
-struct TileMap {
-    size: Vector2,
-    cell_size: i16,
-    tile_set: TileSet,
-    // 2d array of U16 representing tiles/cells
-    array : array2d::Array2D<i16>,
-    spritebatch: graphics::spritebatch::SpriteBatch,
-}
-
-impl TileMap {
-    pub fn new(ctx: &mut Context, size: Vector2, cell_size: i16, tile_set: TileSet) -> Self {
-
-        let array = Array2D::filled_with(0, size.x as usize, size.y as usize);
-
-        let image = graphics::Image::new(ctx, "/element_white_square.png").unwrap();
-        let spritebatch = graphics::spritebatch::SpriteBatch::new(image);
-
-        TileMap {
-            size,
-            cell_size,
-            tile_set,
-            array,
-            spritebatch,
+    // External bot if one was given and it starts, otherwise the built-in one
+    fn launch_bot(&self) -> Box<dyn Controller> {
+        if let Some(command) = self.tbp_command.as_ref() {
+            let mut words = command.split_whitespace().map(String::from);
+            let program = words.next().unwrap_or_default();
+            let args: Vec<String> = words.collect();
+            match ExternalBot::launch(&program, &args, self.bot_pps) {
+                Ok(bot) => return Box::new(bot),
+                Err(e) => eprintln!("Could not start bot '{}': {}", command, e),
+            }
         }
+        Box::new(Bot::new(Weights::default(), self.bot_pps))
     }
 
-    fn get_center(&self) -> Vector2 {
-        return Vector2::new((0.5 * self.size.x as f32) as i16, (0.5 * self.size.y as f32) as i16);
-    }
-
-    fn _get_cell(&self, x: i16, y: i16) -> i16 {
-        if x < 0 || x >= self.size.x || y < 0 || y >= self.size.y {
-            1
-        }
-        else {
-            self.array[(x as usize, y as usize)]
+    // Shown until a network game starts
+    fn waiting_text(&self) -> String {
+        match self.spectate {
+            Some(_) => "Waiting for a match to watch".to_string(),
+            None => "Waiting for opponent".to_string(),
         }
     }
 
-    fn _get_cellv(&self, position: Vector2) -> i16 {
-        return self._get_cell(position.x, position.y);
+    // Opener to practice, None if it can't be loaded
+    fn load_opener(&self) -> Option<Opener> {
+        let name = self.opener.as_ref()?;
+        let file = path::Path::new(name);
+        let file = match file.is_file() {
+            true => file.to_path_buf(),
+            false => path::Path::new("openers").join(format!("{}.json", name)),
+        };
+        match Opener::load(&file) {
+            Ok(opener) => Some(opener),
+            Err(e) => {
+                eprintln!("Could not load opener {}: {}", file.display(), e);
+                None
+            },
+        }
     }
 
-    fn set_cell(&mut self, x: i16, y: i16, tile: i16) {
-        self.array[(x as usize, y as usize)] = tile;
+    // Theme given with its images and font, the built-in one if there is none or it
+    // can't be loaded
+    fn load_skin(&self, ctx: &mut Context) -> Skin {
+        if let Some(name) = self.theme.as_ref() {
+            let dir = Theme::find(name);
+            match load_skin(ctx, Some(&dir), self.palette) {
+                Ok(skin) => return skin,
+                Err(e) => eprintln!("Could not load theme {}: {}", dir.display(), e),
+            }
+        }
+        load_skin(ctx, None, self.palette).expect("built-in theme")
     }
 
-    fn get_pixel_center(&self) -> Vector2 {
-       return self.cell_size * self.get_center();
+    // Editor for the puzzle file or fumen given, None if nothing is to be edited
+    fn load_editor(&self) -> Option<EditorState> {
+        if self.edit.is_none() && self.fumen.is_none() {
+            return None;
+        }
+        let path = self.edit.clone().unwrap_or_else(|| path::PathBuf::from(EDITOR_FILE));
+        let loaded = match (&self.fumen, path.is_file()) {
+            (Some(fumen), _) => Editor::from_fumen(fumen).map_err(|e| format!("Could not read fumen: {}", e)),
+            (None, true) => Editor::load(&path).map_err(|e| format!("Could not load {}: {}", path.display(), e)),
+            (None, false) => Ok(Editor::new(&self.rules)),
+        };
+        let (editor, message) = match loaded {
+            Ok(editor) => (editor, String::new()),
+            Err(e) => (Editor::new(&self.rules), e),
+        };
+        Some(EditorState { editor, path, message })
     }
 
-    // TileMap pixel offset
-    fn get_offset(&self) -> Vector2 {
-        return Screen::get_center() - self.get_pixel_center();
+    // Server address when playing or watching over the network
+    fn network_address(&self) -> Option<&String> {
+        self.connect.as_ref().or(self.spectate.as_ref())
     }
+}
 
-    fn clear_center(&mut self) {
-
-        self.spritebatch.clear();
+// Connection to the versus server. When playing, the own board is always the first
+// board and the opponent's board the second. Spectators see the boards in player order.
+struct Network {
+    client: Client,
+    // Stops reconnecting when the server refused us
+    rejected: bool,
+    last_connect: Instant,
+    last_board_sent: Instant,
+}
 
-        for x in 1..self.size.x-1 {
-            for y in 0..self.size.y-1 {
-                self.array[(x as usize, y as usize)] = 0;
-            }
+impl Network {
+    fn new(address: &str, name: &str, spectator: bool) -> Self {
+        let mut client = match spectator {
+            true => Client::spectator(address, name),
+            false => Client::new(address, name),
+        };
+        let _ = client.connect();
+        Network {
+            client,
+            rejected: false,
+            last_connect: Instant::now(),
+            last_board_sent: Instant::now(),
         }
     }
+}
 
-    fn update_spritebatches(&mut self) {
-
-        self.spritebatch.clear();
+// Puzzles being played, in file name order
+struct Puzzles {
+    list: Vec<(String, Puzzle)>,
+    current: usize,
+    progress: Progress,
+    // Lines cleared in the current attempt
+    lines: u32,
+}
 
-        for ix in 0..self.size.x {
-            for iy in 0..self.size.y {
-                let fx = ix as f32;
-                let fy = iy as f32;
-                let tile = self.array[(ix as usize,iy as usize)];
-                let p = graphics::DrawParam::new()
-                    .dest(Point2::new(fx * self.cell_size as f32, fy * self.cell_size as f32))
-                    .color(self.tile_set.tiles[tile as usize].color);
-                self.spritebatch.add(p);
-            }
+impl Puzzles {
+    // Puzzles at 'path' starting from the first unsolved one, None if there are none.
+    // Puzzles which can't be loaded are reported and skipped.
+    fn load(path: &path::Path) -> Option<Self> {
+        let loaded = match puzzle::load(path) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Could not load puzzles from {}: {}", path.display(), e);
+                return None;
+            },
+        };
+        for error in loaded.errors {
+            eprintln!("Skipping puzzle {}", error);
         }
-
+        let list = loaded.puzzles;
+        if list.is_empty() {
+            eprintln!("No puzzles in {}", path.display());
+            return None;
+        }
+        let progress = Progress::load(path);
+        let current = list.iter().position(|(id, _)| !progress.is_solved(id)).unwrap_or(0);
+        Some(Puzzles { list, current, progress, lines: 0 })
     }
-    
-    // Add images to spritebatch and draw tile_map.
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
 
-        // Transform and scale background
-        let param = graphics::DrawParam::new()
-            .dest(Point2::new(
-               self.get_offset().x as f32,
-               self.get_offset().y as f32,
-            ));
-
-        // Draw background
-        graphics::draw(ctx, &self.spritebatch, param)?;
-
-        Ok(())
+    fn puzzle(&self) -> &Puzzle {
+        &self.list[self.current].1
     }
 
-    fn remove_row(&mut self, y: i16) {
-
-        // Remove row 'y'
-        for x in 1..self.size.x-1 {
-            self.set_cell(x, y, 0);
+    // Marks the current puzzle solved and remembers it for the next time
+    fn solve(&mut self) {
+        let id = self.list[self.current].0.clone();
+        self.progress.solved.insert(id);
+        if let Err(e) = self.progress.save() {
+            eprintln!("Could not save solved puzzles: {}", e);
         }
+    }
 
-        // Move rows down
-        for y2 in (0..y).rev() {
-            for x in 1..self.size.x-1 {
-                let cell = self._get_cell(x, y2);
-                if y2 < (self.size.y) {
-                    self.set_cell(x, y2+1, cell);
-                }
-            }
-        }
+    // Moves on to the next unsolved puzzle, or just the next one when all are solved
+    fn next(&mut self) {
+        let count = self.list.len();
+        self.current = (1..=count)
+            .map(|i| (self.current + i) % count)
+            .find(|&i| !self.progress.is_solved(&self.list[i].0))
+            .unwrap_or((self.current + 1) % count);
     }
 
-    fn check_full_rows(&mut self) -> u32 {
-        let mut added_points = 0;
-        for y in 0..self.size.y-1 {
-            let mut row_is_full = true;
-            for x in 1..self.size.x-1 {
-                if self._get_cell(x, y) < 1 {
-                    row_is_full = false;
-                }
-            }
-            if row_is_full {
-                self.remove_row(y);
-                added_points = 1 + self.check_full_rows();
-            }
-        }
-        added_points
+    fn solved_count(&self) -> usize {
+        self.list.iter().filter(|(id, _)| self.progress.is_solved(id)).count()
     }
 }
 
-#[derive(Debug, Clone)]
-struct Block {
-    position: Vector2,
-    previous_position: Vector2,
-    block_type: u8,
-    shape: array2d::Array2D<u8>,
-    rotation: Rotation,
-    previous_rotation: Rotation,
-    down: bool,
-    moving_down: bool,
+// Theme of the directory 'dir', or the built-in one, in the colors of 'palette' with
+// its images and font
+fn load_skin(ctx: &mut Context, dir: Option<&path::Path>, palette: Palette) -> GameResult<Skin> {
+    let mut theme = match dir {
+        Some(dir) => Theme::load(dir)?,
+        None => Theme::default(),
+    };
+    palette.apply(&mut theme);
+    Skin::load(ctx, theme)
 }
 
-impl Block {
-    pub fn new(position: Vector2, block_type: u8) -> Self {
-        
-        let shapes = vec![  
-            // O-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,0,0,0,
-                0,0,1,1,0,
-                0,0,1,1,0,
-                0,0,0,0,0
-            ],
-            // I-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,1,0,0,
-                0,0,1,0,0,
-                0,0,1,0,0,
-                0,0,1,0,0
-            ],
-            // T-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,0,0,0,
-                0,1,1,1,0,
-                0,0,1,0,0,
-                0,0,0,0,0
-            ],
-            // S-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,0,1,0,
-                0,0,1,1,0,
-                0,0,1,0,0,
-                0,0,0,0,0
-            ],
-            // Z-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,1,0,0,
-                0,0,1,1,0,
-                0,0,0,1,0,
-                0,0,0,0,0
-            ],
-            // L-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,1,0,0,
-                0,0,1,0,0,
-                0,0,1,1,0,
-                0,0,0,0,0
-            ],
-            // J-shape
-            vec![   
-                0,0,0,0,0,
-                0,0,1,0,0,
-                0,0,1,0,0,
-                0,1,1,0,0,
-                0,0,0,0,0
-            ]
-        ];
-
-        let shape = array2d::Array2D::from_column_major(&shapes[block_type as usize], 5, 5);
+// Board editor with the file it saves to
+struct EditorState {
+    editor: Editor,
+    path: path::PathBuf,
+    // Result of the last save or error, shown under the board
+    message: String,
+}
 
-        Block { 
-            position, 
-            previous_position: position, 
-            block_type, 
-            shape, 
-            rotation: Rotation::Cw0, 
-            previous_rotation: Rotation::Cw0, 
-            down: false, 
-            moving_down: false 
+impl EditorState {
+    // Handles a key of the editor, returns whether it was one
+    fn key_down(&mut self, keycode: KeyCode, keymod: KeyMods) -> bool {
+        let editor = &mut self.editor;
+        if keymod.contains(KeyMods::CTRL) {
+            match keycode {
+                KeyCode::Z => {
+                    editor.undo();
+                },
+                KeyCode::Y => {
+                    editor.redo();
+                },
+                KeyCode::S => {
+                    self.message = match editor.save(&self.path) {
+                        Ok(()) => format!("Saved {}", self.path.display()),
+                        Err(e) => format!("Could not save: {}", e),
+                    };
+                },
+                KeyCode::F => {
+                    self.message = match editor.to_fumen() {
+                        Ok(fumen) => {
+                            println!("{}", fumen);
+                            "Fumen printed to the terminal".to_string()
+                        },
+                        Err(e) => format!("No fumen: {}", e),
+                    };
+                },
+                _ => return false,
+            };
+            return true;
         }
-    }
 
-    fn get_cell(&mut self, x: usize, y: usize, previous: bool) -> bool {
-        
-        let r = match previous {
-            true    => &self.previous_rotation,
-            false   => &self.rotation,
-        };
-
-        let c = match r {
-            Rotation::Cw0   => self.shape[(x,y)],
-            Rotation::Cw90  => self.shape[(y,4-x)],
-            Rotation::Cw180 => self.shape[(4-x,4-y)],
-            Rotation::Cw270 => self.shape[(4-y,x)],
+        let letter = match keycode {
+            KeyCode::I => Some('I'),
+            KeyCode::O => Some('O'),
+            KeyCode::T => Some('T'),
+            KeyCode::L => Some('L'),
+            KeyCode::J => Some('J'),
+            KeyCode::S => Some('S'),
+            KeyCode::Z => Some('Z'),
+            _ => None,
         };
-
-        c > 0
-    }
-
-    pub fn _mark_to_tile_map(&mut self, tile_map: &mut TileMap) {
-
-        for x in 0..5 {
-            for y in 0..5 {
-                if self.get_cell(x,y,false) {
-                    tile_map.set_cell(self.position.x + x as i16, self.position.y + y as i16, self.block_type as i16 + 1);
-                }    
-            }
-        }
-    }
-
-    pub fn _delete_from_tile_map(&mut self, tile_map: &mut TileMap) {
-
-        for x in 0..5 {
-            for y in 0..5 {
-                if self.get_cell(x,y,false) {
-                    tile_map.set_cell(self.previous_position.x + x as i16, self.previous_position.y + y as i16, 0);
-                }    
-            }
+        if let Some(piece) = letter.and_then(Piece::from_letter) {
+            editor.push_piece(piece);
+            return true;
         }
-    }
-    
-    pub fn move_down(&mut self, tile_map: &mut TileMap) {
-        if self.position.y < tile_map.size.y - 1 {
-            self.position.y += 1;
-        }
-    }
-
-    pub fn is_down(&mut self) -> bool {
-        return self.down;
-    }
-
-    fn test_position(&mut self, tile_map: &mut TileMap) {
-        for x in 0..5 {
-            for y in 0..5 {
-                if self.get_cell(x,y,false) {
-                    if tile_map._get_cell(self.position.x + x as i16, self.position.y + y as i16) > 0 {
-                        if self.position.y > self.previous_position.y {
-                            if self.position.x == self.previous_position.x {
-                                self.down = true;
-                            }
-                        }
-                        self.position = self.previous_position;
-                    }
-                }
-            }
-        }
-    }
-    
-    fn test_rotation(&mut self, tile_map: &mut TileMap) {
-        for x in 0..5 {
-            for y in 0..5 {
-                if self.get_cell(x,y,false) {
-                    if tile_map._get_cell(self.position.x + x as i16, self.position.y + y as i16) > 0 {
-                        self.rotation = self.previous_rotation;
-                        //self.position = self.previous_position;
-                        //self._delete_from_tile_map(&mut tile_map);
-                    }
-                }
-            }
+        match keycode {
+            // Digits choose the tile with that number, 9 is garbage, B cycles through all of them
+            KeyCode::Key0 => editor.set_brush(0),
+            KeyCode::Key1 => editor.set_brush(1),
+            KeyCode::Key2 => editor.set_brush(2),
+            KeyCode::Key3 => editor.set_brush(3),
+            KeyCode::Key4 => editor.set_brush(4),
+            KeyCode::Key5 => editor.set_brush(5),
+            KeyCode::Key6 => editor.set_brush(6),
+            KeyCode::Key7 => editor.set_brush(7),
+            KeyCode::Key9 => editor.set_brush(GARBAGE_TILE),
+            KeyCode::B => {
+                editor.next_brush();
+                true
+            },
+            KeyCode::Back => {
+                editor.pop_piece();
+                true
+            },
+            KeyCode::G => {
+                editor.next_goal();
+                true
+            },
+            KeyCode::H => {
+                editor.toggle_hold();
+                true
+            },
+            KeyCode::Delete => {
+                editor.clear_field();
+                true
+            },
+            _ => false,
         }
     }
-
 }
 
 /// Now we have the heart of our game, the GameState. This struct
 /// will implement ggez's `EventHandler` trait and will therefore drive
 /// everything ele that happens in our game.
 struct GameState {
-    tile_map: TileMap,
-    block: Block,
+    // One board, or two in versus
+    boards: Vec<Board>,
+    settings: Settings,
+    // Some in network games
+    network: Option<Network>,
+    // Plays the second board in versus against the computer
+    bot: Option<Box<dyn Controller>>,
+    // Some when playing puzzles
+    puzzles: Option<Puzzles>,
+    // Some in the editor
+    editing: Option<EditorState>,
     /// Whether the game is over or not
     _gameover: bool,
     last_update: Instant,
-    last_move_down_time: Instant,
+    /// When the running countdown was started
+    countdown_start: Instant,
+    // Whether the statistics panels are shown
+    show_stats: bool,
+    // Look of boards and texts
+    skin: Skin,
+    // Theme directories F2 cycles through after the built-in theme
+    themes: Vec<path::PathBuf>,
+    // Size of the window everything is laid out for
+    screen: Screen,
+    fullscreen: FullscreenType,
 
     text: graphics::Text,
     text_game_over: graphics::Text,
     text_try_again: graphics::Text,
     text_pause: graphics::Text,
+    text_countdown: graphics::Text,
     text_debug: graphics::Text,
 
     game_state: GameStates,
     music_on: bool,
     music: audio::Source,
     sound_remove_row: audio::Source,
-    points: u32,
 }
 
 impl GameState {
     /// Our new function will set up the initial state of our game.
-    pub fn new(_ctx: &mut Context) -> GameResult<GameState> {
-        let block = Block::new(Vector2::new(START_X, START_Y), 1);
-
+    pub fn new(_ctx: &mut Context, settings: Settings) -> GameResult<GameState> {
         let _font = graphics::Font::new(_ctx, "/DejaVuSerif.ttf");
 
         let mut sound_remove_row = audio::Source::new(_ctx, "/13_item1.wav")?;
@@ -451,90 +547,469 @@ impl GameState {
         music.set_repeat(true);
         let _ = music.play();
         
-        let mut tile_set = TileSet::new();
-        
-        // Background tiles
-        tile_set.add_tile(Tile::new(0, graphics::Color::new(0.2, 0.2, 0.2, 1.0)));
-        // O-shape block tiles
-        tile_set.add_tile(Tile::new(1, graphics::Color::new(0.7, 0.7, 0.1, 1.0)));
-        // I-shape block tiles
-        tile_set.add_tile(Tile::new(2, graphics::Color::new(0.0, 0.7, 0.7, 1.0)));
-        // T-shape block tiles
-        tile_set.add_tile(Tile::new(3, graphics::Color::new(0.5, 0.1, 0.8, 1.0)));
-        // S-shape block tiles
-        tile_set.add_tile(Tile::new(4, graphics::Color::new(0.1, 0.7, 0.0, 1.0)));
-        // Z-shape block tiles
-        tile_set.add_tile(Tile::new(5, graphics::Color::new(0.8, 0.1, 0.1, 1.0)));
-        // L-shape block tiles
-        tile_set.add_tile(Tile::new(6, graphics::Color::new(0.8, 0.4, 0.1, 1.0)));
-        // J-shape block tiles
-        tile_set.add_tile(Tile::new(7, graphics::Color::new(0.1, 0.3, 0.9, 1.0)));
-        // Wall tiles
-        tile_set.add_tile(Tile::new(8, graphics::Color::new(0.6, 0.6, 0.6, 1.0)));
+        let themes = theme::list(path::Path::new(THEMES_DIR));
+        let skin = settings.load_skin(_ctx);
+        let tile_set = skin.theme.tile_set(&settings.rules.pieces);
         
-        let mut tile_map = TileMap::new(_ctx, Vector2::new(TILEMAP_SIZE_X, TILEMAP_SIZE_Y), CELL_SIZE, tile_set);
-
-        // Bottom wall
-        for i in 0..=10 {
-            tile_map.set_cell(i, TILEMAP_SIZE_Y-1, 8);
-        }
+        let multiplayer = settings.versus || settings.network_address().is_some();
+        let editing = match multiplayer {
+            true => None,
+            false => settings.load_editor(),
+        };
+        let puzzles = match (&settings.puzzle, multiplayer || editing.is_some()) {
+            (Some(path), false) => Puzzles::load(path),
+            _ => None,
+        };
 
-        // Left and right wall
-        for i in 0..TILEMAP_SIZE_Y {
-            tile_map.set_cell( 0, i, 8);
-            tile_map.set_cell(TILEMAP_SIZE_X-1, i, 8);
+        let screen = Screen::default();
+        let mut boards = Vec::new();
+        if settings.connect.is_some() || settings.spectate.is_some() {
+            // Own board on the left, the opponent's board on the right
+            let own_keys = settings.spectate.is_none().then(KeyBindings::single_player);
+            let keys = [own_keys, None];
+            for (keys, (center, area)) in keys.iter().zip(screen.board_areas(2)) {
+                boards.push(Board::new(_ctx, settings.rules.clone(), tile_set.clone(), settings.effects.clone(), keys.clone(), center, area));
+            }
+        } else if settings.versus {
+            // Each player gets half of the screen. Against the computer the player can use the usual keys
+            let keys = match settings.bot {
+                true => [Some(KeyBindings::single_player()), None],
+                false => [Some(KeyBindings::versus_left()), Some(KeyBindings::versus_right())],
+            };
+            for (keys, (center, area)) in keys.iter().zip(screen.board_areas(2)) {
+                boards.push(Board::new(_ctx, settings.rules.clone(), tile_set.clone(), settings.effects.clone(), keys.clone(), center, area));
+            }
+        } else if let Some(editing) = editing.as_ref() {
+            let board = Board::new(_ctx, editing.editor.rules(), tile_set, settings.effects.clone(),
+                                   None, screen.center, screen.size);
+            boards.push(board);
+        } else if let Some(puzzles) = puzzles.as_ref() {
+            let puzzle = puzzles.puzzle();
+            let mut board = Board::new(_ctx, puzzle.rules(), tile_set, settings.effects.clone(),
+                                       Some(KeyBindings::single_player()), screen.center, screen.size);
+            board.start(puzzle.engine());
+            boards.push(board);
+        } else if let Some(opener) = settings.load_opener() {
+            let mut board = Board::new(_ctx, opener.rules(), tile_set, settings.effects.clone(),
+                                       Some(KeyBindings::single_player()), screen.center, screen.size);
+            board.practice = Some(Practice::new(opener));
+            board.retry_practice();
+            boards.push(board);
+        } else {
+            let mut board = Board::new(_ctx, settings.rules.clone(), tile_set, settings.effects.clone(),
+                                       Some(KeyBindings::single_player()), screen.center, screen.size);
+            if settings.finesse_trainer {
+                board.trainer = Some(Trainer::default());
+            }
+            boards.push(board);
         }
         
-        let s = GameState {
-            tile_map,
-            block,
+        for board in boards.iter_mut() {
+            board.set_skin(&skin, skin.theme.tile_set(&settings.rules.pieces));
+            board.renderer.patterns = settings.patterns;
+        }
+
+        let network = settings.network_address().map(|address| Network::new(address, &settings.name, settings.spectate.is_some()));
+
+        let bot = match settings.bot {
+            true => Some(settings.launch_bot()),
+            false => None,
+        };
+
+        let mut s = GameState {
+            boards,
+            settings,
+            network,
+            bot,
+            puzzles,
+            editing,
             _gameover: false,
             last_update: Instant::now(),
-            last_move_down_time: Instant::now(),
+            countdown_start: Instant::now(),
+            show_stats: true,
             text: graphics::Text::new("Hello world!"),
-            text_try_again: graphics::Text::new(TextFragment {
-                // `TextFragment` stores a string, and optional parameters which will override those
-                // of `Text` itself. This allows inlining differently formatted lines, words,
-                // or even individual letters, into the same block of text.
-                text: "Do you want to try again? Y/N".to_string(),
-                color: Some(Color::new(1.0, 1.0, 1.0, 1.0)),
-                // `Font` is a handle to a loaded TTF, stored inside the `Context`.
-                // `Font::default()` always exists and maps to DejaVuSerif.
-                font: Some(graphics::Font::default()),
-                scale: Some(Scale::uniform(30.0)),
-                // This doesn't do anything at this point; can be used to omit fields in declarations.
-                ..Default::default()
-            }),
-            text_game_over: graphics::Text::new(TextFragment {
-                text: "GAME OVER".to_string(),
-                color: Some(Color::new(1.0, 0.0, 0.0, 1.0)),
-                font: Some(graphics::Font::default()),
-                scale: Some(Scale::uniform(100.0)),
-                ..Default::default()
-            }),
-           text_pause: graphics::Text::new(TextFragment {
-                text: "PAUSED".to_string(),
-                color: Some(Color::new(1.0, 1.0, 1.0, 1.0)),
-                font: Some(graphics::Font::default()),
-                scale: Some(Scale::uniform(100.0)),
-                ..Default::default()
-            }),
-            text_debug: graphics::Text::new(TextFragment {
-                text: "DEBUG".to_string(),
-                color: Some(Color::new(1.0, 1.0, 1.0, 1.0)),
-                font: Some(graphics::Font::default()),
-                scale: Some(Scale::uniform(14.0)),
-                ..Default::default()
-            }),
-            game_state: GameStates::GameOn,
+            text_try_again: graphics::Text::new(""),
+            text_game_over: graphics::Text::new(""),
+            text_pause: graphics::Text::new(""),
+            text_countdown: graphics::Text::new(""),
+            text_debug: graphics::Text::new(""),
+            skin,
+            themes,
+            screen,
+            fullscreen: FullscreenType::Windowed,
+            game_state: GameStates::Countdown,
             music_on: true,
             music,
             sound_remove_row,
-            points: 0,
+        };
+
+        s.build_texts();
+
+        match (&s.network, &s.editing) {
+            (Some(_), _) => s.game_state = GameStates::Waiting(s.settings.waiting_text()),
+            (None, Some(_)) => s.game_state = GameStates::Editing,
+            (None, None) => s.start_countdown(),
         };
 
         Ok(s)
     }
+
+    // Starts the Ready-Go countdown, or the game right away if the countdown is turned off
+    fn start_countdown(&mut self) {
+        self.countdown_start = Instant::now();
+        self.game_state = match self.settings.countdown_seconds {
+            0 => GameStates::GameOn,
+            _ => GameStates::Countdown,
+        };
+    }
+
+    // Draws the headline, the reason the game ended and the try again question
+    fn draw_game_over(&self, ctx: &mut Context, headline: &graphics::Text, reason: String) -> GameResult {
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(headline.width(ctx) as f32),
+            y: (self.screen.center.y as f32 - headline.height(ctx) as f32).max(0.0),
+        };
+        graphics::draw(ctx, headline, (dest_point,))?;
+
+        let text_reason = self.text(reason, self.skin.hud.bad, 40.0);
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(text_reason.width(ctx) as f32),
+            y: self.screen.center.y as f32 + 20.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &text_reason, (dest_point,))?;
+
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(self.text_try_again.width(ctx) as f32),
+            y: self.screen.center.y as f32 + 80.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &self.text_try_again, (dest_point,))?;
+
+        Ok(())
+    }
+
+    // Faults per block type of the own board under the try again question
+    fn draw_finesse_summary(&self, ctx: &mut Context) -> GameResult {
+        let finesse = &self.boards[0].finesse;
+        if finesse.total_pieces() == 0 {
+            return Ok(());
+        }
+        let text_summary = self.text(finesse.summary(self.boards[0].engine.pieces()), self.skin.hud.text, 30.0);
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(text_summary.width(ctx) as f32),
+            y: self.screen.center.y as f32 + 140.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &text_summary, (dest_point,))?;
+
+        Ok(())
+    }
+
+    // Trainer feedback above the board: too many inputs for the current block, or
+    // how the last block went
+    fn draw_trainer_message(&self, ctx: &mut Context) -> GameResult {
+        let board = &self.boards[0];
+        let trainer = match board.trainer.as_ref() {
+            Some(trainer) => trainer,
+            None => return Ok(()),
+        };
+        let names = |inputs: &[Input]| inputs.iter().map(|i| format!("{:?}", i)).collect::<Vec<String>>().join(" ");
+
+        let (text, color) = if trainer.too_many(board.finesse.current_inputs()) {
+            ("Too many inputs".to_string(), self.skin.hud.bad)
+        } else {
+            match (trainer.last_hit, board.finesse.last.as_ref()) {
+                (Some(false), _) => ("Missed the target".to_string(), self.skin.hud.bad),
+                (Some(true), Some(last)) if last.faults > 0 => (
+                    format!("{} extra inputs, best: {}", last.faults, names(&last.best)),
+                    self.skin.hud.warning,
+                ),
+                (Some(true), _) => ("Perfect".to_string(), self.skin.hud.good),
+                (None, _) => ("Place the block in the outline".to_string(), self.skin.hud.text),
+            }
+        };
+        let text_message = self.text(format!("{}  ({}/{})", text, trainer.placed, TRAINER_PIECES), color, 30.0);
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(text_message.width(ctx) as f32),
+            y: 30.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &text_message, (dest_point,))?;
+
+        Ok(())
+    }
+
+    // Name and goal of the puzzle above the board
+    fn draw_puzzle_header(&self, ctx: &mut Context) -> GameResult {
+        let puzzles = match self.puzzles.as_ref() {
+            Some(puzzles) => puzzles,
+            None => return Ok(()),
+        };
+        let puzzle = puzzles.puzzle();
+        let header = format!("{}: {}  ({}/{} solved)", puzzle.name, puzzle.goal.description(),
+                             puzzles.solved_count(), puzzles.list.len());
+        let text_header = self.text(header, self.skin.hud.text, 30.0);
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(text_header.width(ctx) as f32),
+            y: 30.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &text_header, (dest_point,))?;
+
+        Ok(())
+    }
+
+    // Opener name, progress of the attempt and success rate above the board
+    fn draw_practice_header(&self, ctx: &mut Context) -> GameResult {
+        let practice = match self.boards[0].practice.as_ref() {
+            Some(practice) => practice,
+            None => return Ok(()),
+        };
+        let (last, color) = match practice.last {
+            Some(true) => ("Done!  ", self.skin.hud.good),
+            Some(false) => ("Missed, again  ", self.skin.hud.bad),
+            None => ("", self.skin.hud.text),
+        };
+        let header = format!("{}{}: block {}/{}, {}/{} done ({:.0}%)", last, practice.opener.name,
+                             practice.step + 1, practice.opener.pieces.len(),
+                             practice.successes, practice.attempts, 100.0 * practice.success_rate());
+        let text_header = self.text(header, color, 30.0);
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(text_header.width(ctx) as f32),
+            y: 30.0 * self.screen.scale(),
+        };
+        graphics::draw(ctx, &text_header, (dest_point,))?;
+
+        Ok(())
+    }
+
+    // Switches to the next theme in the themes directory, after the last one back to
+    // the built-in theme
+    fn next_theme(&mut self, ctx: &mut Context) {
+        let current = self.themes.iter().position(|dir| *dir == self.skin.theme.dir);
+        let next = current.map_or(0, |i| i + 1);
+        let skin = load_skin(ctx, self.themes.get(next).map(|dir| dir.as_path()), self.settings.palette);
+        match skin {
+            Ok(skin) => self.set_skin(skin),
+            Err(e) => {
+                eprintln!("Could not load theme: {}", e);
+                // Skip it the next time
+                if let Some(dir) = self.themes.get(next).cloned() {
+                    self.themes.retain(|theme| *theme != dir);
+                }
+            },
+        }
+    }
+
+    // Switches to the next palette, the theme is loaded again in its colors
+    fn next_palette(&mut self, ctx: &mut Context) {
+        self.settings.palette = self.settings.palette.next();
+        let dir = self.skin.theme.dir.clone();
+        let dir = Some(dir.as_path()).filter(|dir| !dir.as_os_str().is_empty());
+        match load_skin(ctx, dir, self.settings.palette) {
+            Ok(skin) => self.set_skin(skin),
+            Err(e) => eprintln!("Could not load theme: {}", e),
+        }
+    }
+
+    // Draws everything with the look of 'skin' from now on
+    fn set_skin(&mut self, skin: Skin) {
+        let tile_set = skin.theme.tile_set(&self.settings.rules.pieces);
+        for board in self.boards.iter_mut() {
+            board.set_skin(&skin, tile_set.clone());
+            board.renderer.patterns = self.settings.patterns;
+        }
+        self.skin = skin;
+        self.build_texts();
+    }
+
+    // Texts which are kept between frames, in the font and size of the moment
+    fn build_texts(&mut self) {
+        self.text_try_again = self.text("Do you want to try again? Y/N", self.skin.hud.text, 30.0);
+        self.text_game_over = self.text("GAME OVER", self.skin.hud.bad, 100.0);
+        self.text_pause = self.text("PAUSED", self.skin.hud.text, 100.0);
+        self.text_debug = self.text("DEBUG", self.skin.hud.text, 14.0);
+    }
+
+    // Text in the theme's font, 'size' is scaled with the window
+    fn text(&self, text: impl Into<String>, color: graphics::Color, size: f32) -> graphics::Text {
+        self.skin.text(text, color, size * self.screen.scale())
+    }
+
+    // Lays everything out for a window of 'width' x 'height' pixels
+    fn resize(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let _ = graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height));
+        self.screen = Screen::new(width, height);
+        self.layout();
+        self.build_texts();
+    }
+
+    // Fits the boards in their parts of the screen
+    fn layout(&mut self) {
+        let scale = self.screen.scale();
+        let areas = self.screen.board_areas(self.boards.len());
+        for (board, (center, area)) in self.boards.iter_mut().zip(areas) {
+            board.resize(center, area, scale);
+        }
+    }
+
+    // Goes from a window to fullscreen, borderless unless real fullscreen was asked
+    // for, and back
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        self.fullscreen = match (self.fullscreen, self.settings.fullscreen) {
+            (FullscreenType::Windowed, FullscreenType::True) => FullscreenType::True,
+            (FullscreenType::Windowed, _) => FullscreenType::Desktop,
+            _ => FullscreenType::Windowed,
+        };
+        if let Err(e) = graphics::set_fullscreen(ctx, self.fullscreen) {
+            eprintln!("Could not change fullscreen: {}", e);
+            return;
+        }
+        let (width, height) = graphics::drawable_size(ctx);
+        self.resize(ctx, width, height);
+    }
+
+    // Editor state above the board and the keys under it
+    fn draw_editor(&self, ctx: &mut Context) -> GameResult {
+        let editing = match self.editing.as_ref() {
+            Some(editing) => editing,
+            None => return Ok(()),
+        };
+        let layout = &editing.editor.layout;
+        let scale = self.screen.scale();
+        let hold = if layout.hold { "on" } else { "off" };
+        let lines = [
+            (format!("{}: {}, hold {}, brush {}, queue {}", editing.editor.name, layout.goal.description(),
+                     hold, editing.editor.brush_name(), layout.pieces), 30.0 * scale),
+            (editing.message.clone(), self.screen.size.y as f32 - 140.0 * scale),
+            ("Mouse paints, right button erases. 0-7, 9 and B choose the brush".to_string(),
+             self.screen.size.y as f32 - 100.0 * scale),
+            ("IOTLJSZ add to the queue, Backspace removes. G goal, H hold, Delete clears".to_string(),
+             self.screen.size.y as f32 - 70.0 * scale),
+            ("Ctrl+Z undo, Ctrl+Y redo, Ctrl+S save, Ctrl+F fumen".to_string(),
+             self.screen.size.y as f32 - 40.0 * scale),
+        ];
+        for (text, y) in lines.iter() {
+            let text = self.text(text.clone(), self.skin.hud.text, 24.0);
+            let dest_point = mint::Vector2 {
+                x: self.screen.centered_x(text.width(ctx) as f32),
+                y: *y,
+            };
+            graphics::draw(ctx, &text, (dest_point,))?;
+        }
+
+        Ok(())
+    }
+
+    // Paints the cell under the mouse, the right button erases
+    fn paint(&mut self, x: f32, y: f32, button: MouseButton) {
+        let editing = match self.editing.as_mut() {
+            Some(editing) if matches!(self.game_state, GameStates::Editing) => editing,
+            _ => return,
+        };
+        let tile = match button {
+            MouseButton::Left => editing.editor.brush,
+            MouseButton::Right => 0,
+            _ => return,
+        };
+        let board = &self.boards[0];
+        if let Some(cell) = board.renderer.cell_at(&board.engine.tile_map, x, y) {
+            editing.editor.paint(cell, tile);
+        }
+    }
+
+    // Reconnects if needed, sends the own board and handles messages from the server
+    fn update_network(&mut self) {
+        let network = match self.network.as_mut() {
+            Some(network) => network,
+            None => return,
+        };
+
+        let spectator = network.client.is_spectator();
+        if !network.client.is_connected() {
+            let over = matches!(self.game_state, GameStates::VersusOver(_));
+            if !over && !matches!(self.game_state, GameStates::Waiting(_)) {
+                self.game_state = GameStates::Waiting("Connection lost, reconnecting".to_string());
+            }
+            // Spectators go on to watch the next match, players keep the result on screen
+            let retry = !network.rejected && (spectator || !over);
+            if retry && network.last_connect.elapsed() >= RECONNECT_INTERVAL {
+                network.last_connect = Instant::now();
+                let _ = network.client.connect();
+            }
+            return;
+        }
+
+        if !spectator && matches!(self.game_state, GameStates::GameOn | GameStates::Countdown)
+            && network.last_board_sent.elapsed() >= BOARD_SEND_INTERVAL {
+            network.last_board_sent = Instant::now();
+            let state = BoardState::from_engine(&self.boards[0].engine);
+            let _ = network.client.send(&ClientMessage::Board { state });
+        }
+
+        for message in network.client.poll() {
+            match message {
+                ServerMessage::Welcome { .. } => {},
+                ServerMessage::Rejected { reason } => {
+                    network.rejected = true;
+                    self.game_state = GameStates::Waiting(reason);
+                },
+                ServerMessage::MatchStart { .. } => {
+                    for board in self.boards.iter_mut() {
+                        board.restart(self.settings.rules.clone());
+                    }
+                    self.countdown_start = Instant::now();
+                    self.game_state = match spectator {
+                        true => GameStates::Spectating,
+                        false => GameStates::Countdown,
+                    };
+                },
+                ServerMessage::Board { player, state } => {
+                    let board = if spectator { player } else { 1 };
+                    if let Some(board) = self.boards.get_mut(board) {
+                        board.apply_state(&state);
+                    }
+                },
+                ServerMessage::Attack { rows } => self.boards[0].engine.receive_garbage(rows),
+                ServerMessage::OpponentDisconnected => {
+                    self.game_state = GameStates::Waiting("Opponent disconnected".to_string());
+                },
+                ServerMessage::OpponentReconnected | ServerMessage::Resume => {
+                    self.countdown_start = Instant::now();
+                    self.game_state = GameStates::Countdown;
+                },
+                ServerMessage::MatchOver { winner } => {
+                    // Own board is the first board
+                    let me = network.client.player();
+                    self.game_state = match spectator {
+                        true => GameStates::VersusOver(winner),
+                        false => GameStates::VersusOver(winner.map(|w| if Some(w) == me { 0 } else { 1 })),
+                    };
+                },
+            };
+        }
+    }
+
+    // Whether the countdown ended so recently that GO is still shown
+    fn showing_go(&self) -> bool {
+        let length = Duration::from_secs(self.settings.countdown_seconds);
+        self.settings.countdown_seconds > 0 && Instant::now() - self.countdown_start < length + GO_TIME
+    }
+
+    // Seconds left or GO in the middle of the screen
+    fn draw_countdown(&self, ctx: &mut Context) -> GameResult {
+        let dest_point = mint::Vector2 {
+            x: self.screen.centered_x(self.text_countdown.width(ctx) as f32),
+            y: self.screen.centered_y(self.text_countdown.height(ctx) as f32),
+        };
+        graphics::draw(ctx, &self.text_countdown, (dest_point,))
+    }
+
+    // Whole seconds left in the countdown, rounded up so it shows 3, 2, 1
+    fn countdown_left(&self) -> u64 {
+        let length = Duration::from_secs(self.settings.countdown_seconds);
+        let elapsed = Instant::now() - self.countdown_start;
+        match length.checked_sub(elapsed) {
+            Some(left) => left.as_secs() + if left.subsec_nanos() > 0 { 1 } else { 0 },
+            None => 0,
+        }
+    }
 }
 
 /// Now we implement EventHandler for GameState. This provides an interface
@@ -545,54 +1020,134 @@ impl event::EventHandler for GameState {
     fn update(&mut self, _ctx: &mut Context) -> GameResult {
         // First we check to see if enough has elapsed since our last update based on
         // the update rate we defined at the top.
-        if Instant::now() - self.last_update >= Duration::from_millis(10) {
+        let dt = Instant::now() - self.last_update;
+        if dt >= Duration::from_millis(10) {
+            let points: Vec<String> = self.boards.iter().map(|b| b.engine.points.to_string()).collect();
             self.text = graphics::Text::new(format!(
                 "FPS: {:.0} Points: {}",
-                ggez::timer::fps(_ctx), self.points,
+                ggez::timer::fps(_ctx), points.join(" - "),
             ));
 
+            // Engine time only advances while the game is on, not while paused or counting down
             match self.game_state {
-                GameStates::GameOver | GameStates::Pause => None,
-                GameStates::Restart => Some({
-                    self.points = 0;
-                    self.tile_map.clear_center();
-                    self.game_state = GameStates::GameOn;
-                }),
-                GameStates::GameOn => Some({
-                    if Instant::now() - self.last_move_down_time >= Duration::from_millis(300) {
-                        if !self.block.down {
-                            self.block.moving_down = true;
+                GameStates::GameOver(_) | GameStates::VersusOver(_) | GameStates::Pause | GameStates::Waiting(_)
+                | GameStates::Spectating | GameStates::TrainerOver | GameStates::PuzzleOver(_) => {},
+                GameStates::Editing => {
+                    if let Some(editing) = self.editing.as_ref() {
+                        self.boards[0].show(&editing.editor.layout.field, &editing.editor.blocks());
+                    }
+                },
+                GameStates::Countdown => {
+                    let left = self.countdown_left();
+                    let text = match left {
+                        0 => {
+                            self.game_state = GameStates::GameOn;
+                            "GO".to_string()
+                        },
+                        _ => left.to_string(),
+                    };
+                    self.text_countdown = self.text(text, self.skin.hud.text, 200.0);
+                },
+                GameStates::Restart => {
+                    if let Some(puzzles) = self.puzzles.as_mut() {
+                        puzzles.lines = 0;
+                        self.boards[0].start(puzzles.puzzle().engine());
+                    } else if self.boards[0].practice.is_some() {
+                        self.boards[0].retry_practice();
+                    } else {
+                        for board in self.boards.iter_mut() {
+                            board.restart(self.settings.rules.clone());
                         }
-                        self.last_move_down_time = Instant::now();
                     }
-                    self.block._delete_from_tile_map(&mut self.tile_map);
-                    if self.block.moving_down {
-                        self.block.move_down(&mut self.tile_map);
-                        self.block.moving_down = false;
+                    if let Some(bot) = self.bot.as_mut() {
+                        bot.new_game();
+                    }
+                    // Puzzles can have boards of different sizes
+                    self.layout();
+                    // A new network game starts with a new connection and a new opponent
+                    if let Some(address) = self.settings.network_address() {
+                        self.network = Some(Network::new(address, &self.settings.name, self.settings.spectate.is_some()));
+                        self.game_state = GameStates::Waiting(self.settings.waiting_text());
+                    } else {
+                        self.start_countdown();
+                    }
+                },
+                GameStates::GameOn if self.network.is_some() => {
+                    // Only the own board is played here, the other one comes from the server
+                    let network = self.network.as_mut().unwrap();
+                    for event in self.boards[0].update(dt) {
+                        if let Event::LinesCleared(clear) = event {
+                            let _ = self.sound_remove_row.play();
+                            let attack = self.boards[0].engine.cancel_garbage(versus::attack(&clear));
+                            if attack > 0 {
+                                let _ = network.client.send(&ClientMessage::Attack { rows: attack });
+                            }
+                        }
                     }
-                    self.block.test_position(&mut self.tile_map);
-                    self.block._mark_to_tile_map(&mut self.tile_map);
-                    self.block.previous_position = self.block.position;
 
-                    self.tile_map.update_spritebatches();
+                    // The server decides the winner
+                    if self.boards[0].engine.top_out().is_some() {
+                        let state = BoardState::from_engine(&self.boards[0].engine);
+                        let _ = network.client.send(&ClientMessage::Board { state });
+                        let _ = network.client.send(&ClientMessage::TopOut);
+                        self.game_state = GameStates::Waiting("Waiting for result".to_string());
+                    }
+                },
+                GameStates::GameOn => {
+                    if let Some(bot) = self.bot.as_mut() {
+                        bot.update(&mut self.boards[1].engine, dt);
+                    }
+                    let mut solved = false;
+                    for i in 0..self.boards.len() {
+                        for event in self.boards[i].update(dt) {
+                            if let Event::LinesCleared(clear) = event {
+                                let _ = self.sound_remove_row.play();
+
+                                if let Some(puzzles) = self.puzzles.as_mut() {
+                                    puzzles.lines += clear.lines() as u32;
+                                    solved |= puzzles.puzzle().goal.is_reached(&clear, puzzles.lines);
+                                }
+
+                                // Attack cancels own pending garbage first, the rest goes to the opponent
+                                if self.settings.versus {
+                                    let attack = self.boards[i].engine.cancel_garbage(versus::attack(&clear));
+                                    let opponent = (i + 1) % self.boards.len();
+                                    self.boards[opponent].engine.receive_garbage(attack);
+                                }
+                            }
+                        }
+                    }
 
-                    if self.block.is_down() {
-                        if self.block.position.y < 5 {
-                            self.game_state = GameStates::GameOver;
+                    let topped_out: Vec<usize> = (0..self.boards.len())
+                        .filter(|&i| self.boards[i].engine.top_out().is_some())
+                        .collect();
+                    if let Some(puzzles) = self.puzzles.as_mut() {
+                        // Reaching the goal with the last block still counts
+                        if solved {
+                            puzzles.solve();
+                            self.game_state = GameStates::PuzzleOver(true);
+                        } else if !topped_out.is_empty() {
+                            self.game_state = GameStates::PuzzleOver(false);
                         }
-                        let mut rng = rand::thread_rng();
-                        let block_type: u8 = rng.gen_range(1,7); // generates
-                        self.block = Block::new(Vector2::new(START_X, START_Y), block_type);
-                        let added_points = self.tile_map.check_full_rows();
-                        if added_points > 0 {
-                            let _ = self.sound_remove_row.play();
-                            self.points += added_points; 
+                    } else if self.settings.versus {
+                        if !topped_out.is_empty() {
+                            let winner = (0..self.boards.len()).find(|i| !topped_out.contains(i));
+                            self.game_state = GameStates::VersusOver(winner);
                         }
-                        
+                    } else if let Some(top_out) = self.boards[0].engine.top_out() {
+                        self.game_state = GameStates::GameOver(top_out);
+                    } else if self.boards[0].trainer.as_ref().is_some_and(|t| t.is_done()) {
+                        self.game_state = GameStates::TrainerOver;
                     }
-                }),
+                },
             };
 
+            self.update_network();
+
+            for board in self.boards.iter_mut() {
+                board.update_graphics(dt);
+            }
+
             // If we updated, we set our last_update to be now
             self.last_update = Instant::now();
         }
@@ -603,41 +1158,115 @@ impl event::EventHandler for GameState {
 
     /// draw is where we should actually render the game's current state.
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, [0.2, 0.3, 0.6, 1.0].into());
+        graphics::clear(ctx, theme::rgb(self.skin.theme.background));
+        if let Some(image) = self.skin.background_image.as_ref() {
+            let scale = mint::Vector2 {
+                x: self.screen.size.x as f32 / image.width() as f32,
+                y: self.screen.size.y as f32 / image.height() as f32,
+            };
+            graphics::draw(ctx, image, graphics::DrawParam::new().scale(scale))?;
+        }
 
-        // Draw tile_map.
-        self.tile_map.draw(ctx)?;
+        // Draw tile_map, unless it is hidden to prevent planning ahead while paused.
+        let board_hidden = matches!(self.game_state, GameStates::Pause) && self.settings.hide_board_on_pause;
+        if !board_hidden {
+            for board in self.boards.iter_mut() {
+                board.draw(ctx)?;
+            }
+            // Statistics come from the events of local boards, not from network updates
+            if self.show_stats {
+                let local = match (&self.network, &self.settings.spectate) {
+                    (_, Some(_)) => 0,
+                    (Some(_), None) => 1,
+                    (None, None) => self.boards.len(),
+                };
+                for board in self.boards.iter().take(local) {
+                    board.draw_stats(ctx, &self.settings.stats, &self.skin)?;
+                }
+            }
+        }
         let dest_point = mint::Vector2 { x: (0.0), y: (0.0) };
         graphics::draw(ctx, &self.text, (dest_point,))?;
 
         match self.game_state {
-            GameStates::GameOver => Some({
-                let dest_point = mint::Vector2 {
-                    x: (Screen::get_center().x as u32 - (0.5 * self.text_game_over.width(ctx) as f32) as u32) as f32,
-                    y: (Screen::get_center().y as u32 - self.text_game_over.height(ctx)) as f32,
+            GameStates::GameOver(top_out) => {
+                self.draw_game_over(ctx, &self.text_game_over, top_out.description().to_string())?;
+                self.draw_finesse_summary(ctx)?;
+            },
+            GameStates::TrainerOver => {
+                let text_done = self.text("TRAINING DONE", self.skin.hud.good, 100.0);
+                let hits = self.boards[0].trainer.as_ref().map_or(0, |t| t.hits);
+                let reason = format!("{}/{} blocks on target, {} finesse faults",
+                                     hits, TRAINER_PIECES, self.boards[0].finesse.total_faults());
+                self.draw_game_over(ctx, &text_done, reason)?;
+                self.draw_finesse_summary(ctx)?;
+            },
+            GameStates::PuzzleOver(solved) => {
+                let (headline, color) = match solved {
+                    true => ("SOLVED", self.skin.hud.good),
+                    false => ("FAILED", self.skin.hud.bad),
                 };
-                graphics::draw(ctx, &self.text_game_over, (dest_point,))?;
-
-                let dest_point = mint::Vector2 {
-                    x: (Screen::get_center().x as u32 - (0.5 * self.text_try_again.width(ctx) as f32) as u32) as f32,
-                    y: (Screen::get_center().y as u32 + 50) as f32,
+                let text_headline = self.text(headline, color, 100.0);
+                let reason = match (solved, self.boards[0].engine.top_out()) {
+                    (true, _) => "Y plays the next puzzle".to_string(),
+                    (false, Some(top_out)) => top_out.description().to_string(),
+                    (false, None) => String::new(),
+                };
+                self.draw_game_over(ctx, &text_headline, reason)?;
+            },
+            GameStates::VersusOver(winner) => {
+                let winner = match winner {
+                    Some(i) => format!("PLAYER {} WINS", i + 1),
+                    None => "DRAW".to_string(),
                 };
-                graphics::draw(ctx, &self.text_try_again, (dest_point,))?;
-            }),
-            GameStates::Pause => Some({
+                let text_winner = self.text(winner, self.skin.hud.warning, 100.0);
+                let reasons: Vec<String> = self.boards.iter().enumerate()
+                    .filter_map(|(i, b)| b.engine.top_out().map(|t| format!("Player {}: {}", i + 1, t.description())))
+                    .collect();
+                self.draw_game_over(ctx, &text_winner, reasons.join(", "))?;
+            },
+            GameStates::Pause => {
                 let dest_point = mint::Vector2 {
-                    x: (Screen::get_size().x as u32 - self.text_game_over.width(ctx)) as f32,
-                    y: (Screen::get_size().y as u32 - self.text_game_over.height(ctx)) as f32,
+                    x: self.screen.right_aligned_x(self.text_game_over.width(ctx) as f32),
+                    y: (self.screen.size.y as f32 - self.text_game_over.height(ctx) as f32).max(0.0),
                 };
                 graphics::draw(ctx, &self.text_pause, (dest_point,))?;
-            }),
-            _ => None,
+            },
+            GameStates::Countdown => {
+                self.draw_countdown(ctx)?;
+                self.draw_puzzle_header(ctx)?;
+                self.draw_practice_header(ctx)?;
+            },
+            GameStates::Waiting(ref reason) => {
+                let text_waiting = self.text(reason.clone(), self.skin.hud.text, 60.0);
+                let dest_point = mint::Vector2 {
+                    x: self.screen.centered_x(text_waiting.width(ctx) as f32),
+                    y: self.screen.centered_y(text_waiting.height(ctx) as f32),
+                };
+                graphics::draw(ctx, &text_waiting, (dest_point,))?;
+            },
+            GameStates::Editing => {
+                self.draw_editor(ctx)?;
+            },
+            GameStates::GameOn if self.boards[0].trainer.is_some() => {
+                self.draw_trainer_message(ctx)?;
+            },
+            GameStates::GameOn if self.puzzles.is_some() => {
+                self.draw_puzzle_header(ctx)?;
+            },
+            GameStates::GameOn if self.boards[0].practice.is_some() => {
+                self.draw_practice_header(ctx)?;
+            },
+            _ => {},
         };
+        if matches!(self.game_state, GameStates::GameOn) && self.showing_go() {
+            self.draw_countdown(ctx)?;
+        }
 
         if DEBUG_ON {
             let dest_point = mint::Vector2 {
                 x: 0.0,
-                y: Screen::get_size().y as f32 - self.text_debug.height(ctx) as f32,
+                y: self.screen.size.y as f32 - self.text_debug.height(ctx) as f32,
             };
             graphics::draw(ctx, &self.text_debug, (dest_point,))?;
         }
@@ -654,67 +1283,93 @@ impl event::EventHandler for GameState {
 
     /// key_down_event gets fired when a key gets pressed.
     fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods, _repeat: bool) {
-        _ctx.continuing = match keycode {
-            KeyCode::Q | KeyCode::Escape => false,
-            _ => true,
-        };
+        _ctx.continuing = !matches!(keycode, KeyCode::Q | KeyCode::Escape);
+
+        if let (GameStates::Editing, Some(editing)) = (&self.game_state, self.editing.as_mut()) {
+            if editing.key_down(keycode, _keymod) {
+                return;
+            }
+        }
         
-        if !self.block.down {
-            match keycode {
-                KeyCode::Left => Some({
-                    self.block._delete_from_tile_map(&mut self.tile_map);
-                    self.block.position.x -= 1;
-                    self.block.test_position(&mut self.tile_map);
-                }),
-                KeyCode::Right => Some({
-                    self.block._delete_from_tile_map(&mut self.tile_map);
-                    self.block.position.x += 1;
-                    self.block.test_position(&mut self.tile_map);
-                }),
-                KeyCode::Down => Some({
-                    self.block.moving_down = true;
-                }),
-                KeyCode::Up | KeyCode::Space => Some({
-                    self.block._delete_from_tile_map(&mut self.tile_map);
-                    self.block.previous_rotation = self.block.rotation;
-                    self.block.rotation = self.block.rotation.next();
-                    self.block.test_rotation(&mut self.tile_map);
-                }),
-                _ => None,
-            };
+        // The block can be moved only while the game is on, not while paused or counting down
+        if matches!(self.game_state, GameStates::GameOn) {
+            for board in self.boards.iter_mut() {
+                board.key_down(keycode);
+            }
         }
 
         match keycode {
-                KeyCode::P => Some(
-                    self.game_state = match self.game_state {
-                        GameStates::GameOn => GameStates::Pause,
-                        _ => GameStates::GameOn,
-                    },
-                ),
-                KeyCode::M => Some({
+                // Network games can't be paused
+                KeyCode::P if self.network.is_none() => {
+                    match self.game_state {
+                        GameStates::GameOn | GameStates::Countdown => self.game_state = GameStates::Pause,
+                        // Resuming from pause goes through the countdown
+                        GameStates::Pause => self.start_countdown(),
+                        _ => {},
+                    }
+                },
+                // Debug key for testing garbage
+                KeyCode::G if DEBUG_ON => {
+                    for board in self.boards.iter_mut() {
+                        board.engine.receive_garbage(1);
+                    }
+                },
+                KeyCode::Tab => self.show_stats = !self.show_stats,
+                KeyCode::F2 => self.next_theme(_ctx),
+                KeyCode::F3 => self.next_palette(_ctx),
+                KeyCode::F11 => self.toggle_fullscreen(_ctx),
+                KeyCode::F4 => {
+                    self.settings.patterns = !self.settings.patterns;
+                    for board in self.boards.iter_mut() {
+                        board.renderer.patterns = self.settings.patterns;
+                    }
+                },
+                // Starts the opener over without counting the attempt
+                KeyCode::R if matches!(self.game_state, GameStates::GameOn) => self.boards[0].retry_practice(),
+                KeyCode::M => {
                     self.music_on = !self.music_on;
 
                     match self.music_on {
-                        true => Some({
-                            self.music.resume();
-                        }),
-                        false => Some({
-                            self.music.pause();
-                        }),
+                        true => self.music.resume(),
+                        false => self.music.pause(),
                     };
-                }),
-                _ => None,
+                },
+                _ => {},
         };
-        match self.game_state {
-            GameStates::GameOver => Some({
-                match keycode {
-                    KeyCode::N => Some(_ctx.continuing = false),
-                    KeyCode::Y => Some(self.game_state = GameStates::Restart),
-                    _ => None,
-                };
-            }),
-            _ => None,
-        };      
+        if let GameStates::GameOver(_) | GameStates::VersusOver(_) | GameStates::TrainerOver
+            | GameStates::PuzzleOver(_) = self.game_state {
+            match keycode {
+                KeyCode::N => _ctx.continuing = false,
+                KeyCode::Y => {
+                    // A solved puzzle is followed by the next one, a failed one is tried again
+                    if let (GameStates::PuzzleOver(true), Some(puzzles)) = (&self.game_state, self.puzzles.as_mut()) {
+                        puzzles.next();
+                    }
+                    self.game_state = GameStates::Restart;
+                },
+                _ => {},
+            };
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        // Everything painted until the button is let go is undone at once
+        if let Some(editing) = self.editing.as_mut() {
+            editing.editor.start_stroke();
+        }
+        self.paint(x, y, button);
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        for button in [MouseButton::Left, MouseButton::Right] {
+            if mouse::button_pressed(_ctx, button) {
+                self.paint(x, y, button);
+            }
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.resize(ctx, width, height);
     }
 }
 
@@ -727,26 +1382,34 @@ fn main() -> GameResult {
         path::PathBuf::from("./resources")
     };
 
+    let settings = Settings::from_args();
+    let (width, height) = DEFAULT_SCREEN_SIZE;
+    let (min_width, min_height) = MIN_SCREEN_SIZE;
+
     // Here we use a ContextBuilder to setup metadata about our game. First the title and author
     let (ctx, events_loop) = &mut ggez::ContextBuilder::new("Jetris", "jotalamp")
         // Next we set up the window. This title will be displayed in the title bar of the window.
         .window_setup(ggez::conf::WindowSetup::default().title("Jetris"))
         .window_mode(
             ggez::conf::WindowMode::default()
-                .dimensions(Screen::get_size().x.into(), Screen::get_size().y.into()),
+                .dimensions(width, height)
+                .min_dimensions(min_width, min_height)
+                .resizable(true),
         )
         // And finally we attempt to build the context and create the window. If it fails, we panic with the message
         // "Failed to build ggez context"
         .add_resource_path(resource_dir)
         .build()?;
 
-    // Fullscreen (set right resolution to Screen::get_size())
-    //let window = graphics::window(ctx);
-    //let monitor = window.get_current_monitor();
-    //window.set_fullscreen(Some(monitor));
-
     // Next we create a new instance of our GameState struct, which implements EventHandler
-    let state = &mut GameState::new(ctx)?;
+    let start_fullscreen = settings.start_fullscreen;
+    let state = &mut GameState::new(ctx, settings)?;
+    // The window may not have got the size it asked for
+    let (width, height) = graphics::drawable_size(ctx);
+    state.resize(ctx, width, height);
+    if start_fullscreen {
+        state.toggle_fullscreen(ctx);
+    }
 
     // And finally we actually run our game, passing in our context and state.
     event::run(ctx, events_loop, state)
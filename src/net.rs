@@ -0,0 +1,5 @@
+// Networked versus over TCP. Messages are JSON, one per line.
+
+pub mod client;
+pub mod protocol;
+pub mod server;
@@ -0,0 +1,176 @@
+use std::io::{self, BufReader};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::net::protocol::{read_message, write_message, ClientMessage, ServerMessage, PROTOCOL_VERSION};
+
+// Connecting is retried from the game loop, so an unreachable server may only hold
+// up a frame or two
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Connection from a player to the versus server. Messages are read on a
+/// background thread, so polling never blocks.
+pub struct Client {
+    address: String,
+    name: String,
+    session: Option<u64>,
+    player: Option<usize>,
+    spectator: bool,
+    // None while disconnected
+    writer: Option<TcpStream>,
+    // None from the reading thread means the connection was closed
+    receiver: Receiver<Option<ServerMessage>>,
+}
+
+impl Client {
+    /// Client for the server at 'address', not connected yet
+    pub fn new(address: &str, name: &str) -> Self {
+        // Nothing is ever received from this channel, 'connect' replaces it
+        let (_, receiver) = mpsc::channel();
+        Client {
+            address: address.to_string(),
+            name: name.to_string(),
+            session: None,
+            player: None,
+            spectator: false,
+            writer: None,
+            receiver,
+        }
+    }
+
+    /// Client which watches a match instead of playing, not connected yet
+    pub fn spectator(address: &str, name: &str) -> Self {
+        let mut client = Client::new(address, name);
+        client.spectator = true;
+        client
+    }
+
+    pub fn is_spectator(&self) -> bool {
+        self.spectator
+    }
+
+    /// Connects to the server and says hello. After the first connection the
+    /// session is sent along, which takes the player back to the same match.
+    pub fn connect(&mut self) -> io::Result<()> {
+        self.disconnect();
+
+        let stream = connect_timeout(&self.address)?;
+        stream.set_nodelay(true)?;
+        let mut writer = stream.try_clone()?;
+        write_message(&mut writer, &ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: self.name.clone(),
+            session: self.session,
+            spectator: self.spectator,
+        })?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(Some(message)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(None);
+        });
+
+        self.writer = Some(writer);
+        self.receiver = receiver;
+        Ok(())
+    }
+
+    /// Closes the connection as if it was lost, the session can still be used to reconnect
+    pub fn disconnect(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Index of this player in the match, known after the server welcomed us
+    pub fn player(&self) -> Option<usize> {
+        self.player
+    }
+
+    pub fn session(&self) -> Option<u64> {
+        self.session
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let result = match self.writer.as_mut() {
+            Some(writer) => write_message(writer, message),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "not connected")),
+        };
+        if result.is_err() {
+            self.writer = None;
+        }
+        result
+    }
+
+    /// Messages received since the last call
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Some(message)) => {
+                    self.handle(&message);
+                    messages.push(message);
+                },
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    self.writer = None;
+                    break;
+                },
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        messages
+    }
+
+    /// Waits for the next message at most 'timeout'
+    pub fn wait(&mut self, timeout: Duration) -> Option<ServerMessage> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(Some(message)) => {
+                self.handle(&message);
+                Some(message)
+            },
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                self.writer = None;
+                None
+            },
+            Err(RecvTimeoutError::Timeout) => None,
+        }
+    }
+
+    // Remembers who we are so we can reconnect
+    fn handle(&mut self, message: &ServerMessage) {
+        if let ServerMessage::Welcome { player, session } = message {
+            self.player = Some(*player);
+            self.session = Some(*session);
+        }
+    }
+}
+
+impl Drop for Client {
+    // Also ends the reading thread, which would otherwise keep the connection open
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+// Tries each address 'address' resolves to, waiting at most CONNECT_TIMEOUT for each
+fn connect_timeout(address: &str) -> io::Result<TcpStream> {
+    let mut error = io::Error::new(io::ErrorKind::InvalidInput, "address resolves to nothing");
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use std::io::{self, BufRead, Write};
+
+use crate::engine::Engine;
+use crate::tile_map::TileMap;
+use crate::Vector2;
+
+/// Bumped whenever messages change so old clients and servers refuse each other
+pub const PROTOCOL_VERSION: u32 = 4;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Snapshot of a board for drawing it on the other side.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoardState {
+    pub width: i16,
+    pub height: i16,
+    pub hidden_rows: i16,
+    /// Cells row by row from the top, see TileMap::cells
+    pub cells: Vec<i16>,
+    pub points: u32,
+    pub pending_garbage: u32,
+    pub preview: Vec<u8>,
+    pub hold: Option<u8>,
+}
+
+impl BoardState {
+    pub fn from_engine(engine: &Engine) -> Self {
+        let tile_map = &engine.tile_map;
+        BoardState {
+            width: tile_map.size.x,
+            height: tile_map.size.y,
+            hidden_rows: tile_map.hidden_rows,
+            cells: tile_map.cells(),
+            points: engine.points,
+            pending_garbage: engine.pending_garbage,
+            preview: engine.preview().iter().copied().collect(),
+            hold: engine.held(),
+        }
+    }
+
+    /// None if the cells don't match the size
+    pub fn to_tile_map(&self) -> Option<TileMap> {
+        TileMap::from_cells(Vector2::new(self.width, self.height), self.hidden_rows, &self.cells)
+    }
+}
+
+/// Messages from a player to the server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// First message on every connection. 'session' is given when reconnecting.
+    /// Spectators only receive the boards of a running match.
+    Hello { version: u32, name: String, session: Option<u64>, spectator: bool },
+    /// Current state of the player's own board
+    Board { state: BoardState },
+    /// Garbage rows sent to the opponent, after cancelling own pending garbage
+    Attack { rows: u32 },
+    /// The player topped out
+    TopOut,
+}
+
+/// Messages from the server to a player or a spectator. Spectators get MatchStart,
+/// Board and MatchOver, delayed so they can't help a player.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Connection accepted. 'session' is used to reconnect to the same match.
+    Welcome { player: usize, session: u64 },
+    /// Connection refused, e.g. because of a different protocol version
+    Rejected { reason: String },
+    /// Both players are connected and the match begins
+    MatchStart { names: Vec<String> },
+    /// Board of a player changed
+    Board { player: usize, state: BoardState },
+    /// Garbage rows sent by the opponent
+    Attack { rows: u32 },
+    OpponentDisconnected,
+    OpponentReconnected,
+    /// The match goes on after the player reconnected
+    Resume,
+    /// Match ended, with the winning player or None for a draw
+    MatchOver { winner: Option<usize> },
+}
+
+/// Writes a message as one line of JSON
+pub fn write_message<W: Write, M: Serialize>(writer: &mut W, message: &M) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+/// Reads one line of JSON. Returns Ok(None) when the connection was closed.
+pub fn read_message<R: BufRead, M: for<'de> Deserialize<'de>>(reader: &mut R) -> io::Result<Option<M>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let message = serde_json::from_str(line.trim_end())?;
+    Ok(Some(message))
+}
@@ -0,0 +1,426 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::net::protocol::{read_message, write_message, ClientMessage, ServerMessage, PROTOCOL_VERSION};
+
+/// How long a disconnected player may take to reconnect before losing the match
+pub const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How much later than the players spectators see the boards
+pub const SPECTATOR_DELAY: Duration = Duration::from_secs(3);
+
+// How often delayed messages are sent and disconnected players checked for timeouts
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+// Slow clients are dropped instead of piling up messages
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Messages to one connection, written by a thread of its own so nothing is written
+// while the server state is locked. Once the last sender is dropped or a write fails
+// the connection is shut down, which also lets spectators know the match is gone.
+#[derive(Clone)]
+struct Outbox(Sender<ServerMessage>);
+
+impl Outbox {
+    fn spawn(mut stream: TcpStream) -> Self {
+        let (sender, receiver) = mpsc::channel::<ServerMessage>();
+        thread::spawn(move || {
+            for message in receiver {
+                if write_message(&mut stream, &message).is_err() {
+                    break;
+                }
+            }
+            let _ = stream.shutdown(Shutdown::Both);
+        });
+        Outbox(sender)
+    }
+
+    // False if the connection is gone
+    fn send(&self, message: &ServerMessage) -> bool {
+        self.0.send(message.clone()).is_ok()
+    }
+}
+
+struct Player {
+    name: String,
+    session: u64,
+    // Id of the current connection, so an old connection closing late doesn't
+    // disconnect a player who already reconnected
+    connection: u64,
+    // None while disconnected
+    writer: Option<Outbox>,
+    disconnected_at: Option<Instant>,
+}
+
+impl Player {
+    fn send(&self, message: &ServerMessage) {
+        if let Some(writer) = self.writer.as_ref() {
+            // A failed write shows up as a closed connection in the reading thread
+            writer.send(message);
+        }
+    }
+}
+
+struct Spectator {
+    connection: u64,
+    writer: Outbox,
+}
+
+// Two players playing against each other, and anybody watching
+struct Match {
+    players: Vec<Player>,
+    spectators: Vec<Spectator>,
+    // Messages for spectators and when they may be sent
+    delayed: VecDeque<(Instant, ServerMessage)>,
+    spectator_delay: Duration,
+    started: bool,
+    over: bool,
+    winner: Option<usize>,
+}
+
+impl Match {
+    fn new(spectator_delay: Duration) -> Self {
+        Match {
+            players: Vec::new(),
+            spectators: Vec::new(),
+            delayed: VecDeque::new(),
+            spectator_delay,
+            started: false,
+            over: false,
+            winner: None,
+        }
+    }
+
+    fn send_to_all(&mut self, message: &ServerMessage) {
+        for player in &self.players {
+            player.send(message);
+        }
+        self.send_to_spectators(message);
+    }
+
+    fn send_to_spectators(&mut self, message: &ServerMessage) {
+        self.delayed.push_back((Instant::now() + self.spectator_delay, message.clone()));
+    }
+
+    // Sends the delayed messages whose time has come. Spectators who can't keep up are dropped.
+    fn flush_spectators(&mut self) {
+        while self.delayed.front().is_some_and(|(time, _)| *time <= Instant::now()) {
+            let (_, message) = self.delayed.pop_front().unwrap();
+            self.spectators.retain(|s| s.writer.send(&message));
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.players.iter().map(|p| p.name.clone()).collect()
+    }
+
+    fn send_to_opponent(&mut self, player: usize, message: &ServerMessage) {
+        let opponent = 1 - player;
+        if let Some(opponent) = self.players.get(opponent) {
+            opponent.send(message);
+        }
+    }
+
+    fn end(&mut self, winner: Option<usize>) {
+        if !self.over {
+            self.over = true;
+            self.winner = winner;
+            self.send_to_all(&ServerMessage::MatchOver { winner });
+        }
+    }
+}
+
+struct State {
+    matches: HashMap<u64, Match>,
+    next_id: u64,
+    reconnect_timeout: Duration,
+    spectator_delay: Duration,
+}
+
+impl State {
+    fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+/// Versus server which pairs connecting players into matches and relays boards and
+/// attacks between them. Cloning gives another handle to the same server.
+#[derive(Clone)]
+pub struct Server {
+    state: Arc<Mutex<State>>,
+}
+
+impl Server {
+    pub fn new(reconnect_timeout: Duration, spectator_delay: Duration) -> Self {
+        let state = State {
+            matches: HashMap::new(),
+            next_id: 0,
+            reconnect_timeout,
+            spectator_delay,
+        };
+        Server { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Accepts connections until the listener fails, each connection on its own thread
+    pub fn run(&self, listener: TcpListener) -> io::Result<()> {
+        let server = self.clone();
+        thread::spawn(move || server.tick());
+
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || server.handle_connection(stream));
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) {
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => return,
+        };
+        let mut reader = BufReader::new(stream);
+
+        let (name, session, spectator) = match read_message(&mut reader) {
+            Ok(Some(ClientMessage::Hello { version, name, session, spectator })) => {
+                if version != PROTOCOL_VERSION {
+                    let reason = format!("Protocol version {} is not supported, server uses {}", version, PROTOCOL_VERSION);
+                    let _ = write_message(&mut writer, &ServerMessage::Rejected { reason });
+                    return;
+                }
+                (name, session, spectator)
+            },
+            _ => {
+                let reason = "Expected hello".to_string();
+                let _ = write_message(&mut writer, &ServerMessage::Rejected { reason });
+                return;
+            },
+        };
+
+        let writer = Outbox::spawn(writer);
+        if spectator {
+            let (match_id, connection) = self.watch(writer);
+            // Spectators have nothing to say, just wait for them to leave
+            while let Ok(Some(_)) = read_message::<_, ClientMessage>(&mut reader) {}
+            self.stop_watching(match_id, connection);
+            return;
+        }
+
+        let (match_id, player, connection) = match self.join(name, session, writer) {
+            Some(joined) => joined,
+            None => return,
+        };
+
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            self.handle_message(match_id, player, message);
+        }
+
+        self.disconnect(match_id, player, connection);
+    }
+
+    // Adds a player to a match waiting for an opponent, or a new match. With a session
+    // the player goes back to the match they were in. Returns match id, player index
+    // and connection id, or None if the session is unknown.
+    fn join(&self, name: String, session: Option<u64>, writer: Outbox) -> Option<(u64, usize, u64)> {
+        let mut state = self.state.lock().unwrap();
+        let connection = state.new_id();
+
+        if let Some(session) = session {
+            let found = state.matches.iter_mut()
+                .find_map(|(&id, m)| m.players.iter().position(|p| p.session == session).map(|i| (id, i)));
+            let (match_id, player) = match found {
+                Some(found) => found,
+                None => {
+                    let reason = "Unknown session".to_string();
+                    writer.send(&ServerMessage::Rejected { reason });
+                    return None;
+                },
+            };
+
+            let m = state.matches.get_mut(&match_id).unwrap();
+            let p = &mut m.players[player];
+            p.writer = Some(writer);
+            p.connection = connection;
+            p.disconnected_at = None;
+            p.send(&ServerMessage::Welcome { player, session });
+            if m.over {
+                let winner = m.winner;
+                m.players[player].send(&ServerMessage::MatchOver { winner });
+            } else if m.players[1 - player].writer.is_some() {
+                m.players[player].send(&ServerMessage::Resume);
+                m.send_to_opponent(player, &ServerMessage::OpponentReconnected);
+            } else {
+                m.players[player].send(&ServerMessage::OpponentDisconnected);
+            }
+            return Some((match_id, player, connection));
+        }
+
+        let session = rand::thread_rng().gen::<u64>();
+        let waiting = state.matches.iter()
+            .find(|(_, m)| !m.started && m.players.len() < 2)
+            .map(|(&id, _)| id);
+        let match_id = match waiting {
+            Some(id) => id,
+            None => {
+                let id = state.new_id();
+                let spectator_delay = state.spectator_delay;
+                state.matches.insert(id, Match::new(spectator_delay));
+                id
+            },
+        };
+
+        let m = state.matches.get_mut(&match_id).unwrap();
+        let player = m.players.len();
+        m.players.push(Player {
+            name,
+            session,
+            connection,
+            writer: Some(writer),
+            disconnected_at: None,
+        });
+        m.players[player].send(&ServerMessage::Welcome { player, session });
+
+        if m.players.len() == 2 {
+            m.started = true;
+            let names = m.names();
+            m.send_to_all(&ServerMessage::MatchStart { names });
+        }
+
+        Some((match_id, player, connection))
+    }
+
+    // Adds a spectator to the oldest running match, or the match waiting for players.
+    // Returns match id and connection id.
+    fn watch(&self, writer: Outbox) -> (u64, u64) {
+        let mut state = self.state.lock().unwrap();
+        let connection = state.new_id();
+
+        let running = state.matches.iter()
+            .filter(|(_, m)| m.started && !m.over)
+            .map(|(&id, _)| id)
+            .min();
+        let waiting = state.matches.iter()
+            .filter(|(_, m)| !m.started)
+            .map(|(&id, _)| id)
+            .min();
+        let match_id = match running.or(waiting) {
+            Some(id) => id,
+            None => {
+                let id = state.new_id();
+                let spectator_delay = state.spectator_delay;
+                state.matches.insert(id, Match::new(spectator_delay));
+                id
+            },
+        };
+
+        let m = state.matches.get_mut(&match_id).unwrap();
+        // Who is playing is no secret, the boards come later. Unless the start is
+        // still waiting to be sent to all spectators.
+        let start_delayed = m.delayed.iter().any(|(_, message)| matches!(message, ServerMessage::MatchStart { .. }));
+        if m.started && !start_delayed {
+            writer.send(&ServerMessage::MatchStart { names: m.names() });
+        }
+        m.spectators.push(Spectator { connection, writer });
+
+        (match_id, connection)
+    }
+
+    fn stop_watching(&self, match_id: u64, connection: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(m) = state.matches.get_mut(&match_id) {
+            m.spectators.retain(|s| s.connection != connection);
+        }
+    }
+
+    fn handle_message(&self, match_id: u64, player: usize, message: ClientMessage) {
+        let mut state = self.state.lock().unwrap();
+        let m = match state.matches.get_mut(&match_id) {
+            Some(m) => m,
+            None => return,
+        };
+        if !m.started || m.over {
+            return;
+        }
+
+        match message {
+            ClientMessage::Board { state } => {
+                let message = ServerMessage::Board { player, state };
+                m.send_to_opponent(player, &message);
+                m.send_to_spectators(&message);
+            },
+            ClientMessage::Attack { rows } => {
+                m.send_to_opponent(player, &ServerMessage::Attack { rows });
+            },
+            ClientMessage::TopOut => {
+                m.end(Some(1 - player));
+            },
+            ClientMessage::Hello { .. } => {},
+        }
+    }
+
+    fn disconnect(&self, match_id: u64, player: usize, connection: u64) {
+        let mut state = self.state.lock().unwrap();
+        let m = match state.matches.get_mut(&match_id) {
+            Some(m) => m,
+            None => return,
+        };
+        if m.players.get(player).is_none_or(|p| p.connection != connection) {
+            return;
+        }
+
+        // Nobody to play against yet, forget the player
+        if !m.started {
+            m.players.clear();
+            return;
+        }
+
+        let p = &mut m.players[player];
+        p.writer = None;
+        p.disconnected_at = Some(Instant::now());
+        if !m.over {
+            m.send_to_opponent(player, &ServerMessage::OpponentDisconnected);
+        }
+    }
+
+    // Sends delayed messages to spectators and ends matches of players who don't reconnect
+    // in time. Matches are removed when they are over or nobody is waiting in them anymore.
+    fn tick(&self) {
+        loop {
+            thread::sleep(TICK_INTERVAL);
+
+            let mut state = self.state.lock().unwrap();
+            let timeout = state.reconnect_timeout;
+            for m in state.matches.values_mut() {
+                if !m.started || m.over {
+                    continue;
+                }
+                let timed_out: Vec<usize> = (0..m.players.len())
+                    .filter(|&i| m.players[i].disconnected_at.is_some_and(|t| t.elapsed() >= timeout))
+                    .collect();
+                match timed_out.len() {
+                    0 => {},
+                    1 => m.end(Some(1 - timed_out[0])),
+                    _ => m.end(None),
+                }
+            }
+
+            for m in state.matches.values_mut() {
+                m.flush_spectators();
+            }
+            state.matches.retain(|_, m| {
+                let left = m.players.iter().all(|p| p.writer.is_none());
+                let finished = m.over && left && m.delayed.is_empty();
+                let abandoned = !m.started && m.players.is_empty() && m.spectators.is_empty();
+                !(finished || abandoned)
+            });
+        }
+    }
+}
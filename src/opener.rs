@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::engine::{Engine, Rules, BUFFER_ROWS, MAX_BOARD_WIDTH, MIN_BOARD_WIDTH};
+use crate::finesse::same_cells;
+use crate::tbp::Piece;
+use crate::tile_map::{TileMap, GARBAGE_TILE};
+use crate::Vector2;
+
+// Labels of the blocks in the field, in the order they are placed
+const LABELS: &str = "123456789abcdefghijklmnopqrstuvwxyz";
+
+/// An opener or perfect clear to practice: the finished setup with the place of
+/// every block, and the blocks in the order they are placed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Opener {
+    pub name: String,
+    /// Letters of the blocks in order, e.g. "JIOZLST"
+    pub pieces: String,
+    /// Rows of the finished setup from top to bottom: '.' is empty, 'X' garbage which
+    /// is there from the start, and '1' to '9' and then 'a' to 'z' the cells of the
+    /// first, second and later blocks
+    pub field: Vec<String>,
+}
+
+impl Opener {
+    /// Reads an opener from a JSON file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let opener: Opener = serde_json::from_str(&json).map_err(io::Error::other)?;
+        opener.validate().map_err(io::Error::other)?;
+        Ok(opener)
+    }
+
+    /// Checks that every block has a place of four cells
+    pub fn validate(&self) -> Result<(), String> {
+        let width = self.field.first().map_or(0, |row| row.chars().count());
+        if !(MIN_BOARD_WIDTH as usize..=MAX_BOARD_WIDTH as usize).contains(&width) {
+            return Err(format!("field must be {} to {} wide", MIN_BOARD_WIDTH, MAX_BOARD_WIDTH));
+        }
+        if self.field.iter().any(|row| row.chars().count() != width) {
+            return Err("rows are not all the same width".to_string());
+        }
+        if let Some(c) = self.field.iter().flat_map(|row| row.chars())
+            .find(|&c| c != '.' && c != 'X' && label_step(c).is_none_or(|step| step >= self.pieces.len())) {
+            return Err(format!("unknown cell '{}'", c));
+        }
+        if let Some(c) = self.pieces.chars().find(|&c| Piece::from_letter(c).is_none()) {
+            return Err(format!("unknown piece '{}'", c));
+        }
+        match self.targets().iter().position(|cells| cells.len() != 4) {
+            Some(step) => Err(format!("block {} doesn't have 4 cells", step + 1)),
+            None if self.pieces.is_empty() => Err("no pieces".to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Rules for the opener's board, hold is off as the order is fixed
+    pub fn rules(&self) -> Rules {
+        Rules {
+            board_width: self.field.first().map_or(0, |row| row.chars().count()) as i16,
+            hold: false,
+            ..Rules::default()
+        }
+    }
+
+    /// Cells of each block in the tile map of the board, in order
+    pub fn targets(&self) -> Vec<Vec<Vector2>> {
+        let mut targets = vec![Vec::new(); self.pieces.chars().count()];
+        self.cells(|position, c| {
+            if let Some(cells) = label_step(c).and_then(|step| targets.get_mut(step)) {
+                cells.push(position);
+            }
+        });
+        targets
+    }
+
+    /// Engine with the garbage of the setup and the blocks in order
+    pub fn engine(&self) -> Engine {
+        let rules = self.rules();
+        let size = Vector2::new(rules.board_width + 2, BUFFER_ROWS + rules.board_height + 1);
+        let mut tile_map = TileMap::new(size, BUFFER_ROWS);
+        tile_map.add_walls();
+        self.cells(|position, c| {
+            if c == 'X' {
+                tile_map.set_cell(position.x, position.y, GARBAGE_TILE);
+            }
+        });
+
+        let blocks: Vec<u8> = self.pieces.chars().filter_map(Piece::from_letter).map(|p| p.block_type()).collect();
+        let mut engine = Engine::new(rules);
+        engine.play_sequence(&blocks);
+        engine.set_field(tile_map);
+        engine
+    }
+
+    // Calls 'f' with the tile map position and character of each cell, rows are
+    // bottom aligned like in puzzles
+    fn cells(&self, mut f: impl FnMut(Vector2, char)) {
+        let bottom = BUFFER_ROWS + self.rules().board_height - 1;
+        let top = bottom + 1 - self.field.len() as i16;
+        for (y, row) in self.field.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                f(Vector2::new(x as i16 + 1, top + y as i16), c);
+            }
+        }
+    }
+}
+
+// Index of the block a label belongs to
+fn label_step(c: char) -> Option<usize> {
+    LABELS.find(c)
+}
+
+/// Practice of one opener: each block has to go to its place, and an attempt starts
+/// over as soon as a block misses.
+#[derive(Debug, Clone)]
+pub struct Practice {
+    pub opener: Opener,
+    /// Block being placed, counted from 0
+    pub step: usize,
+    // Places of the blocks, moved down when rows clear on the way
+    targets: Vec<Vec<Vector2>>,
+    pub attempts: u32,
+    pub successes: u32,
+    /// Whether the last finished attempt succeeded
+    pub last: Option<bool>,
+    // Whether the running attempt has ended
+    over: bool,
+}
+
+impl Practice {
+    pub fn new(opener: Opener) -> Self {
+        let targets = opener.targets();
+        Practice { opener, step: 0, targets, attempts: 0, successes: 0, last: None, over: false }
+    }
+
+    /// Starts a new attempt and returns its engine
+    pub fn start(&mut self) -> Engine {
+        self.step = 0;
+        self.targets = self.opener.targets();
+        self.over = false;
+        self.opener.engine()
+    }
+
+    /// Place of the current block
+    pub fn target(&self) -> Option<&[Vector2]> {
+        self.targets.get(self.step).map(|cells| cells.as_slice())
+    }
+
+    /// Places of the blocks after the current one
+    pub fn later_targets(&self) -> &[Vec<Vector2>] {
+        self.targets.get(self.step + 1..).unwrap_or(&[])
+    }
+
+    /// Checks the block which locked on 'cells' against its place
+    pub fn lock(&mut self, cells: &[Vector2]) {
+        if self.over {
+            return;
+        }
+        let hit = self.target().is_some_and(|target| same_cells(target, cells));
+        if hit {
+            self.step += 1;
+        }
+        if !hit || self.step >= self.targets.len() {
+            self.attempts += 1;
+            if hit {
+                self.successes += 1;
+            }
+            self.last = Some(hit);
+            self.over = true;
+        }
+    }
+
+    /// Moves the places above cleared 'rows' down with the stack
+    pub fn clear(&mut self, rows: &[i16]) {
+        for cell in self.targets.iter_mut().flatten() {
+            cell.y += rows.iter().filter(|&&row| row > cell.y).count() as i16;
+        }
+    }
+
+    /// Whether the attempt has ended and the next one should start
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Share of attempts which succeeded, from 0.0 to 1.0
+    pub fn success_rate(&self) -> f32 {
+        if self.attempts > 0 { self.successes as f32 / self.attempts as f32 } else { 0.0 }
+    }
+}
@@ -0,0 +1,181 @@
+use array2d::Array2D;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::block::{Block, Rotation};
+use crate::Vector2;
+
+// Blocks are kept in a square grid of this size and rotate around its middle cell
+pub const SHAPE_SIZE: usize = 5;
+// Most pieces in a set, block types are u8 and tiles above them are taken
+const MAX_PIECES: usize = 200;
+
+fn default_random() -> bool {
+    true
+}
+
+/// One piece of a set: its cells, color and how it spawns.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PieceShape {
+    pub name: String,
+    /// Rows from top to bottom, '#' are cells of the piece and '.' empty
+    pub shape: Vec<String>,
+    /// Cell the piece rotates around as [column, row] of 'shape', the middle of
+    /// 'shape' if not given
+    #[serde(default)]
+    pub center: Option<[usize; 2]>,
+    /// Red, green and blue from 0.0 to 1.0
+    pub color: [f32; 3],
+    /// Clockwise quarter turns from 'shape' when the piece spawns
+    #[serde(default)]
+    pub spawn_rotation: u8,
+    /// Whether the randomizer deals the piece, fixed sequences can use it anyway
+    #[serde(default = "default_random")]
+    pub random: bool,
+}
+
+impl PieceShape {
+    fn new(name: &str, shape: [&str; SHAPE_SIZE], color: [f32; 3]) -> Self {
+        PieceShape {
+            name: name.to_string(),
+            shape: shape.iter().map(|row| row.to_string()).collect(),
+            center: None,
+            color,
+            spawn_rotation: 0,
+            random: true,
+        }
+    }
+
+    /// Cells in the grid of a block, None if the piece doesn't fit in it
+    pub fn grid(&self) -> Option<Array2D<u8>> {
+        let rows = self.shape.len();
+        let columns = self.shape.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let [center_x, center_y] = self.center.unwrap_or([columns / 2, rows / 2]);
+        let middle = SHAPE_SIZE / 2;
+
+        // Indexed (x, y) like Block::get_cell
+        let mut grid = Array2D::filled_with(0, SHAPE_SIZE, SHAPE_SIZE);
+        for (y, row) in self.shape.iter().enumerate() {
+            for (x, _) in row.chars().enumerate().filter(|&(_, c)| c == '#') {
+                let x = (x + middle).checked_sub(center_x).filter(|&x| x < SHAPE_SIZE)?;
+                let y = (y + middle).checked_sub(center_y).filter(|&y| y < SHAPE_SIZE)?;
+                grid[(x, y)] = 1;
+            }
+        }
+        Some(grid)
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.shape.iter().flat_map(|row| row.chars()).filter(|&c| c == '#').count()
+    }
+
+    /// Cells along the longer side of the piece, its width when turned the widest way
+    pub fn extent(&self) -> usize {
+        let cells: Vec<(usize, usize)> = self.shape.iter().enumerate()
+            .flat_map(|(y, row)| row.chars().enumerate().filter(|&(_, c)| c == '#').map(move |(x, _)| (x, y)))
+            .collect();
+        let span = |values: Vec<usize>| match (values.iter().min(), values.iter().max()) {
+            (Some(min), Some(max)) => max - min + 1,
+            _ => 0,
+        };
+        span(cells.iter().map(|c| c.0).collect()).max(span(cells.iter().map(|c| c.1).collect()))
+    }
+
+    pub fn spawn_rotation(&self) -> Rotation {
+        (0..self.spawn_rotation % 4).fold(Rotation::Cw0, |rotation, _| rotation.next())
+    }
+}
+
+/// Pieces of a game. The block type of a piece is its index in the set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PieceSet {
+    pub name: String,
+    pub pieces: Vec<PieceShape>,
+}
+
+impl PieceSet {
+    /// The seven tetrominoes in the order of their block types
+    pub fn standard() -> Self {
+        let pieces = vec![
+            PieceShape::new("O", [".....", ".....", "..##.", "..##.", "....."], [0.7, 0.7, 0.1]),
+            PieceShape::new("I", [".....", "..#..", "..#..", "..#..", "..#.."], [0.0, 0.7, 0.7]),
+            PieceShape::new("T", [".....", ".....", ".###.", "..#..", "....."], [0.5, 0.1, 0.8]),
+            PieceShape::new("S", [".....", "..#..", "..##.", "...#.", "....."], [0.1, 0.7, 0.0]),
+            PieceShape::new("Z", [".....", "...#.", "..##.", "..#..", "....."], [0.8, 0.1, 0.1]),
+            PieceShape::new("L", [".....", "..#..", "..#..", "..##.", "....."], [0.8, 0.4, 0.1]),
+            PieceShape::new("J", [".....", "..#..", "..#..", ".##..", "....."], [0.1, 0.3, 0.9]),
+        ];
+        PieceSet { name: "standard".to_string(), pieces }
+    }
+
+    /// Reads a piece set from a JSON file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let pieces: PieceSet = serde_json::from_str(&json).map_err(io::Error::other)?;
+        pieces.validate().map_err(io::Error::other)?;
+        Ok(pieces)
+    }
+
+    /// Checks that every piece fits in a block and something can be dealt
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pieces.is_empty() || self.pieces.len() > MAX_PIECES {
+            return Err(format!("a set needs 1 to {} pieces", MAX_PIECES));
+        }
+        for piece in &self.pieces {
+            match piece.grid() {
+                None => return Err(format!("piece {} doesn't fit in {}x{} around its center", piece.name, SHAPE_SIZE, SHAPE_SIZE)),
+                Some(_) if piece.cell_count() == 0 => return Err(format!("piece {} has no cells", piece.name)),
+                Some(_) => {},
+            }
+        }
+        if self.random_types().is_empty() {
+            return Err("no piece is dealt at random".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    /// Block types the randomizer deals, in order
+    pub fn random_types(&self) -> Vec<u8> {
+        (0..self.pieces.len()).filter(|&i| self.pieces[i].random).map(|i| i as u8).collect()
+    }
+
+    /// Block type of the T tetromino, for T-spins. None if the set has no T.
+    pub fn t_block(&self) -> Option<u8> {
+        self.pieces.iter()
+            .position(|piece| piece.name == "T" && piece.cell_count() == 4)
+            .map(|i| i as u8)
+    }
+
+    /// Narrowest board every piece fits on in any rotation
+    pub fn width(&self) -> i16 {
+        self.pieces.iter().map(PieceShape::extent).max().unwrap_or(0) as i16
+    }
+
+    pub fn name(&self, block_type: u8) -> &str {
+        self.pieces.get(block_type as usize).map_or("?", |piece| piece.name.as_str())
+    }
+
+    /// New block of 'block_type' at 'position'. Unknown types, e.g. from a board
+    /// played with another set, get the first piece's shape.
+    pub fn block(&self, position: Vector2, block_type: u8) -> Block {
+        let piece = self.pieces.get(block_type as usize).unwrap_or(&self.pieces[0]);
+        Block::new(position, block_type, piece)
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> Self {
+        PieceSet::standard()
+    }
+}
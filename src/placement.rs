@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::block::{Block, Rotation};
+use crate::engine::{self, Input};
+use crate::pieces::PieceSet;
+use crate::tile_map::TileMap;
+
+// Offsets tried in order when a rotated block overlaps the stack. Jetris rotation has
+// no kicks, the block only turns in place. The engine and the search below both rotate
+// with 'rotated', so kicks added here apply to both.
+const KICKS: [(i16, i16); 1] = [(0, 0)];
+
+/// Where a block ends up and how to get it there.
+#[derive(Debug, Clone)]
+pub struct Placement {
+    /// The block in its final position
+    pub block: Block,
+    /// Fewest inputs from the starting block, ending with a hard drop. Starts with a
+    /// hold when the held or next block is placed instead of the current one.
+    pub inputs: Vec<Input>,
+    /// Whether the last move before locking was a rotation, needed for T-spins
+    pub spin: bool,
+}
+
+/// Whether 'block' fits on 'field' without overlapping walls or the stack
+pub fn fits(field: &TileMap, block: &Block) -> bool {
+    !block.collides(field)
+}
+
+/// The block moved by (dx, dy), None if it doesn't fit there
+pub fn moved(field: &TileMap, block: &Block, dx: i16, dy: i16) -> Option<Block> {
+    let mut moved = block.clone();
+    moved.position.x += dx;
+    moved.position.y += dy;
+    Some(moved).filter(|moved| fits(field, moved))
+}
+
+/// The block turned clockwise with the first kick which fits, None if none does
+pub fn rotated(field: &TileMap, block: &Block) -> Option<Block> {
+    let mut turned = block.clone();
+    turned.rotation = block.rotation.next();
+    KICKS.iter().find_map(|&(dx, dy)| moved(field, &turned, dx, dy))
+}
+
+/// The block dropped as far down as it goes
+pub fn dropped(field: &TileMap, block: &Block) -> Block {
+    let mut dropped = block.clone();
+    while let Some(lower) = moved(field, &dropped, 0, 1) {
+        dropped = lower;
+    }
+    dropped
+}
+
+// Position and rotation of a block, for remembering visited places
+type Place = (i16, i16, Rotation);
+
+/// All placements of a new block of 'block_type' on 'field', none if it can't spawn
+pub fn placements(field: &TileMap, pieces: &PieceSet, block_type: u8) -> Vec<Placement> {
+    let (block, free) = engine::spawn(field, pieces, block_type);
+    if !free {
+        return Vec::new();
+    }
+    placements_from(field, pieces, &block)
+}
+
+/// All placements 'block' can reach on 'field' by moving, rotating, soft dropping and
+/// hard dropping, including tucks and spins under overhangs. Placements with the same
+/// cells are only listed once, except spins of the T of 'pieces' which differ from the
+/// same cells reached without one. Each comes with the fewest inputs reaching it, a soft
+/// drop counting once per row.
+pub fn placements_from(field: &TileMap, pieces: &PieceSet, block: &Block) -> Vec<Placement> {
+    let t_block = pieces.t_block();
+    let mut found = Vec::new();
+    let mut found_cells = HashSet::new();
+
+    let mut paths: HashMap<Place, Vec<Input>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    paths.insert(place(block), Vec::new());
+    queue.push_back(block.clone());
+
+    // Breadth first, so the first path to a placement is one of the shortest
+    while let Some(block) = queue.pop_front() {
+        let path = paths[&place(&block)].clone();
+
+        // Hard drop from here. It only keeps a spin when the block was already down.
+        let landed = dropped(field, &block);
+        let spin = path.last() == Some(&Input::Rotate) && landed.position == block.position;
+        let mut cells: Vec<(i16, i16)> = landed.cells().iter().map(|c| (c.x, c.y)).collect();
+        cells.sort_unstable();
+        if found_cells.insert((cells, spin && Some(block.block_type) == t_block)) {
+            let mut inputs = path.clone();
+            inputs.push(Input::HardDrop);
+            found.push(Placement { block: landed, inputs, spin });
+        }
+
+        for &input in &[Input::Left, Input::Right, Input::Rotate, Input::SoftDrop] {
+            let next = match input {
+                Input::Left => moved(field, &block, -1, 0),
+                Input::Right => moved(field, &block, 1, 0),
+                Input::Rotate => rotated(field, &block),
+                _ => moved(field, &block, 0, 1),
+            };
+            let next = match next {
+                Some(next) if !paths.contains_key(&place(&next)) => next,
+                _ => continue,
+            };
+            let mut next_path = path.clone();
+            next_path.push(input);
+            paths.insert(place(&next), next_path);
+            queue.push_back(next);
+        }
+    }
+
+    found
+}
+
+fn place(block: &Block) -> Place {
+    (block.position.x, block.position.y, block.rotation)
+}
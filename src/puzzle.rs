@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::engine::{Clear, Engine, Rules, TSpin, BUFFER_ROWS, MAX_BOARD_WIDTH, MIN_BOARD_WIDTH};
+use crate::tbp::Piece;
+use crate::block::BLOCK_NAMES;
+use crate::tile_map::{block_tile, tile_block, TileMap, GARBAGE_TILE};
+use crate::Vector2;
+
+// Most rows a puzzle field can have, so everything fits under the buffer zone
+const MAX_ROWS: usize = 40;
+// File next to the puzzles which keeps the names of the solved ones
+const PROGRESS_FILE: &str = "solved.json";
+
+/// What has to be done to solve a puzzle.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Goal {
+    /// Clear at least this many lines in total
+    Lines { lines: u32 },
+    /// Leave the field empty after a clear
+    PerfectClear,
+    /// Clear this many lines at once with a full T-spin, e.g. 2 for a T-spin double
+    TSpin { lines: u32 },
+}
+
+impl Goal {
+    pub fn description(&self) -> String {
+        match self {
+            Goal::Lines { lines: 1 } => "Clear 1 line".to_string(),
+            Goal::Lines { lines } => format!("Clear {} lines", lines),
+            Goal::PerfectClear => "Perfect clear".to_string(),
+            Goal::TSpin { lines: 1 } => "T-spin single".to_string(),
+            Goal::TSpin { lines: 2 } => "T-spin double".to_string(),
+            Goal::TSpin { lines } => format!("T-spin clearing {} lines", lines),
+        }
+    }
+
+    /// Whether 'clear' reaches the goal, 'lines' are all lines cleared so far
+    /// including this clear
+    pub fn is_reached(&self, clear: &Clear, lines: u32) -> bool {
+        match *self {
+            Goal::Lines { lines: goal } => lines >= goal,
+            Goal::PerfectClear => clear.perfect_clear,
+            Goal::TSpin { lines: goal } => clear.t_spin == TSpin::Full && clear.lines() as u32 == goal,
+        }
+    }
+}
+
+fn default_hold() -> bool {
+    true
+}
+
+/// A board setup with a fixed sequence of blocks and a goal.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    /// Rows of the field from top to bottom: '.' is empty, 'X' garbage and
+    /// piece letters are cells of that piece. Rows above these are empty.
+    pub field: Vec<String>,
+    /// Letters of the blocks in the order they come, e.g. "TSZ"
+    pub pieces: String,
+    #[serde(default = "default_hold")]
+    pub hold: bool,
+    pub goal: Goal,
+}
+
+impl Puzzle {
+    /// Checks that the puzzle makes a playable board
+    pub fn validate(&self) -> Result<(), String> {
+        let width = match self.field.first() {
+            Some(row) => row.chars().count(),
+            None => return Err("field has no rows".to_string()),
+        };
+        if !(MIN_BOARD_WIDTH as usize..=MAX_BOARD_WIDTH as usize).contains(&width) {
+            return Err(format!("field must be {} to {} wide", MIN_BOARD_WIDTH, MAX_BOARD_WIDTH));
+        }
+        if self.field.len() > MAX_ROWS {
+            return Err(format!("field can have at most {} rows", MAX_ROWS));
+        }
+        for (i, row) in self.field.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row {} is not {} wide", i + 1, width));
+            }
+            if let Some(c) = row.chars().find(|&c| tile(c).is_none()) {
+                return Err(format!("unknown cell '{}' in row {}", c, i + 1));
+            }
+            if row.chars().all(|c| c != '.') {
+                return Err(format!("row {} is already full", i + 1));
+            }
+        }
+        if self.pieces.is_empty() {
+            return Err("no pieces".to_string());
+        }
+        if let Some(c) = self.pieces.chars().find(|&c| Piece::from_letter(c).is_none()) {
+            return Err(format!("unknown piece '{}'", c));
+        }
+        match self.goal {
+            Goal::Lines { lines: 0 } => Err("goal needs at least 1 line".to_string()),
+            Goal::TSpin { lines } if !(1..=3).contains(&lines) => Err("T-spins clear 1 to 3 lines".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rules for the puzzle's board, the field is at least as high as usual
+    pub fn rules(&self) -> Rules {
+        let default = Rules::default();
+        let width = self.field.first().map_or(0, |row| row.chars().count());
+        Rules {
+            board_width: width as i16,
+            board_height: default.board_height.max(self.field.len() as i16),
+            hold: self.hold,
+            ..default
+        }
+    }
+
+    /// Tile map of the starting field, with walls
+    pub fn tile_map(&self) -> TileMap {
+        let rules = self.rules();
+        let size = Vector2::new(rules.board_width + 2, BUFFER_ROWS + rules.board_height + 1);
+        let mut tile_map = TileMap::new(size, BUFFER_ROWS);
+        tile_map.add_walls();
+
+        // Rows are bottom aligned, the last one is right above the floor
+        let top = size.y - 1 - self.field.len() as i16;
+        for (y, row) in self.field.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                tile_map.set_cell(x as i16 + 1, top + y as i16, tile(c).unwrap_or(0));
+            }
+        }
+        tile_map
+    }
+
+    /// Puzzle of the field inside the walls of 'field', rows from the highest one
+    /// with cells down to the floor
+    pub fn from_field(name: &str, field: &TileMap, pieces: &str, hold: bool, goal: Goal) -> Puzzle {
+        let bottom = field.size.y - 2;
+        let row = |y: i16| -> String { (1..field.size.x - 1).map(|x| cell(field._get_cell(x, y))).collect() };
+        let top = (field.hidden_rows..=bottom)
+            .find(|&y| row(y).chars().any(|c| c != '.'))
+            .unwrap_or(bottom);
+        Puzzle {
+            name: name.to_string(),
+            field: (top..=bottom).map(row).collect(),
+            pieces: pieces.to_string(),
+            hold,
+            goal,
+        }
+    }
+
+    /// Block types of the sequence
+    pub fn blocks(&self) -> Vec<u8> {
+        self.pieces.chars().filter_map(Piece::from_letter).map(|piece| piece.block_type()).collect()
+    }
+
+    /// Engine set up with the field and sequence, the first block falling
+    pub fn engine(&self) -> Engine {
+        let mut engine = Engine::new(self.rules());
+        engine.play_sequence(&self.blocks());
+        engine.set_field(self.tile_map());
+        engine
+    }
+}
+
+// Tile of a field cell
+fn tile(c: char) -> Option<i16> {
+    match c {
+        '.' => Some(0),
+        'X' => Some(GARBAGE_TILE),
+        _ => Piece::from_letter(c).map(|piece| block_tile(piece.block_type())),
+    }
+}
+
+// Field cell of a tile, anything but the standard pieces is garbage
+fn cell(tile: i16) -> char {
+    match (tile, tile_block(tile)) {
+        (0, _) => '.',
+        (_, Some(block_type)) if (block_type as usize) < BLOCK_NAMES.len() => Piece::from_block_type(block_type).letter(),
+        _ => 'X',
+    }
+}
+
+/// Puzzles read from disk with the files which couldn't be used.
+pub struct Loaded {
+    /// Puzzles with their ids, sorted by id. The id is the file name without extension.
+    pub puzzles: Vec<(String, Puzzle)>,
+    /// Files which can't be read or aren't valid puzzles, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Puzzles from a puzzle file or a directory of them. Broken files are returned as
+/// errors, so the rest can still be played.
+pub fn load(path: &Path) -> io::Result<Loaded> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let file = entry?.path();
+            let is_json = file.extension().is_some_and(|extension| extension == "json");
+            let is_progress = file.file_name().is_some_and(|name| name == PROGRESS_FILE);
+            if is_json && !is_progress {
+                files.push(file);
+            }
+        }
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut puzzles = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let id = file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let puzzle = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<Puzzle>(&json).map_err(|e| e.to_string()))
+            .and_then(|puzzle| puzzle.validate().map(|_| puzzle));
+        match puzzle {
+            Ok(puzzle) => puzzles.push((id, puzzle)),
+            Err(e) => errors.push(format!("{}: {}", file.display(), e)),
+        }
+    }
+    puzzles.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Loaded { puzzles, errors })
+}
+
+/// Ids of the solved puzzles, kept in a file next to the puzzles.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Progress {
+    pub solved: BTreeSet<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Progress {
+    /// Progress for the puzzles at 'path', empty if nothing has been solved yet
+    pub fn load(path: &Path) -> Self {
+        let dir = if path.is_dir() { path } else { path.parent().unwrap_or_else(|| Path::new(".")) };
+        let path = dir.join(PROGRESS_FILE);
+        let mut progress: Progress = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        progress.path = path;
+        progress
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&self.path, json)
+    }
+
+    pub fn is_solved(&self, id: &str) -> bool {
+        self.solved.contains(id)
+    }
+}
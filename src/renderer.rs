@@ -0,0 +1,292 @@
+use ggez::graphics::{self, Color, DrawMode, Rect};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+
+use crate::pieces::PieceSet;
+use crate::theme::Skin;
+use crate::tile_map::{block_tile, tile_block, TileMap, TileSet};
+use crate::Vector2;
+
+// Size of preview and hold blocks compared to the cells of the board
+pub const QUEUE_SCALE: f32 = 0.6;
+// Patterns are drawn darkening the tile under them
+const PATTERN_COLOR: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.55 };
+
+// Draws a TileMap with a spritebatch, one sprite per cell
+pub struct TileMapRenderer {
+    pub cell_size: i16,
+    // Pixel position of the center of the drawn tile map
+    pub center: Vector2,
+    pub tile_set: TileSet,
+    spritebatch: graphics::spritebatch::SpriteBatch,
+    // Next blocks on the right and the held block on the left of the board
+    queue_spritebatch: graphics::spritebatch::SpriteBatch,
+    // Height of the tile image in pixels, used to scale tiles to cell size
+    image_size: u16,
+    // Tiles side by side in the image of an atlas, 0 for a single tinted tile
+    atlas_tiles: u16,
+    // Whether each piece has its own pattern on its tiles, so pieces can be told
+    // apart without colors
+    pub patterns: bool,
+    // Cells of the queue with their block type and pixel position in the queue spritebatch
+    queue_cells: Vec<(u8, f32, f32)>,
+}
+
+impl TileMapRenderer {
+    pub fn new(ctx: &mut Context, cell_size: i16, center: Vector2, tile_set: TileSet) -> Self {
+
+        let image = graphics::Image::new(ctx, "/element_white_square.png").unwrap();
+        let image_size = image.height();
+        let spritebatch = graphics::spritebatch::SpriteBatch::new(image.clone());
+        let queue_spritebatch = graphics::spritebatch::SpriteBatch::new(image);
+
+        TileMapRenderer {
+            cell_size,
+            center,
+            tile_set,
+            spritebatch,
+            queue_spritebatch,
+            image_size,
+            atlas_tiles: 0,
+            patterns: false,
+            queue_cells: Vec::new(),
+        }
+    }
+
+    // Draws with the tile image and colors of a theme from now on
+    pub fn set_skin(&mut self, skin: &Skin, tile_set: TileSet) {
+        self.tile_set = tile_set;
+        self.spritebatch = graphics::spritebatch::SpriteBatch::new(skin.tile_image.clone());
+        self.queue_spritebatch = graphics::spritebatch::SpriteBatch::new(skin.tile_image.clone());
+        self.image_size = skin.tile_image.height();
+        self.atlas_tiles = skin.atlas_tiles;
+    }
+
+    fn get_pixel_center(&self, tile_map: &TileMap) -> Vector2 {
+        self.cell_size * tile_map.get_center()
+    }
+
+    // Cell inside the walls under the pixel at 'x', 'y', hidden rows left out
+    pub fn cell_at(&self, tile_map: &TileMap, x: f32, y: f32) -> Option<Vector2> {
+        let offset = self.get_offset(tile_map);
+        let cell = self.cell_size as f32;
+        let column = ((x - offset.x as f32) / cell).floor() as i16;
+        let row = ((y - offset.y as f32) / cell).floor() as i16 + tile_map.hidden_rows;
+        let inside = column > 0 && column < tile_map.size.x - 1 && row >= tile_map.hidden_rows && row < tile_map.size.y - 1;
+        inside.then(|| Vector2::new(column, row))
+    }
+
+    // TileMap pixel offset
+    pub fn get_offset(&self, tile_map: &TileMap) -> Vector2 {
+        self.center - self.get_pixel_center(tile_map)
+    }
+
+    // Color of a tile, unknown tiles e.g. from a remote board are drawn as background
+    fn tile_color(&self, tile: i16) -> Color {
+        let tiles = &self.tile_set.tiles;
+        tiles.get(tile as usize).unwrap_or(&tiles[0]).color
+    }
+
+    // Part of the image and tint of a tile drawn in 'color'. Textures of an atlas
+    // are drawn as they are, tiles past its end get the last one tinted.
+    fn sprite(&self, tile: i16, color: Color) -> (Rect, Color) {
+        if self.atlas_tiles == 0 {
+            return (Rect::one(), color);
+        }
+        let width = 1.0 / self.atlas_tiles as f32;
+        let index = (tile.max(0) as u16).min(self.atlas_tiles - 1);
+        let untinted = (tile as u16) < self.atlas_tiles;
+        let white = Color::new(1.0, 1.0, 1.0, color.a);
+        (Rect::new(index as f32 * width, 0.0, width, 1.0), if untinted { white } else { color })
+    }
+
+    // Color of a tile in a row being cleared. First half of the clear the tile
+    // flashes to white, second half it dissolves into the background.
+    fn clearing_color(&self, color: Color, progress: f32) -> Color {
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let (from, to, t) = if progress < 0.5 {
+            (color, white, progress * 2.0)
+        } else {
+            (white, self.tile_set.tiles[0].color, (progress - 0.5) * 2.0)
+        };
+        Color::new(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+            1.0,
+        )
+    }
+
+    // Rows in 'cleared_rows' are animated by 'clear_progress' going from 0.0 to 1.0
+    pub fn update_spritebatches(&mut self, tile_map: &TileMap, cleared_rows: &[i16], clear_progress: f32) {
+
+        self.spritebatch.clear();
+
+        let scale = self.cell_size as f32 / self.image_size as f32;
+
+        for ix in 0..tile_map.size.x {
+            for iy in tile_map.hidden_rows..tile_map.size.y {
+                let fx = ix as f32;
+                let fy = (iy - tile_map.hidden_rows) as f32;
+                let tile = tile_map._get_cell(ix, iy);
+                let (src, mut color) = self.sprite(tile, self.tile_color(tile));
+                if cleared_rows.contains(&iy) && ix > 0 && ix < tile_map.size.x - 1 {
+                    color = self.clearing_color(color, clear_progress);
+                }
+                let p = graphics::DrawParam::new()
+                    .src(src)
+                    .dest(Point2::new(fx * self.cell_size as f32, fy * self.cell_size as f32))
+                    .scale(mint::Vector2 { x: scale, y: scale })
+                    .color(color);
+                self.spritebatch.add(p);
+            }
+        }
+
+    }
+    
+    // Next blocks from top to bottom right of the board, the held block left of it
+    pub fn update_queue(&mut self, tile_map: &TileMap, pieces: &PieceSet, preview: &[u8], hold: Option<u8>) {
+
+        self.queue_spritebatch.clear();
+        self.queue_cells.clear();
+
+        let cell = self.cell_size as f32;
+        let small = QUEUE_SCALE * cell;
+        // Each block is drawn in its own 5x5 box
+        let right = (tile_map.size.x + 1) as f32 * cell;
+        let left = -5.0 * small - 0.5 * cell;
+        let boxes = preview.iter().enumerate()
+            .map(|(i, &block_type)| (block_type, right, i as f32 * 5.0 * small))
+            .chain(hold.map(|block_type| (block_type, left, 0.0)));
+
+        let scale = small / self.image_size as f32;
+        for (block_type, x, y) in boxes {
+            let tile = block_tile(block_type);
+            let (src, color) = self.sprite(tile, self.tile_color(tile));
+            for c in pieces.block(Vector2::new(0, 0), block_type).cells() {
+                let p = graphics::DrawParam::new()
+                    .src(src)
+                    .dest(Point2::new(x + c.x as f32 * small, y + c.y as f32 * small))
+                    .scale(mint::Vector2 { x: scale, y: scale })
+                    .color(color);
+                self.queue_spritebatch.add(p);
+                self.queue_cells.push((block_type, x + c.x as f32 * small, y + c.y as f32 * small));
+            }
+        }
+    }
+
+    // Add images to spritebatch and draw tile_map, moved by 'shake' pixels.
+    pub fn draw(&mut self, ctx: &mut Context, tile_map: &TileMap, shake: (f32, f32)) -> GameResult<()> {
+
+        // Transform and scale background
+        let offset = self.get_offset(tile_map);
+        let param = graphics::DrawParam::new()
+            .dest(Point2::new(
+               offset.x as f32 + shake.0,
+               offset.y as f32 + shake.1,
+            ));
+
+        // Draw background
+        graphics::draw(ctx, &self.spritebatch, param)?;
+        graphics::draw(ctx, &self.queue_spritebatch, param)?;
+        if self.patterns {
+            self.draw_patterns(ctx, tile_map, param)?;
+        }
+
+        Ok(())
+    }
+
+    // Draws the pattern of its piece on every block tile of the board and the queue
+    fn draw_patterns(&mut self, ctx: &mut Context, tile_map: &TileMap, param: graphics::DrawParam) -> GameResult<()> {
+        let cell = self.cell_size as f32;
+        let mut cells = Vec::new();
+        for x in 0..tile_map.size.x {
+            for y in tile_map.hidden_rows..tile_map.size.y {
+                if let Some(block_type) = tile_block(tile_map._get_cell(x, y)) {
+                    cells.push((block_type, x as f32 * cell, (y - tile_map.hidden_rows) as f32 * cell, cell));
+                }
+            }
+        }
+        let small = QUEUE_SCALE * cell;
+        cells.extend(self.queue_cells.iter().map(|&(block_type, x, y)| (block_type, x, y, small)));
+        if cells.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = graphics::MeshBuilder::new();
+        for (block_type, x, y, size) in cells {
+            add_pattern(&mut builder, block_type, x, y, size);
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, param)?;
+
+        Ok(())
+    }
+
+    // Draws the borders of 'cells' in the visible part of the tile map, e.g. a target place
+    pub fn draw_outline(&mut self, ctx: &mut Context, tile_map: &TileMap, cells: &[Vector2], color: Color, shake: (f32, f32)) -> GameResult<()> {
+        let cell = self.cell_size as f32;
+        let offset = self.get_offset(tile_map);
+        let mut builder = graphics::MeshBuilder::new();
+        let mut empty = true;
+        for c in cells.iter().filter(|c| c.y >= tile_map.hidden_rows) {
+            let rect = Rect::new(
+                offset.x as f32 + shake.0 + c.x as f32 * cell,
+                offset.y as f32 + shake.1 + (c.y - tile_map.hidden_rows) as f32 * cell,
+                cell,
+                cell,
+            );
+            builder.rectangle(DrawMode::stroke(0.1 * cell), rect, color);
+            empty = false;
+        }
+        if empty {
+            return Ok(());
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::new())?;
+
+        Ok(())
+    }
+
+    // Draws a red bar next to the right wall, one cell high for each pending garbage row
+    pub fn draw_garbage_meter(&mut self, ctx: &mut Context, tile_map: &TileMap, pending: u32, shake: (f32, f32)) -> GameResult<()> {
+        if pending == 0 {
+            return Ok(());
+        }
+
+        let cell = self.cell_size as f32;
+        let offset = self.get_offset(tile_map);
+        let visible_rows = (tile_map.size.y - tile_map.hidden_rows - 1) as f32;
+        let height = (pending as f32).min(visible_rows) * cell;
+        let rect = Rect::new(
+            offset.x as f32 + shake.0 + tile_map.size.x as f32 * cell + 0.25 * cell,
+            offset.y as f32 + shake.1 + visible_rows * cell - height,
+            0.5 * cell,
+            height,
+        );
+        let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::new(0.9, 0.1, 0.1, 1.0))?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::new())?;
+
+        Ok(())
+    }
+}
+
+// Adds the pattern of 'block_type' for a cell of 'size' pixels at 'x', 'y': a dot, lines
+// in four directions, a triangle and a ring for the standard pieces, a cross for others
+fn add_pattern(builder: &mut graphics::MeshBuilder, block_type: u8, x: f32, y: f32, size: f32) {
+    let at = |fx: f32, fy: f32| Point2::new(x + fx * size, y + fy * size);
+    let width = 0.12 * size;
+    // Lines which couldn't be built are left out, they are only a hint
+    let _ = match block_type {
+        0 => Ok(builder.circle(DrawMode::fill(), at(0.5, 0.5), 0.15 * size, 0.5, PATTERN_COLOR)),
+        1 => builder.line(&[at(0.5, 0.2), at(0.5, 0.8)], width, PATTERN_COLOR),
+        2 => builder.polygon(DrawMode::fill(), &[at(0.25, 0.3), at(0.75, 0.3), at(0.5, 0.7)], PATTERN_COLOR),
+        3 => builder.line(&[at(0.25, 0.75), at(0.75, 0.25)], width, PATTERN_COLOR),
+        4 => builder.line(&[at(0.25, 0.25), at(0.75, 0.75)], width, PATTERN_COLOR),
+        5 => builder.line(&[at(0.2, 0.5), at(0.8, 0.5)], width, PATTERN_COLOR),
+        6 => Ok(builder.circle(DrawMode::stroke(0.08 * size), at(0.5, 0.5), 0.22 * size, 0.5, PATTERN_COLOR)),
+        _ => builder
+            .line(&[at(0.3, 0.5), at(0.7, 0.5)], width, PATTERN_COLOR)
+            .and_then(|builder| builder.line(&[at(0.5, 0.3), at(0.5, 0.7)], width, PATTERN_COLOR)),
+    };
+}
@@ -0,0 +1,59 @@
+// Window size and where things go in it. Texts are placed in f32 so a text wider
+// than a small window ends up at its edge instead of underflowing.
+
+use crate::Vector2;
+
+/// Window size the layout is made for, everything scales from it
+pub const DEFAULT_SCREEN_SIZE: (f32, f32) = (1800.0, 1000.0);
+/// Smallest window size the layout still fits in
+pub const MIN_SCREEN_SIZE: (f32, f32) = (640.0, 360.0);
+
+/// Screen resolution / window size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Screen {
+    pub size: Vector2,
+    pub center: Vector2,
+}
+
+impl Screen {
+    pub fn new(width: f32, height: f32) -> Self {
+        let size = Vector2::new(width as i16, height as i16);
+        Screen { size, center: Vector2::new(size.x / 2, size.y / 2) }
+    }
+
+    /// How much larger texts, gaps and cells are than in a window of the default size
+    pub fn scale(&self) -> f32 {
+        let (width, height) = DEFAULT_SCREEN_SIZE;
+        (self.size.x as f32 / width).min(self.size.y as f32 / height)
+    }
+
+    /// Center and area of each of 'count' boards side by side
+    pub fn board_areas(&self, count: usize) -> Vec<(Vector2, Vector2)> {
+        let area = Vector2::new(self.size.x / count.max(1) as i16, self.size.y);
+        (0..count).map(|i| (Vector2::new(area.x / 2 + i as i16 * area.x, self.center.y), area)).collect()
+    }
+
+    /// Left edge of something 'width' wide in the middle of the screen, the left
+    /// edge of the screen if it's wider
+    pub fn centered_x(&self, width: f32) -> f32 {
+        (self.center.x as f32 - 0.5 * width).max(0.0)
+    }
+
+    /// Top edge of something 'height' high in the middle of the screen, the top
+    /// edge of the screen if it's higher
+    pub fn centered_y(&self, height: f32) -> f32 {
+        (self.center.y as f32 - 0.5 * height).max(0.0)
+    }
+
+    /// Left edge of something 'width' wide at the right edge of the screen
+    pub fn right_aligned_x(&self, width: f32) -> f32 {
+        (self.size.x as f32 - width).max(0.0)
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        let (width, height) = DEFAULT_SCREEN_SIZE;
+        Screen::new(width, height)
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+
+use std::thread;
+use std::time::Duration;
+
+use crate::bot::Controller;
+use crate::engine::{Engine, Event, Rules};
+use crate::pieces::PieceSet;
+use crate::versus;
+
+/// Simulated time between engine updates
+pub const STEP: Duration = Duration::from_millis(5);
+
+/// Result of one simulated game.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GameStats {
+    pub seed: u64,
+    /// Blocks locked
+    pub pieces: u32,
+    pub lines: u32,
+    pub score: u32,
+    /// Garbage rows the clears would have sent in versus
+    pub attack: u32,
+    /// Simulated game time in seconds
+    pub time: f32,
+    pub topped_out: bool,
+    /// Locked blocks of each type, in the order of the piece set
+    pub piece_counts: Vec<u32>,
+}
+
+impl GameStats {
+    /// Pieces per second
+    pub fn pps(&self) -> f32 {
+        if self.time > 0.0 { self.pieces as f32 / self.time } else { 0.0 }
+    }
+
+    /// Attack per piece
+    pub fn app(&self) -> f32 {
+        if self.pieces > 0 { self.attack as f32 / self.pieces as f32 } else { 0.0 }
+    }
+}
+
+/// Averages over several games.
+#[derive(Serialize, Debug, Clone)]
+pub struct Summary {
+    pub games: usize,
+    pub pieces: f32,
+    pub lines: f32,
+    pub score: f32,
+    pub pps: f32,
+    pub app: f32,
+    /// Share of games which ended by topping out before the piece limit
+    pub top_out_rate: f32,
+    /// Share of each block type among all locked blocks, by name
+    pub piece_distribution: Vec<(String, f32)>,
+}
+
+impl Summary {
+    /// Averages of 'games' played with 'pieces'
+    pub fn new(games: &[GameStats], pieces: &PieceSet) -> Self {
+        let count = games.len().max(1) as f32;
+        let mean = |value: &dyn Fn(&GameStats) -> f32| games.iter().map(value).sum::<f32>() / count;
+
+        let mut piece_counts = vec![0; pieces.len()];
+        for game in games {
+            for (total, count) in piece_counts.iter_mut().zip(game.piece_counts.iter()) {
+                *total += count;
+            }
+        }
+        let total = piece_counts.iter().sum::<u32>().max(1) as f32;
+
+        Summary {
+            games: games.len(),
+            pieces: mean(&|g| g.pieces as f32),
+            lines: mean(&|g| g.lines as f32),
+            score: mean(&|g| g.score as f32),
+            pps: mean(&|g| g.pps()),
+            app: mean(&|g| g.app()),
+            top_out_rate: mean(&|g| if g.topped_out { 1.0 } else { 0.0 }),
+            piece_distribution: piece_counts.iter().enumerate()
+                .map(|(i, &count)| (pieces.name(i as u8).to_string(), count as f32 / total))
+                .collect(),
+        }
+    }
+}
+
+/// Plays one game with 'controller' until it tops out or has locked 'max_pieces'
+/// blocks. Time is simulated in steps of STEP, unless 'realtime' is set for
+/// controllers which need actual time to think, like external bots.
+pub fn run_game(rules: Rules, controller: &mut dyn Controller, seed: u64, max_pieces: u32,
+                realtime: bool) -> GameStats {
+    run_game_with(rules, controller, seed, max_pieces, realtime, &mut |_| {})
+}
+
+/// Plays one game like `run_game`, calling 'watch' with the engine after every step in
+/// which a block locked, e.g. to record a replay. The controller starts the game afresh,
+/// so each seed plays the same whichever games came before.
+pub fn run_game_with(rules: Rules, controller: &mut dyn Controller, seed: u64, max_pieces: u32,
+                     realtime: bool, watch: &mut dyn FnMut(&Engine)) -> GameStats {
+    controller.new_game();
+    let mut engine = Engine::with_seed(rules, seed);
+    let piece_counts = vec![0; engine.pieces().len()];
+    let mut stats = GameStats {
+        seed,
+        pieces: 0,
+        lines: 0,
+        score: 0,
+        attack: 0,
+        time: 0.0,
+        topped_out: false,
+        piece_counts,
+    };
+
+    let mut time = Duration::from_millis(0);
+    while stats.pieces < max_pieces {
+        if engine.top_out().is_some() {
+            stats.topped_out = true;
+            break;
+        }
+
+        controller.update(&mut engine, STEP);
+        engine.update(STEP);
+        time += STEP;
+        if realtime {
+            thread::sleep(STEP);
+        }
+
+        let mut locked = false;
+        for event in engine.drain_events() {
+            match event {
+                Event::Locked(block_type, _) => {
+                    locked = true;
+                    stats.pieces += 1;
+                    if let Some(count) = stats.piece_counts.get_mut(block_type as usize) {
+                        *count += 1;
+                    }
+                },
+                Event::LinesCleared(clear) => {
+                    stats.lines += clear.lines() as u32;
+                    stats.attack += versus::attack(&clear);
+                },
+                _ => {},
+            }
+        }
+        if locked {
+            watch(&engine);
+        }
+    }
+
+    stats.score = engine.points;
+    stats.time = time.as_secs_f32();
+    stats
+}
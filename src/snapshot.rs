@@ -0,0 +1,183 @@
+// Draws boards into images without a window or GPU, for bug reports and sharing games.
+// The layout is the one of the window: the held block left of the board and the next
+// blocks right of it.
+
+use ggez::graphics::{self, Color};
+use gif::SetParameter;
+use image::{Rgba, RgbaImage};
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::engine::{Engine, PREVIEW_LENGTH};
+use crate::pieces::PieceSet;
+use crate::renderer::QUEUE_SCALE;
+use crate::tile_map::{block_tile, TileMap, TileSet};
+use crate::Vector2;
+
+/// Cell size in pixels when none is given
+pub const CELL_SIZE: u32 = 24;
+// Speed of the color quantizer for frames with too many colors for a GIF palette,
+// 1 is the best and slowest
+const QUANTIZER_SPEED: i32 = 10;
+
+/// A board as it is drawn: the tile map with the falling block and the blocks around it.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub tile_map: TileMap,
+    pub preview: Vec<u8>,
+    pub hold: Option<u8>,
+}
+
+impl Snapshot {
+    /// Snapshot of a field without a queue
+    pub fn new(tile_map: TileMap) -> Self {
+        Snapshot { tile_map, preview: Vec::new(), hold: None }
+    }
+
+    pub fn from_engine(engine: &Engine) -> Self {
+        Snapshot {
+            tile_map: engine.tile_map.clone(),
+            preview: engine.preview().iter().take(PREVIEW_LENGTH).copied().collect(),
+            hold: engine.held(),
+        }
+    }
+}
+
+/// Software renderer of snapshots in the colors of a tile set.
+#[derive(Clone)]
+pub struct SnapshotRenderer {
+    pub cell_size: u32,
+    pub tile_set: TileSet,
+    /// Shapes of the blocks in the preview and hold
+    pub pieces: PieceSet,
+    /// Color around the board and between cells
+    pub background: Color,
+}
+
+impl SnapshotRenderer {
+    pub fn new(tile_set: TileSet, pieces: PieceSet, background: Color) -> Self {
+        SnapshotRenderer { cell_size: CELL_SIZE, tile_set, pieces, background }
+    }
+
+    pub fn render(&self, snapshot: &Snapshot) -> RgbaImage {
+        let cell = self.cell_size.max(1);
+        let small = ((QUEUE_SCALE * cell as f32) as u32).max(1);
+        let margin = cell / 2;
+        let tile_map = &snapshot.tile_map;
+        let columns = tile_map.size.x.max(0) as u32;
+        let rows = (tile_map.size.y - tile_map.hidden_rows).max(0) as u32;
+
+        // Each block of the queue is drawn in its own 5x5 box, the preview one cell
+        // right of the board
+        let board_x = margin + 5 * small + margin;
+        let preview_x = board_x + (columns + 1) * cell;
+        let width = preview_x + 5 * small + margin;
+        let height = 2 * margin + (rows * cell).max(PREVIEW_LENGTH as u32 * 5 * small);
+        let mut image = RgbaImage::from_pixel(width, height, rgba(self.background));
+
+        for y in 0..rows {
+            for x in 0..columns {
+                let tile = tile_map._get_cell(x as i16, y as i16 + tile_map.hidden_rows);
+                fill_cell(&mut image, board_x + x * cell, margin + y * cell, cell, self.tile_color(tile));
+            }
+        }
+        if let Some(block_type) = snapshot.hold {
+            self.draw_block(&mut image, block_type, margin, margin, small);
+        }
+        for (i, &block_type) in snapshot.preview.iter().enumerate() {
+            self.draw_block(&mut image, block_type, preview_x, margin + i as u32 * 5 * small, small);
+        }
+        image
+    }
+
+    // Block of the queue with the top left corner of its box at 'x', 'y'
+    fn draw_block(&self, image: &mut RgbaImage, block_type: u8, x: u32, y: u32, size: u32) {
+        let color = self.tile_color(block_tile(block_type));
+        for c in self.pieces.block(Vector2::new(0, 0), block_type).cells() {
+            if c.x >= 0 && c.y >= 0 {
+                fill_cell(image, x + c.x as u32 * size, y + c.y as u32 * size, size, color);
+            }
+        }
+    }
+
+    fn tile_color(&self, tile: i16) -> Rgba<u8> {
+        let tiles = &self.tile_set.tiles;
+        rgba(tiles.get(tile as usize).or_else(|| tiles.first()).map_or(graphics::WHITE, |t| t.color))
+    }
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    let (r, g, b) = color.to_rgb();
+    Rgba([r, g, b, 255])
+}
+
+// Fills a cell but its right and bottom edge, so a line of background keeps cells apart
+fn fill_cell(image: &mut RgbaImage, x: u32, y: u32, size: u32, color: Rgba<u8>) {
+    let inner = size.saturating_sub(1).max(1);
+    for py in y..(y + inner).min(image.height()) {
+        for px in x..(x + inner).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Writes the frames as numbered PNG files in 'dir', e.g. `frame-0001.png`, and returns
+/// the files
+pub fn save_frames(frames: &[RgbaImage], dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    frames.iter().enumerate().map(|(i, frame)| {
+        let path = dir.join(format!("frame-{:04}.png", i + 1));
+        frame.save(&path)?;
+        Ok(path)
+    }).collect()
+}
+
+/// Writes the frames as a looping animated GIF, showing each for 'delay'. The frames
+/// must all have the same size.
+pub fn save_gif(frames: &[RgbaImage], delay: Duration, path: &Path) -> io::Result<()> {
+    let first = frames.first().ok_or_else(|| io::Error::other("no frames to save"))?;
+    let (width, height) = first.dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(io::Error::other("frames are too large for a GIF"));
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])?;
+    encoder.set(gif::Repeat::Infinite)?;
+    // GIF delays are in hundredths of a second
+    let delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for frame in frames {
+        if frame.dimensions() != (width, height) {
+            return Err(io::Error::other("frames differ in size"));
+        }
+        let mut gif_frame = gif_frame(frame);
+        gif_frame.delay = delay;
+        encoder.write_frame(&gif_frame)?;
+    }
+    Ok(())
+}
+
+// GIF frame with the exact colors of the image. Boards have far fewer colors than the
+// 256 of a GIF palette, other images are quantized.
+fn gif_frame(image: &RgbaImage) -> gif::Frame<'static> {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    let mut indices = Vec::with_capacity(image.len() / 4);
+    for pixel in image.pixels() {
+        let rgb = [pixel[0], pixel[1], pixel[2]];
+        let index = match colors.iter().position(|&color| color == rgb) {
+            Some(index) => index,
+            None => {
+                colors.push(rgb);
+                colors.len() - 1
+            },
+        };
+        if colors.len() > 256 {
+            return gif::Frame::from_rgba_speed(width, height, &mut image.clone().into_raw(), QUANTIZER_SPEED);
+        }
+        indices.push(index as u8);
+    }
+    gif::Frame::from_palette_pixels(width, height, &indices, &colors.concat(), None)
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use crate::engine::{Event, TSpin};
+use crate::finesse::Finesse;
+use crate::versus;
+
+// Lines per level, levels only show progress and don't change the speed
+const LINES_PER_LEVEL: u32 = 10;
+
+/// Statistics of one board, counted from its events.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Time the game has been on, without pauses
+    pub time: Duration,
+    pub pieces: u32,
+    pub lines: u32,
+    /// Garbage rows the clears send, also when playing alone
+    pub attack: u32,
+    /// Key presses for the board
+    pub keys: u32,
+    /// Most clears in a row
+    pub max_combo: u32,
+    pub t_spins: u32,
+    pub t_spin_minis: u32,
+    /// Clears of 1, 2, 3 and 4 rows
+    pub clears: [u32; 4],
+    pub perfect_clears: u32,
+}
+
+impl Stats {
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::Locked(..) => self.pieces += 1,
+            Event::LinesCleared(clear) => {
+                self.lines += clear.lines() as u32;
+                self.attack += versus::attack(clear);
+                self.max_combo = self.max_combo.max(clear.combo + 1);
+                match clear.t_spin {
+                    TSpin::Full => self.t_spins += 1,
+                    TSpin::Mini => self.t_spin_minis += 1,
+                    TSpin::None => {},
+                }
+                if let Some(count) = self.clears.get_mut(clear.lines().saturating_sub(1)) {
+                    *count += 1;
+                }
+                if clear.perfect_clear {
+                    self.perfect_clears += 1;
+                }
+            },
+            Event::HardDrop => {},
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.lines / LINES_PER_LEVEL + 1
+    }
+
+    /// Pieces per second
+    pub fn pps(&self) -> f32 {
+        self.per_second(self.pieces)
+    }
+
+    /// Attack per minute
+    pub fn apm(&self) -> f32 {
+        60.0 * self.per_second(self.attack)
+    }
+
+    /// Keys per piece
+    pub fn kpp(&self) -> f32 {
+        if self.pieces > 0 { self.keys as f32 / self.pieces as f32 } else { 0.0 }
+    }
+
+    fn per_second(&self, count: u32) -> f32 {
+        let seconds = self.time.as_secs_f32();
+        if seconds > 0.0 { count as f32 / seconds } else { 0.0 }
+    }
+}
+
+/// Lines of the statistics panel, each can be turned on separately.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stat {
+    Time,
+    Lines,
+    Level,
+    Pieces,
+    Pps,
+    Apm,
+    Kpp,
+    MaxCombo,
+    TSpins,
+    Clears,
+    Finesse,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 11] = [
+        Stat::Time, Stat::Lines, Stat::Level, Stat::Pieces, Stat::Pps, Stat::Apm,
+        Stat::Kpp, Stat::MaxCombo, Stat::TSpins, Stat::Clears, Stat::Finesse,
+    ];
+
+    /// Stat from its command line name, e.g. "pps" or "max-combo"
+    pub fn from_name(name: &str) -> Option<Stat> {
+        Stat::ALL.iter().copied().find(|stat| stat.name() == name)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Stat::Time     => "time",
+            Stat::Lines    => "lines",
+            Stat::Level    => "level",
+            Stat::Pieces   => "pieces",
+            Stat::Pps      => "pps",
+            Stat::Apm      => "apm",
+            Stat::Kpp      => "kpp",
+            Stat::MaxCombo => "max-combo",
+            Stat::TSpins   => "t-spins",
+            Stat::Clears   => "clears",
+            Stat::Finesse  => "finesse",
+        }
+    }
+
+    /// The line shown in the panel
+    pub fn text(&self, stats: &Stats, finesse: &Finesse) -> String {
+        match self {
+            Stat::Time => {
+                let seconds = stats.time.as_secs();
+                format!("Time {}:{:02}", seconds / 60, seconds % 60)
+            },
+            Stat::Lines => format!("Lines {}", stats.lines),
+            Stat::Level => format!("Level {}", stats.level()),
+            Stat::Pieces => format!("Pieces {}", stats.pieces),
+            Stat::Pps => format!("PPS {:.2}", stats.pps()),
+            Stat::Apm => format!("APM {:.1}", stats.apm()),
+            Stat::Kpp => format!("KPP {:.2}", stats.kpp()),
+            Stat::MaxCombo => format!("Max combo {}", stats.max_combo),
+            Stat::TSpins => format!("T-spins {} / mini {}", stats.t_spins, stats.t_spin_minis),
+            Stat::Clears => {
+                let [singles, doubles, triples, tetrises] = stats.clears;
+                format!("1/2/3/4 {}/{}/{}/{} PC {}", singles, doubles, triples, tetrises, stats.perfect_clears)
+            },
+            Stat::Finesse => format!("Finesse faults {}", finesse.total_faults()),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+// Tetris Bot Protocol (TBP), which lets external bots like Cold Clear play a board.
+// The bot runs as a child process and messages are lines of JSON on its stdin and
+// stdout. See https://github.com/tetris-bot-protocol/tbp-spec
+
+use serde::{Deserialize, Serialize};
+
+use std::io::{self, BufReader};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::bot::{self, Controller, Weights};
+use crate::placement::{self, Placement};
+use crate::engine::{self, Engine, Input};
+use crate::net::protocol::{read_message, write_message};
+use crate::tile_map::{block_tile, tile_block, TileMap, GARBAGE_TILE};
+use crate::Vector2;
+
+/// How long a bot may take to introduce itself and get ready
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Blocks by their TBP names.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Piece { I, O, T, L, J, S, Z }
+
+impl Piece {
+    /// Piece of a block type of the standard piece set
+    pub fn from_block_type(block_type: u8) -> Piece {
+        match block_type {
+            0 => Piece::O,
+            1 => Piece::I,
+            2 => Piece::T,
+            3 => Piece::S,
+            4 => Piece::Z,
+            5 => Piece::L,
+            _ => Piece::J,
+        }
+    }
+
+    pub fn block_type(&self) -> u8 {
+        match self {
+            Piece::O => 0,
+            Piece::I => 1,
+            Piece::T => 2,
+            Piece::S => 3,
+            Piece::Z => 4,
+            Piece::L => 5,
+            Piece::J => 6,
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Piece::I => 'I',
+            Piece::O => 'O',
+            Piece::T => 'T',
+            Piece::L => 'L',
+            Piece::J => 'J',
+            Piece::S => 'S',
+            Piece::Z => 'Z',
+        }
+    }
+
+    /// Piece from its letter, e.g. 'T'
+    pub fn from_letter(letter: char) -> Option<Piece> {
+        [Piece::I, Piece::O, Piece::T, Piece::L, Piece::J, Piece::S, Piece::Z]
+            .iter().copied().find(|piece| piece.letter() == letter)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation { North, East, South, West }
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Spin { None, Mini, Full }
+
+/// Piece with the position of its rotation center. x goes right from the left wall
+/// and y up from the bottom row, both starting from 0.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PieceLocation {
+    #[serde(rename = "type")]
+    pub piece: Piece,
+    pub orientation: Orientation,
+    pub x: i16,
+    pub y: i16,
+}
+
+impl PieceLocation {
+    /// Cells of the piece, in the same coordinates as the location
+    pub fn cells(&self) -> Vec<(i16, i16)> {
+        // Cells of the north orientation around the rotation center
+        let north: [(i16, i16); 4] = match self.piece {
+            Piece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Piece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Piece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Piece::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            Piece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Piece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        north.iter()
+            .map(|&(x, y)| match self.orientation {
+                Orientation::North => (x, y),
+                Orientation::East => (y, -x),
+                Orientation::South => (-x, -y),
+                Orientation::West => (-y, x),
+            })
+            .map(|(x, y)| (self.x + x, self.y + y))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Move {
+    pub location: PieceLocation,
+    pub spin: Spin,
+}
+
+/// Messages from jetris to the bot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    /// Answer to the bot's info, only the default rules are used
+    Rules,
+    /// Starts thinking about a position. 'queue' starts with the current piece and
+    /// 'board' is 40 rows of 10 cells from the bottom up.
+    Start {
+        hold: Option<Piece>,
+        queue: Vec<Piece>,
+        combo: u32,
+        back_to_back: bool,
+        board: Vec<Vec<Option<char>>>,
+    },
+    /// Asks for the moves the bot would make now
+    Suggest,
+    /// Tells the bot which move was made
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    /// A new piece was added to the end of the queue
+    NewPiece { piece: Piece },
+    /// Forget the position, a new start follows
+    Stop,
+    Quit,
+}
+
+/// Messages from the bot to jetris.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Error { reason: String },
+    Ready,
+    Info { name: String, version: String, author: String, features: Vec<String> },
+    /// Moves from the best down
+    Suggestion { moves: Vec<Move> },
+}
+
+/// The field as a TBP board, rows from the bottom up without walls. Falling block not
+/// included, so give Engine::field.
+pub fn board(field: &TileMap) -> Vec<Vec<Option<char>>> {
+    (0..field.size.y - 1).rev()
+        .map(|y| (1..field.size.x - 1)
+            .map(|x| match field._get_cell(x, y) {
+                0 => None,
+                GARBAGE_TILE => Some('G'),
+                tile => tile_block(tile).map(|block_type| Piece::from_block_type(block_type).letter()),
+            })
+            .collect())
+        .collect()
+}
+
+// Tile map position of a TBP position
+fn to_tile_map(field: &TileMap, (x, y): (i16, i16)) -> Vector2 {
+    Vector2::new(x + 1, field.size.y - 2 - y)
+}
+
+/// Start message for the position of 'engine'
+pub fn start(engine: &Engine) -> FrontendMessage {
+    let queue = std::iter::once(engine.block.block_type)
+        .chain(engine.preview().iter().copied())
+        .map(Piece::from_block_type)
+        .collect();
+    FrontendMessage::Start {
+        hold: engine.held().map(Piece::from_block_type),
+        queue,
+        combo: engine.combo(),
+        back_to_back: engine.back_to_back(),
+        board: board(&engine.field()),
+    }
+}
+
+/// How to make a move with the current block, holding first if the move is for the
+/// held or next block. None if the move can't be reached with jetris rotation.
+pub fn find_placement(engine: &Engine, mv: &Move) -> Option<Placement> {
+    let field = engine.field();
+    let mut wanted: Vec<(i16, i16)> = mv.location.cells().iter()
+        .map(|&cell| to_tile_map(&field, cell))
+        .map(|v| (v.x, v.y))
+        .collect();
+    wanted.sort_unstable();
+
+    let block_type = mv.location.piece.block_type();
+    let (start, prefix) = if block_type == engine.block.block_type {
+        (engine.block.clone(), None)
+    } else {
+        let swapped = engine.held().or_else(|| engine.preview().front().copied());
+        if !engine.can_hold() || swapped != Some(block_type) {
+            return None;
+        }
+        let (block, free) = engine::spawn(&field, engine.pieces(), block_type);
+        if !free {
+            return None;
+        }
+        (block, Some(Input::Hold))
+    };
+
+    // Prefer a placement whose last move matches the spin, T-spins need a rotation last
+    let mut found: Vec<Placement> = placement::placements_from(&field, engine.pieces(), &start).into_iter()
+        .filter(|p| {
+            let mut cells: Vec<(i16, i16)> = p.block.cells().iter().map(|c| (c.x, c.y)).collect();
+            cells.sort_unstable();
+            cells == wanted
+        })
+        .collect();
+    found.sort_by_key(|p| p.spin == (mv.spin == Spin::None));
+    let mut placement = found.into_iter().next()?;
+    placement.inputs.splice(0..0, prefix);
+    Some(placement)
+}
+
+/// External bot process playing a board. Waits for the bot's suggestion without
+/// blocking and places one block every 1/pps seconds.
+pub struct ExternalBot {
+    /// Name, version and author from the bot's info
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    /// Pieces per second
+    pub pps: f32,
+    /// Why the bot stopped working, after which the board is played without it
+    pub error: Option<String>,
+    child: Child,
+    stdin: ChildStdin,
+    // None from the reading thread means the bot closed its stdout
+    receiver: Receiver<Option<BotMessage>>,
+    started: bool,
+    // Field the bot thinks there is, None when it has to start over
+    expected: Option<Vec<i16>>,
+    // Number of blocks the bot knows of, see Engine::blocks_dealt
+    told: u32,
+    // Waiting for a suggestion, and the suggestion when it came
+    suggesting: bool,
+    suggestion: Option<Vec<Move>>,
+    // Suggestions asked for in a previous game which are still to come
+    stale_suggestions: u32,
+    time: Duration,
+}
+
+impl ExternalBot {
+    /// Starts the bot with 'command' and its arguments and waits until it is ready
+    pub fn launch(command: &str, args: &[String], pps: f32) -> io::Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(Some(message)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(None);
+        });
+
+        let mut bot = ExternalBot {
+            name: String::new(),
+            version: String::new(),
+            author: String::new(),
+            pps,
+            error: None,
+            child,
+            stdin,
+            receiver,
+            started: false,
+            expected: None,
+            told: 0,
+            suggesting: false,
+            suggestion: None,
+            stale_suggestions: 0,
+            time: Duration::from_millis(0),
+        };
+
+        match bot.wait()? {
+            BotMessage::Info { name, version, author, .. } => {
+                bot.name = name;
+                bot.version = version;
+                bot.author = author;
+            },
+            message => return Err(unexpected(message)),
+        }
+        bot.send(&FrontendMessage::Rules)?;
+        match bot.wait()? {
+            BotMessage::Ready => Ok(bot),
+            message => Err(unexpected(message)),
+        }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> io::Result<()> {
+        write_message(&mut self.stdin, message)
+    }
+
+    // Next message during the handshake
+    fn wait(&mut self) -> io::Result<BotMessage> {
+        match self.receiver.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(Some(message)) => Ok(message),
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "bot closed its output"))
+            },
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "bot didn't answer")),
+        }
+    }
+
+    // Starts the bot again from the engine's position if it has gone out of sync,
+    // e.g. after garbage or a new game
+    fn sync(&mut self, engine: &Engine) -> io::Result<()> {
+        let known = engine.blocks_dealt() + engine.preview().len() as u32;
+        let field = engine.field().cells();
+        if self.expected.as_ref() == Some(&field) && self.told <= known {
+            // Tell about blocks which came to the preview since the last time
+            let dealt = engine.blocks_dealt();
+            while self.told < known {
+                let block_type = engine.preview()[(self.told - dealt) as usize];
+                self.send(&FrontendMessage::NewPiece { piece: Piece::from_block_type(block_type) })?;
+                self.told += 1;
+            }
+            return Ok(());
+        }
+
+        if self.started {
+            self.send(&FrontendMessage::Stop)?;
+        }
+        self.send(&start(engine))?;
+        self.started = true;
+        self.expected = Some(field);
+        self.told = known;
+        Ok(())
+    }
+
+    // Makes the first suggested move which jetris can do, or the built-in bot's move
+    // if none
+    fn play(&mut self, engine: &mut Engine, moves: &[Move]) -> io::Result<()> {
+        let chosen = moves.iter().find_map(|mv| find_placement(engine, mv).map(|p| (*mv, p)));
+        let placement = match chosen {
+            Some((mv, placement)) => {
+                self.send(&FrontendMessage::Play { mv })?;
+                placement
+            },
+            None => {
+                let preview: Vec<u8> = engine.preview().iter().copied().collect();
+                let placement = bot::best_placement(&engine.field(), engine.pieces(), &engine.block, engine.held(),
+                                                    engine.can_hold(), &preview, &Weights::default());
+                // The bot has to start over from whatever this leads to
+                self.expected = None;
+                match placement {
+                    Some(placement) => placement,
+                    None => {
+                        engine.hard_drop();
+                        return Ok(());
+                    },
+                }
+            },
+        };
+
+        // What the field looks like once the block is locked and rows cleared
+        if self.expected.is_some() {
+            let mut field = engine.field();
+            let block = &placement.block;
+            for cell in block.cells() {
+                field.set_cell(cell.x, cell.y, block_tile(block.block_type));
+            }
+            field.remove_rows(&field.full_rows());
+            self.expected = Some(field.cells());
+        }
+
+        for input in placement.inputs {
+            engine.apply(input);
+        }
+        Ok(())
+    }
+
+    fn try_update(&mut self, engine: &mut Engine, dt: Duration) -> io::Result<()> {
+        self.time += dt;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Some(BotMessage::Suggestion { .. })) if self.stale_suggestions > 0 => self.stale_suggestions -= 1,
+                Ok(Some(BotMessage::Suggestion { moves })) => self.suggestion = Some(moves),
+                Ok(Some(BotMessage::Error { reason })) => return Err(io::Error::other(reason)),
+                Ok(Some(_)) => {},
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "bot closed its output"));
+                },
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        if !engine.can_move() {
+            return Ok(());
+        }
+
+        if !self.suggesting {
+            self.sync(engine)?;
+            self.send(&FrontendMessage::Suggest)?;
+            self.suggesting = true;
+            return Ok(());
+        }
+
+        let interval = Duration::from_secs_f32(1.0 / self.pps.max(0.01));
+        if self.time >= interval {
+            if let Some(moves) = self.suggestion.take() {
+                self.time = Duration::from_millis(0);
+                self.suggesting = false;
+                self.play(engine, &moves)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Controller for ExternalBot {
+    // A bot which stops working gives up the game by dropping blocks where they are
+    fn update(&mut self, engine: &mut Engine, dt: Duration) {
+        if self.error.is_none() {
+            if let Err(e) = self.try_update(engine, dt) {
+                self.error = Some(e.to_string());
+            }
+        }
+        if self.error.is_some() && engine.can_move() {
+            engine.hard_drop();
+        }
+    }
+
+    // The bot answers in order, so a suggestion still to come belongs to the old game.
+    // The next sync starts the bot over from the new field.
+    fn new_game(&mut self) {
+        if self.suggesting && self.suggestion.is_none() {
+            self.stale_suggestions += 1;
+        }
+        self.expected = None;
+        self.suggesting = false;
+        self.suggestion = None;
+        self.time = Duration::from_millis(0);
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn unexpected(message: BotMessage) -> io::Error {
+    let reason = match message {
+        BotMessage::Error { reason } => reason,
+        message => format!("unexpected message {:?}", message),
+    };
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
@@ -0,0 +1,303 @@
+use ggez::graphics::{self, Color, Font, Image, Scale, TextFragment};
+use ggez::{Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::pieces::PieceSet;
+use crate::tile_map::{block_tile, Tile, TileSet, GARBAGE_TILE, WALL_TILE};
+
+/// Directory the themes are looked up in by name
+pub const THEMES_DIR: &str = "themes";
+// Manifest in each theme directory
+const MANIFEST: &str = "theme.json";
+// Tile image of the built-in theme, from the resources
+const DEFAULT_TILE_IMAGE: &str = "/element_white_square.png";
+
+/// Colors of the texts around the boards.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct HudColors {
+    pub text: [f32; 3],
+    /// Good news, e.g. a solved puzzle
+    pub good: [f32; 3],
+    /// Bad news, e.g. game over
+    pub bad: [f32; 3],
+    /// Something to look at, e.g. finesse faults or the winner
+    pub warning: [f32; 3],
+}
+
+impl Default for HudColors {
+    fn default() -> Self {
+        HudColors {
+            text: [1.0, 1.0, 1.0],
+            good: [0.3, 1.0, 0.3],
+            bad: [1.0, 0.3, 0.3],
+            warning: [1.0, 0.8, 0.2],
+        }
+    }
+}
+
+/// Look of the game, read from the `theme.json` of a theme directory. Files are
+/// relative to that directory. Red, green and blue go from 0.0 to 1.0.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    /// Color behind the boards
+    pub background: [f32; 3],
+    /// Image stretched over the window behind the boards
+    pub background_image: Option<String>,
+    /// Image of one tile, tinted with the color of each tile
+    pub tile_image: Option<String>,
+    /// Image of square tiles side by side, the first for tile 0 and so on, drawn
+    /// instead of 'tile_image' without tinting. Tiles past its end use the last
+    /// texture tinted with their color.
+    pub atlas: Option<String>,
+    /// TrueType font of all texts
+    pub font: Option<String>,
+    pub empty: [f32; 3],
+    pub wall: [f32; 3],
+    pub garbage: [f32; 3],
+    /// Piece colors by block type, pieces without one keep the color of their set
+    pub pieces: Vec<[f32; 3]>,
+    pub hud: HudColors,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "Classic".to_string(),
+            background: [0.2, 0.3, 0.6],
+            background_image: None,
+            tile_image: None,
+            atlas: None,
+            font: None,
+            empty: [0.2, 0.2, 0.2],
+            wall: [0.6, 0.6, 0.6],
+            garbage: [0.4, 0.4, 0.4],
+            pieces: Vec::new(),
+            hud: HudColors::default(),
+            dir: PathBuf::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Reads the manifest of the theme directory 'dir'
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(dir.join(MANIFEST))?;
+        let mut theme: Theme = serde_json::from_str(&json).map_err(io::Error::other)?;
+        theme.dir = dir.to_path_buf();
+        Ok(theme)
+    }
+
+    /// Theme directory of 'name', which is a directory or the name of one in `themes`
+    pub fn find(name: &str) -> PathBuf {
+        match Path::new(name).is_dir() {
+            true => PathBuf::from(name),
+            false => Path::new(THEMES_DIR).join(name),
+        }
+    }
+
+    /// Colors of the tiles for the pieces of 'pieces'
+    pub fn tile_set(&self, pieces: &PieceSet) -> TileSet {
+        let mut tile_set = TileSet::new();
+        tile_set.add_tile(Tile::new(0, rgb(self.empty)));
+        tile_set.set_color(WALL_TILE, rgb(self.wall));
+        tile_set.set_color(GARBAGE_TILE, rgb(self.garbage));
+        for (block_type, piece) in pieces.pieces.iter().enumerate() {
+            let color = self.pieces.get(block_type).unwrap_or(&piece.color);
+            tile_set.set_color(block_tile(block_type as u8), rgb(*color));
+        }
+        tile_set
+    }
+
+    // Path of a file of the theme
+    fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
+/// Theme directories in 'dir' sorted by name, the ones with a manifest
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut themes: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    themes.retain(|theme| theme.join(MANIFEST).is_file());
+    themes.sort();
+    themes
+}
+
+/// Built-in piece colors for players who tell colors apart differently, used over the
+/// colors of a theme.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    /// The colors of the theme
+    Theme,
+    /// For red-green color blindness with weak green
+    Deuteranopia,
+    /// For red-green color blindness with weak red
+    Protanopia,
+    /// For blue-yellow color blindness
+    Tritanopia,
+    /// Saturated pieces on black
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Theme, Palette::Deuteranopia, Palette::Protanopia, Palette::Tritanopia, Palette::HighContrast,
+    ];
+
+    /// Palette from its command line name, e.g. "deuteranopia" or "high-contrast"
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.iter().copied().find(|palette| palette.name() == name)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Palette::Theme        => "theme",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia   => "protanopia",
+            Palette::Tritanopia   => "tritanopia",
+            Palette::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn next(&self) -> Palette {
+        let i = Palette::ALL.iter().position(|palette| palette == self).unwrap_or(0);
+        Palette::ALL[(i + 1) % Palette::ALL.len()]
+    }
+
+    // Colors of the standard pieces in block type order O, I, T, S, Z, L, J. The S and
+    // Z differ in lightness as well as hue.
+    fn pieces(&self) -> Option<[[f32; 3]; 7]> {
+        match self {
+            Palette::Theme => None,
+            // Okabe-Ito colors, which stay apart for both kinds of red-green color blindness
+            Palette::Deuteranopia => Some([
+                [0.94, 0.89, 0.26], [0.34, 0.71, 0.91], [0.80, 0.47, 0.65], [0.00, 0.45, 0.70],
+                [0.90, 0.62, 0.00], [0.84, 0.37, 0.00], [0.00, 0.62, 0.45],
+            ]),
+            // Reds look dark without red cones, so there are none
+            Palette::Protanopia => Some([
+                [0.94, 0.89, 0.26], [0.34, 0.71, 0.91], [0.80, 0.60, 0.80], [0.00, 0.35, 0.65],
+                [0.95, 0.70, 0.20], [0.60, 0.60, 0.60], [0.00, 0.62, 0.45],
+            ]),
+            // Blue and yellow are confused, so pieces use reds, cyans and grays
+            Palette::Tritanopia => Some([
+                [0.95, 0.95, 0.95], [0.00, 0.75, 0.80], [0.85, 0.20, 0.45], [0.00, 0.45, 0.50],
+                [0.95, 0.55, 0.60], [0.70, 0.10, 0.10], [0.45, 0.45, 0.45],
+            ]),
+            Palette::HighContrast => Some([
+                [1.0, 1.0, 0.0], [0.0, 1.0, 1.0], [1.0, 0.0, 1.0], [0.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0], [1.0, 0.55, 0.0], [0.3, 0.5, 1.0],
+            ]),
+        }
+    }
+
+    /// Changes the piece colors of 'theme', high contrast also darkens everything
+    /// around the pieces
+    pub fn apply(&self, theme: &mut Theme) {
+        if let Some(pieces) = self.pieces() {
+            theme.pieces = pieces.to_vec();
+            // Textures of an atlas would hide the colors
+            theme.atlas = None;
+        }
+        if *self == Palette::HighContrast {
+            theme.background = [0.0, 0.0, 0.0];
+            theme.background_image = None;
+            theme.empty = [0.0, 0.0, 0.0];
+            theme.wall = [1.0, 1.0, 1.0];
+            theme.garbage = [0.55, 0.55, 0.55];
+            theme.hud = HudColors {
+                text: [1.0, 1.0, 1.0],
+                good: [0.0, 1.0, 0.0],
+                bad: [1.0, 0.2, 0.2],
+                warning: [1.0, 1.0, 0.0],
+            };
+        }
+    }
+}
+
+pub fn rgb(color: [f32; 3]) -> Color {
+    Color::new(color[0], color[1], color[2], 1.0)
+}
+
+/// Colors of the texts as ggez colors.
+#[derive(Debug, Clone, Copy)]
+pub struct Hud {
+    pub text: Color,
+    pub good: Color,
+    pub bad: Color,
+    pub warning: Color,
+}
+
+/// A theme with its images and font loaded.
+#[derive(Debug, Clone)]
+pub struct Skin {
+    pub theme: Theme,
+    /// Tile image or atlas
+    pub tile_image: Image,
+    /// Number of tiles in 'tile_image', 0 if it is a single tinted tile
+    pub atlas_tiles: u16,
+    pub background_image: Option<Image>,
+    pub font: Font,
+    pub hud: Hud,
+}
+
+impl Skin {
+    pub fn load(ctx: &mut Context, theme: Theme) -> GameResult<Self> {
+        let (tile_image, atlas_tiles) = match (&theme.atlas, &theme.tile_image) {
+            (Some(atlas), _) => {
+                let image = load_image(ctx, &theme.file(atlas))?;
+                let tiles = (image.width() / image.height().max(1)).max(1);
+                (image, tiles)
+            },
+            (None, Some(tile)) => (load_image(ctx, &theme.file(tile))?, 0),
+            (None, None) => (Image::new(ctx, DEFAULT_TILE_IMAGE)?, 0),
+        };
+        let background_image = match &theme.background_image {
+            Some(file) => Some(load_image(ctx, &theme.file(file))?),
+            None => None,
+        };
+        let font = match &theme.font {
+            Some(file) => Font::new_glyph_font_bytes(ctx, &fs::read(theme.file(file))?)?,
+            None => Font::default(),
+        };
+        let hud = Hud {
+            text: rgb(theme.hud.text),
+            good: rgb(theme.hud.good),
+            bad: rgb(theme.hud.bad),
+            warning: rgb(theme.hud.warning),
+        };
+        Ok(Skin { theme, tile_image, atlas_tiles, background_image, font, hud })
+    }
+
+    /// Text in the font of the theme
+    pub fn text(&self, text: impl Into<String>, color: Color, scale: f32) -> graphics::Text {
+        graphics::Text::new(TextFragment {
+            text: text.into(),
+            color: Some(color),
+            font: Some(self.font),
+            scale: Some(Scale::uniform(scale)),
+        })
+    }
+}
+
+// Image from a file outside of the resources
+fn load_image(ctx: &mut Context, path: &Path) -> GameResult<Image> {
+    let image = image::open(path)
+        .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path.display(), e)))?
+        .to_rgba();
+    let (width, height) = image.dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(GameError::ResourceLoadError(format!("{} is too large", path.display())));
+    }
+    Image::from_rgba8(ctx, width as u16, height as u16, &image)
+}
@@ -0,0 +1,232 @@
+use array2d::Array2D;
+use ggez::graphics;
+
+use crate::Vector2;
+
+pub const WALL_TILE: i16 = 8;
+pub const GARBAGE_TILE: i16 = 9;
+// Blocks of the first seven types come before the wall and garbage tiles, the
+// rest after them
+const LOW_BLOCK_TYPES: i16 = 7;
+
+/// Tile of the cells of a block of 'block_type'
+pub fn block_tile(block_type: u8) -> i16 {
+    match block_type as i16 {
+        t if t < LOW_BLOCK_TYPES => t + 1,
+        t => t + 3,
+    }
+}
+
+/// Block type of a tile, None for the background, walls and garbage
+pub fn tile_block(tile: i16) -> Option<u8> {
+    match tile {
+        1..=LOW_BLOCK_TYPES => Some((tile - 1) as u8),
+        t if t > GARBAGE_TILE => Some((t - 3) as u8),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tile{
+    #[allow(dead_code)]
+    id: u16,
+    pub color: graphics::Color,
+}
+
+impl Tile {
+    pub fn new(id: u16, color: graphics::Color) -> Self {
+        Tile { id, color }
+    }
+}
+
+#[derive(Clone)]
+pub struct TileSet {
+    pub tiles: Vec<Tile>,
+}
+
+impl TileSet {
+    pub fn new() -> Self {
+        TileSet {
+            tiles: Vec::new(),
+        }
+    }
+
+    pub fn add_tile(&mut self, tile: Tile) {
+        self.tiles.push(tile);
+    }
+
+    /// Sets the color of 'tile', tiles in between get the background color
+    pub fn set_color(&mut self, tile: i16, color: graphics::Color) {
+        let background = self.tiles.first().map_or(color, |t| t.color);
+        while self.tiles.len() <= tile as usize {
+            let id = self.tiles.len() as u16;
+            self.tiles.push(Tile::new(id, background));
+        }
+        self.tiles[tile as usize].color = color;
+    }
+}
+
+impl Default for TileSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// TileMap
+#[derive(Debug, Clone)]
+pub struct TileMap {
+    pub size: Vector2,
+    // Rows at the top which are not drawn (buffer zone)
+    pub hidden_rows: i16,
+    // 2d array of U16 representing tiles/cells
+    array : Array2D<i16>,
+}
+
+impl TileMap {
+    pub fn new(size: Vector2, hidden_rows: i16) -> Self {
+
+        let array = Array2D::filled_with(0, size.x as usize, size.y as usize);
+
+        TileMap {
+            size,
+            hidden_rows,
+            array,
+        }
+    }
+
+    // Tile map from cells given row by row from the top. Returns None if the
+    // number of cells doesn't match the size.
+    pub fn from_cells(size: Vector2, hidden_rows: i16, cells: &[i16]) -> Option<Self> {
+        if size.x <= 0 || size.y <= 0 || cells.len() != size.x as usize * size.y as usize {
+            return None;
+        }
+
+        let mut tile_map = TileMap::new(size, hidden_rows);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                tile_map.set_cell(x, y, cells[(y * size.x + x) as usize]);
+            }
+        }
+        Some(tile_map)
+    }
+
+    // Cells row by row from the top
+    pub fn cells(&self) -> Vec<i16> {
+        let mut cells = Vec::with_capacity(self.size.x as usize * self.size.y as usize);
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                cells.push(self._get_cell(x, y));
+            }
+        }
+        cells
+    }
+
+    // Walls on the left, right and bottom of the tile map
+    pub fn add_walls(&mut self) {
+        for x in 0..self.size.x {
+            self.set_cell(x, self.size.y-1, WALL_TILE);
+        }
+
+        for y in 0..self.size.y {
+            self.set_cell(0, y, WALL_TILE);
+            self.set_cell(self.size.x-1, y, WALL_TILE);
+        }
+    }
+
+    // Column where the 5x5 shape of a new block is placed so the block is in the middle
+    pub fn spawn_column(&self) -> i16 {
+        self.size.x / 2 - 2
+    }
+
+    // Center of the visible part of the tile map
+    pub fn get_center(&self) -> Vector2 {
+        let visible_rows = self.size.y - self.hidden_rows;
+        Vector2::new((0.5 * self.size.x as f32) as i16, (0.5 * visible_rows as f32) as i16)
+    }
+
+    pub fn _get_cell(&self, x: i16, y: i16) -> i16 {
+        if x < 0 || x >= self.size.x || y < 0 || y >= self.size.y {
+            1
+        }
+        else {
+            self.array[(x as usize, y as usize)]
+        }
+    }
+
+    pub fn _get_cellv(&self, position: Vector2) -> i16 {
+        self._get_cell(position.x, position.y)
+    }
+
+    pub fn set_cell(&mut self, x: i16, y: i16, tile: i16) {
+        self.array[(x as usize, y as usize)] = tile;
+    }
+
+    fn remove_row(&mut self, y: i16) {
+
+        // Remove row 'y'
+        for x in 1..self.size.x-1 {
+            self.set_cell(x, y, 0);
+        }
+
+        // Move rows down
+        for y2 in (0..y).rev() {
+            for x in 1..self.size.x-1 {
+                let cell = self._get_cell(x, y2);
+                if y2 < (self.size.y) {
+                    self.set_cell(x, y2+1, cell);
+                }
+            }
+        }
+    }
+
+    // Removes rows and moves rows above them down. Rows must be in ascending order.
+    pub fn remove_rows(&mut self, rows: &[i16]) {
+        for &y in rows {
+            self.remove_row(y);
+        }
+    }
+
+    // Pushes all rows up by one and adds a garbage row with a hole at column 'hole_x'
+    // to the bottom. Returns true if blocks were pushed out from the top (garbage out).
+    pub fn add_garbage_row(&mut self, hole_x: i16) -> bool {
+        let mut pushed_out = false;
+        for x in 1..self.size.x-1 {
+            if self._get_cell(x, 0) > 0 {
+                pushed_out = true;
+            }
+        }
+
+        // Move rows up
+        for y in 0..self.size.y-2 {
+            for x in 1..self.size.x-1 {
+                let cell = self._get_cell(x, y+1);
+                self.set_cell(x, y, cell);
+            }
+        }
+
+        let bottom = self.size.y - 2;
+        for x in 1..self.size.x-1 {
+            let tile = if x == hole_x { 0 } else { GARBAGE_TILE };
+            self.set_cell(x, bottom, tile);
+        }
+
+        pushed_out
+    }
+
+    // Indices of full rows from top to bottom
+    pub fn full_rows(&self) -> Vec<i16> {
+        let mut rows = Vec::new();
+        for y in 0..self.size.y-1 {
+            let mut row_is_full = true;
+            for x in 1..self.size.x-1 {
+                if self._get_cell(x, y) < 1 {
+                    row_is_full = false;
+                }
+            }
+            if row_is_full {
+                rows.push(y);
+            }
+        }
+        rows
+    }
+}
@@ -0,0 +1,204 @@
+use ratatui::crossterm::event::KeyCode;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use std::time::Duration;
+
+use crate::engine::{Engine, Event, Input, Rules, PREVIEW_LENGTH};
+use crate::finesse::Finesse;
+use crate::stats::{Stat, Stats};
+use crate::tile_map::{block_tile, TileSet, WALL_TILE};
+use crate::Vector2;
+
+// Terminal columns per cell, so cells look about square
+const CELL_WIDTH: u16 = 2;
+// Width of the hold and preview columns in cells
+const SIDE_CELLS: u16 = 6;
+// Width of the statistics column left of the hold
+const STATS_WIDTH: u16 = 28;
+const HELP: &str = "Arrows move, rotate and drop, Space hard drop, C hold, P pause, R restart, Q quit";
+
+/// Input of a key, the same keys as in the window
+pub fn key_input(code: KeyCode) -> Option<Input> {
+    match code {
+        KeyCode::Left => Some(Input::Left),
+        KeyCode::Right => Some(Input::Right),
+        KeyCode::Up => Some(Input::Rotate),
+        KeyCode::Down => Some(Input::SoftDrop),
+        KeyCode::Char(' ') => Some(Input::HardDrop),
+        KeyCode::Char('c') => Some(Input::Hold),
+        _ => None,
+    }
+}
+
+/// Terminal color of a tile color
+pub fn terminal_color(color: ggez::graphics::Color) -> Color {
+    let (r, g, b) = color.to_rgb();
+    Color::Rgb(r, g, b)
+}
+
+/// A single player game drawn in a terminal: the engine with statistics, without
+/// effects or sounds.
+pub struct TerminalGame {
+    pub engine: Engine,
+    pub stats: Stats,
+    pub finesse: Finesse,
+    pub tile_set: TileSet,
+    pub paused: bool,
+    rules: Rules,
+}
+
+impl TerminalGame {
+    pub fn new(rules: Rules, tile_set: TileSet) -> Self {
+        TerminalGame {
+            engine: Engine::new(rules.clone()),
+            stats: Stats::default(),
+            finesse: Finesse::default(),
+            tile_set,
+            paused: false,
+            rules,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.engine = Engine::new(self.rules.clone());
+        self.stats = Stats::default();
+        self.finesse = Finesse::default();
+        self.paused = false;
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.engine.top_out().is_some()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused && !self.is_over();
+    }
+
+    /// Moves the block, nothing happens while paused or after the game is over
+    pub fn input(&mut self, input: Input) {
+        if self.paused || self.is_over() {
+            return;
+        }
+        self.stats.keys += 1;
+        self.finesse.input(&self.engine, input);
+        self.engine.apply(input);
+        self.handle_events();
+    }
+
+    /// Advances the game by 'dt' unless it is paused or over
+    pub fn update(&mut self, dt: Duration) {
+        if self.paused || self.is_over() {
+            return;
+        }
+        self.engine.update(dt);
+        self.stats.time += dt;
+        self.handle_events();
+    }
+
+    // Counts statistics and judges finesse for the events of the engine
+    fn handle_events(&mut self) {
+        for event in self.engine.drain_events() {
+            self.stats.record(&event);
+            if let Event::Locked(block_type, cells) = &event {
+                self.finesse.lock(self.engine.pieces(), *block_type, cells);
+            }
+        }
+        self.finesse.watch(&self.engine);
+    }
+
+    /// Draws the board in the middle of the frame, statistics and the held block left
+    /// of it, the next blocks right of it and a line of help or the state of the game
+    /// under it. Parts which don't fit in the terminal are cut off.
+    pub fn draw(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let tile_map = &self.engine.tile_map;
+        let board_width = tile_map.size.x as u16 * CELL_WIDTH;
+        let board_height = (tile_map.size.y - tile_map.hidden_rows) as u16;
+        let side = SIDE_CELLS * CELL_WIDTH;
+
+        let left = area.x + area.width.saturating_sub(STATS_WIDTH + 2 * side + board_width) / 2;
+        let top = area.y + area.height.saturating_sub(board_height + 2) / 2;
+        let board_x = left + STATS_WIDTH + side;
+        let mut render = |paragraph: Paragraph, rect: Rect| frame.render_widget(paragraph, rect.intersection(area));
+
+        let stats: Vec<Line> = Stat::ALL.iter().map(|stat| Line::from(stat.text(&self.stats, &self.finesse))).collect();
+        render(Paragraph::new(stats).alignment(Alignment::Right), Rect::new(left, top, STATS_WIDTH - CELL_WIDTH, board_height));
+
+        let mut hold = vec![Line::from("Hold")];
+        if let Some(block_type) = self.engine.held() {
+            // Grayed out until it can be swapped again
+            let tile = if self.engine.can_hold() { block_tile(block_type) } else { WALL_TILE };
+            hold.extend(self.piece_lines(block_type, tile));
+        }
+        render(Paragraph::new(hold), Rect::new(left + STATS_WIDTH, top, side, board_height));
+
+        render(Paragraph::new(self.board_lines()), Rect::new(board_x, top, board_width, board_height));
+
+        let mut next = vec![Line::from("Next")];
+        for &block_type in self.engine.preview().iter().take(PREVIEW_LENGTH) {
+            next.extend(self.piece_lines(block_type, block_tile(block_type)));
+            next.push(Line::from(""));
+        }
+        render(Paragraph::new(next), Rect::new(board_x + board_width + CELL_WIDTH, top, side, board_height));
+
+        let message = match self.engine.top_out() {
+            Some(top_out) => format!("GAME OVER: {}. R starts again, Q quits", top_out.description()),
+            None if self.paused => "PAUSED, P goes on".to_string(),
+            None => HELP.to_string(),
+        };
+        let message = Paragraph::new(message).alignment(Alignment::Center);
+        render(message, Rect::new(area.x, top + board_height + 1, area.width, 1));
+    }
+
+    // Visible rows of the tile map with the falling block, rows being cleared are white
+    fn board_lines(&self) -> Vec<Line<'static>> {
+        let tile_map = &self.engine.tile_map;
+        (tile_map.hidden_rows..tile_map.size.y).map(|y| {
+            let cleared = self.engine.cleared_rows().contains(&y);
+            let cells: Vec<Span> = (0..tile_map.size.x).map(|x| {
+                let tile = tile_map._get_cell(x, y);
+                let color = match cleared && tile != WALL_TILE {
+                    true => Color::White,
+                    false => self.tile_color(tile),
+                };
+                cell(color)
+            }).collect();
+            Line::from(cells)
+        }).collect()
+    }
+
+    // Rows of a block of 'block_type' in the color of 'tile', as small as the block
+    fn piece_lines(&self, block_type: u8, tile: i16) -> Vec<Line<'static>> {
+        let cells = self.engine.pieces().block(Vector2::new(0, 0), block_type).cells();
+        let (left, right) = (cells.iter().map(|c| c.x).min(), cells.iter().map(|c| c.x).max());
+        let (top, bottom) = (cells.iter().map(|c| c.y).min(), cells.iter().map(|c| c.y).max());
+        let (left, right, top, bottom) = match (left, right, top, bottom) {
+            (Some(left), Some(right), Some(top), Some(bottom)) => (left, right, top, bottom),
+            _ => return Vec::new(),
+        };
+        let color = self.tile_color(tile);
+        (top..=bottom).map(|y| {
+            let row: Vec<Span> = (left..=right)
+                .map(|x| match cells.contains(&Vector2::new(x, y)) {
+                    true => cell(color),
+                    false => Span::raw("  "),
+                })
+                .collect();
+            Line::from(row)
+        }).collect()
+    }
+
+    fn tile_color(&self, tile: i16) -> Color {
+        let tiles = &self.tile_set.tiles;
+        tiles.get(tile as usize).or_else(|| tiles.first()).map_or(Color::Reset, |t| terminal_color(t.color))
+    }
+}
+
+// One cell of the board, two columns wide
+fn cell(color: Color) -> Span<'static> {
+    Span::styled("  ", Style::default().bg(color))
+}
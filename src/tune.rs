@@ -0,0 +1,238 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+use crate::bot::{Bot, Weights};
+use crate::engine::Rules;
+use crate::sim;
+
+/// Best individuals copied unchanged into the next generation
+pub const ELITES: usize = 2;
+/// Smallest population which breeds at least one new individual
+pub const MIN_POPULATION: usize = ELITES + 1;
+// Individuals competing for each parent
+const TOURNAMENT_SIZE: usize = 3;
+
+/// Settings of a tuning run. Everything random is derived from 'seed', so the same
+/// settings give the same weights, whatever the number of threads.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Config {
+    pub seed: u64,
+    pub population: usize,
+    /// Games each individual plays per generation, the same games for all of them
+    pub games: u64,
+    /// Blocks per game
+    pub pieces: u32,
+    /// Chance for each weight to be mutated
+    pub mutation_rate: f32,
+    /// Standard deviation of a mutation, relative to the size of the weight
+    pub mutation_size: f32,
+    pub board_width: i16,
+    pub board_height: i16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let rules = Rules::default();
+        Config {
+            seed: 0,
+            population: 20,
+            games: 3,
+            pieces: 300,
+            mutation_rate: 0.3,
+            mutation_size: 0.3,
+            board_width: rules.board_width,
+            board_height: rules.board_height,
+        }
+    }
+}
+
+impl Config {
+    /// Rules of the games, the default ones on a board of the configured size
+    pub fn rules(&self) -> Rules {
+        Rules {
+            board_width: self.board_width,
+            board_height: self.board_height,
+            ..Rules::default()
+        }
+    }
+
+    /// Checks that the population is large enough to breed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.population < MIN_POPULATION {
+            return Err(format!("population must be at least {}", MIN_POPULATION));
+        }
+        Ok(())
+    }
+}
+
+/// Weights with the fitness they reached.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Individual {
+    pub weights: Weights,
+    pub fitness: f32,
+}
+
+/// State of a tuning run, written to disk after every generation so the run can
+/// be continued.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub config: Config,
+    /// Generations evaluated so far
+    pub generation: u64,
+    /// Weights to evaluate in the next generation
+    pub population: Vec<Weights>,
+    /// Best individual of each generation so far
+    pub history: Vec<Individual>,
+}
+
+impl Checkpoint {
+    /// Starts with the default weights and mutations of them
+    pub fn new(config: Config) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let mut population = vec![Weights::default()];
+        while population.len() < config.population.max(MIN_POPULATION) {
+            let mut genes = genes(&Weights::default());
+            for gene in genes.iter_mut() {
+                *gene += normal(&mut rng) * (gene.abs() + 1.0);
+            }
+            population.push(from_genes(&genes));
+        }
+        Checkpoint { config, generation: 0, population, history: Vec::new() }
+    }
+
+    /// Fails if the checkpoint has too small a population to continue with
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let checkpoint: Checkpoint = serde_json::from_str(&json).map_err(io::Error::other)?;
+        if checkpoint.population.len() < MIN_POPULATION {
+            let reason = format!("{}: population must be at least {}", path.display(), MIN_POPULATION);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes to a temporary file first, so a crash doesn't leave half a checkpoint
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Best weights found so far
+    pub fn best(&self) -> Option<&Individual> {
+        self.history.iter().max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+    }
+
+    /// Evaluates the population on 'threads' threads and breeds the next one.
+    /// Returns the individuals of the evaluated generation, best first.
+    pub fn step(&mut self, threads: usize) -> Vec<Individual> {
+        let config = &self.config;
+        let game_seeds: Vec<u64> = (0..config.games)
+            .map(|i| config.seed.wrapping_add(self.generation.wrapping_mul(config.games)).wrapping_add(i))
+            .collect();
+
+        let mut individuals = evaluate(&self.population, config, &game_seeds, threads);
+        individuals.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+
+        let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(self.generation + 1));
+        let mut next: Vec<Weights> = individuals.iter().take(ELITES).map(|i| i.weights.clone()).collect();
+        while next.len() < individuals.len() {
+            let a = tournament(&individuals, &mut rng);
+            let b = tournament(&individuals, &mut rng);
+            next.push(breed(a, b, config, &mut rng));
+        }
+
+        self.history.push(individuals[0].clone());
+        self.population = next;
+        self.generation += 1;
+        individuals
+    }
+}
+
+// Fitness of each weights, the mean of lines cleared plus attack sent over the games.
+// Games end early when the bot tops out, which costs it the lines it would have cleared.
+fn evaluate(population: &[Weights], config: &Config, game_seeds: &[u64], threads: usize) -> Vec<Individual> {
+    let rules = config.rules();
+    let fitness = |weights: &Weights| {
+        let mut bot = Bot::new(weights.clone(), 2.0);
+        let total: u32 = game_seeds.iter()
+            .map(|&seed| sim::run_game(rules.clone(), &mut bot, seed, config.pieces, false))
+            .map(|stats| stats.lines + stats.attack)
+            .sum();
+        total as f32 / game_seeds.len().max(1) as f32
+    };
+
+    let chunk_size = population.len().div_ceil(threads.max(1)).max(1);
+    let fitnesses: Vec<f32> = thread::scope(|scope| {
+        let handles: Vec<_> = population.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(&fitness).collect::<Vec<f32>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    });
+
+    population.iter().zip(fitnesses)
+        .map(|(weights, fitness)| Individual { weights: weights.clone(), fitness })
+        .collect()
+}
+
+// Fittest of a few random individuals
+fn tournament<'a>(individuals: &'a [Individual], rng: &mut StdRng) -> &'a Weights {
+    let mut best = &individuals[rng.gen_range(0, individuals.len())];
+    for _ in 1..TOURNAMENT_SIZE {
+        let other = &individuals[rng.gen_range(0, individuals.len())];
+        if other.fitness > best.fitness {
+            best = other;
+        }
+    }
+    &best.weights
+}
+
+// Each weight from either parent, some of them mutated
+fn breed(a: &Weights, b: &Weights, config: &Config, rng: &mut StdRng) -> Weights {
+    let genes: Vec<f32> = genes(a).iter().zip(genes(b))
+        .map(|(&a, b)| {
+            let gene = if rng.gen_bool(0.5) { a } else { b };
+            if rng.gen::<f32>() < config.mutation_rate {
+                gene + normal(rng) * config.mutation_size * (gene.abs() + 0.1)
+            } else {
+                gene
+            }
+        })
+        .collect();
+    from_genes(&genes)
+}
+
+// Standard normal random number with the Box-Muller transform
+fn normal(rng: &mut StdRng) -> f32 {
+    let u: f32 = rng.gen_range(f32::EPSILON, 1.0);
+    let v: f32 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+}
+
+// Weights as a list of numbers and back, the clear rewards last
+fn genes(weights: &Weights) -> Vec<f32> {
+    let mut genes = vec![weights.holes, weights.bumpiness, weights.height, weights.max_height,
+                         weights.wells, weights.t_slots, weights.t_spin];
+    genes.extend_from_slice(&weights.clears);
+    genes
+}
+
+fn from_genes(genes: &[f32]) -> Weights {
+    Weights {
+        holes: genes[0],
+        bumpiness: genes[1],
+        height: genes[2],
+        max_height: genes[3],
+        wells: genes[4],
+        t_slots: genes[5],
+        t_spin: genes[6],
+        clears: [genes[7], genes[8], genes[9], genes[10], genes[11]],
+    }
+}
@@ -0,0 +1,34 @@
+use crate::engine::{Clear, TSpin};
+
+// Extra garbage rows for combos, indexed by Clear::combo
+const COMBO_ATTACK: [u32; 13] = [0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+// Extra garbage rows for clearing the whole board
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+/// Garbage rows sent to the opponent by a clear, following the guideline attack table:
+/// double 1, triple 2, tetris 4, T-spin 2 per row, mini T-spin one less than rows,
+/// plus 1 for back-to-back, combo bonus and perfect clear bonus.
+pub fn attack(clear: &Clear) -> u32 {
+    let lines = clear.lines() as u32;
+
+    let mut attack = match clear.t_spin {
+        TSpin::Full => 2 * lines,
+        TSpin::Mini => lines.saturating_sub(1),
+        TSpin::None => match lines {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            _ => 4,
+        },
+    };
+
+    if clear.back_to_back {
+        attack += 1;
+    }
+    attack += COMBO_ATTACK[(clear.combo as usize).min(COMBO_ATTACK.len() - 1)];
+    if clear.perfect_clear {
+        attack += PERFECT_CLEAR_ATTACK;
+    }
+
+    attack
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use jetris::bot::{self, Bot, Weights};
+use jetris::engine::{Engine, Event, Rules};
+use jetris::tile_map::GARBAGE_TILE;
+
+const I_BLOCK: u8 = 1;
+
+// Engine with an I to play and the bottom row full but for a gap of four cells
+fn single_ready() -> Engine {
+    let rules = Rules { line_clear_delay: Duration::from_millis(0), ..Rules::default() };
+    let mut engine = Engine::with_seed(rules, 0);
+    engine.play_sequence(&[I_BLOCK, I_BLOCK]);
+    let mut field = engine.field();
+    let bottom = field.size.y - 2;
+    for x in (1..field.size.x-1).filter(|x| !(3..7).contains(x)) {
+        field.set_cell(x, bottom, GARBAGE_TILE);
+    }
+    engine.set_field(field);
+    engine
+}
+
+#[test]
+fn bot_fills_the_gap_to_clear_the_row() {
+    let mut engine = single_ready();
+    Bot::new(Weights::default(), 1.0).play(&mut engine);
+
+    let cleared = engine.drain_events().into_iter()
+        .find_map(|event| match event {
+            Event::LinesCleared(clear) => Some(clear),
+            _ => None,
+        })
+        .expect("no rows cleared");
+    assert_eq!(cleared.lines(), 1);
+    assert!(cleared.perfect_clear);
+}
+
+#[test]
+fn weights_which_are_not_numbers_still_give_a_placement() {
+    let engine = single_ready();
+    let weights = Weights { holes: f32::NAN, ..Weights::default() };
+    let placement = bot::best_placement(&engine.field(), engine.pieces(), &engine.block, engine.held(),
+                                        engine.can_hold(), &[], &weights);
+    assert!(placement.is_some());
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use jetris::editor::Editor;
+use jetris::engine::Rules;
+use jetris::pieces::PieceSet;
+use jetris::puzzle::Goal;
+use jetris::tbp::Piece;
+use jetris::tile_map::{block_tile, GARBAGE_TILE};
+use jetris::Vector2;
+
+// Bottom row of the field in the tile map of the default board
+const BOTTOM: i16 = 39;
+
+#[test]
+fn strokes_are_undone_and_redone_at_once() {
+    let mut editor = Editor::new(&Rules::default());
+    editor.start_stroke();
+    for x in 1..=3 {
+        editor.paint(Vector2::new(x, BOTTOM), GARBAGE_TILE);
+    }
+    editor.start_stroke();
+    editor.paint(Vector2::new(4, BOTTOM), GARBAGE_TILE);
+    // Walls can't be painted over
+    editor.paint(Vector2::new(0, BOTTOM), 0);
+
+    assert!(editor.undo());
+    assert_eq!(editor.layout.field._get_cell(4, BOTTOM), 0);
+    assert_eq!(editor.layout.field._get_cell(3, BOTTOM), GARBAGE_TILE);
+    assert!(editor.undo());
+    assert_eq!(editor.layout.field._get_cell(1, BOTTOM), 0);
+    assert!(!editor.undo());
+
+    assert!(editor.redo());
+    assert!(editor.redo());
+    assert_eq!(editor.layout.field._get_cell(4, BOTTOM), GARBAGE_TILE);
+    assert!(!editor.redo());
+}
+
+#[test]
+fn layout_is_saved_as_a_puzzle() {
+    let mut editor = Editor::new(&Rules::default());
+    editor.set_brush(GARBAGE_TILE);
+    editor.start_stroke();
+    for x in 1..=8 {
+        editor.paint(Vector2::new(x, BOTTOM), editor.brush);
+    }
+    assert!(editor.set_brush(3));
+    assert!(!editor.set_brush(8));
+    editor.paint(Vector2::new(1, BOTTOM - 1), editor.brush);
+    // Without blocks there is nothing to play
+    assert!(editor.to_puzzle().is_err());
+
+    editor.push_piece(Piece::I);
+    editor.push_piece(Piece::O);
+    editor.pop_piece();
+    editor.next_goal();
+    editor.toggle_hold();
+    let puzzle = editor.to_puzzle().unwrap();
+    assert_eq!(puzzle.field, vec!["T.........", "XXXXXXXX.."]);
+    assert_eq!(puzzle.pieces, "I");
+    assert_eq!(puzzle.goal, Goal::Lines { lines: 2 });
+    assert!(!puzzle.hold);
+
+    let reloaded = Editor::from_puzzle(&puzzle);
+    assert_eq!(reloaded.to_puzzle().unwrap().field, puzzle.field);
+}
+
+#[test]
+fn fumen_keeps_the_field_and_queue() {
+    let mut editor = Editor::new(&Rules::default());
+    editor.set_brush(GARBAGE_TILE);
+    editor.start_stroke();
+    for x in 1..=9 {
+        editor.paint(Vector2::new(x, BOTTOM), editor.brush);
+    }
+    editor.push_piece(Piece::T);
+    editor.push_piece(Piece::S);
+    let fumen = editor.to_fumen().unwrap();
+
+    let loaded = Editor::from_fumen(&fumen).unwrap();
+    assert_eq!(loaded.layout.pieces, "TS");
+    assert_eq!(loaded.to_puzzle().unwrap().field, vec!["XXXXXXXXX."]);
+}
+
+#[test]
+fn every_piece_of_the_set_is_a_brush() {
+    let pieces = PieceSet::load(Path::new("pieces/pentominoes.json")).unwrap();
+    let mut editor = Editor::new(&Rules { pieces, ..Rules::default() });
+    assert_eq!(editor.brushes().len(), 2 + 18);
+    assert!(editor.set_brush(block_tile(17)));
+    assert_eq!(editor.brush_name(), "S");
+    assert!(!editor.set_brush(block_tile(18)));
+
+    // Cycling goes past the last piece back to empty
+    editor.next_brush();
+    assert_eq!(editor.brush_name(), "empty");
+    editor.next_brush();
+    assert_eq!(editor.brush_name(), "garbage");
+    editor.next_brush();
+    assert_eq!(editor.brush_name(), "F");
+}
+
+#[test]
+fn cells_above_the_visible_field_are_not_painted() {
+    let mut editor = Editor::new(&Rules::default());
+    let hidden = editor.layout.field.hidden_rows;
+    editor.push_piece(Piece::I);
+    editor.start_stroke();
+    editor.paint(Vector2::new(1, hidden - 1), GARBAGE_TILE);
+    editor.paint(Vector2::new(1, hidden), GARBAGE_TILE);
+    assert_eq!(editor.layout.field._get_cell(1, hidden - 1), 0);
+    assert_eq!(editor.layout.field._get_cell(1, hidden), GARBAGE_TILE);
+    assert!(editor.to_puzzle().is_ok());
+
+    // A fumen can have them, the puzzle would lose them
+    editor.layout.field.set_cell(1, hidden - 1, GARBAGE_TILE);
+    assert!(editor.to_puzzle().is_err());
+    editor.paint(Vector2::new(1, hidden - 1), 0);
+    assert!(editor.to_puzzle().is_ok());
+}
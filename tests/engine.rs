@@ -0,0 +1,206 @@
+use jetris::engine::{Engine, Event, Input, Rules, TopOut, BUFFER_ROWS, MAX_BOARD_WIDTH, MIN_BOARD_WIDTH};
+use jetris::pieces::PieceSet;
+use jetris::tile_map::{TileMap, GARBAGE_TILE, WALL_TILE};
+
+use std::path::Path;
+use std::time::Duration;
+
+const O_BLOCK: u8 = 0;
+const I_BLOCK: u8 = 1;
+
+// Field of 'engine' with garbage in 'rows', leaving out column 1 so no row is full and
+// the cells of the falling block so it still fits
+fn stacked(engine: &Engine, rows: std::ops::RangeInclusive<i16>) -> TileMap {
+    let mut field = engine.field();
+    let block = engine.block.cells();
+    for y in rows {
+        for x in 2..field.size.x-1 {
+            if !block.iter().any(|c| c.x == x && c.y == y) {
+                field.set_cell(x, y, GARBAGE_TILE);
+            }
+        }
+    }
+    field
+}
+
+#[test]
+fn block_spawning_into_the_stack_is_a_block_out() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[I_BLOCK, I_BLOCK]);
+    let field = stacked(&engine, 10..=engine.tile_map.size.y-2);
+    engine.set_field(field);
+    assert_eq!(engine.top_out(), None);
+
+    // Locks in the visible field, the next block has no room in the buffer zone
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.top_out(), Some(TopOut::Block));
+}
+
+#[test]
+fn block_locking_above_the_visible_field_is_a_lock_out() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[I_BLOCK, O_BLOCK]);
+    let hidden_rows = engine.tile_map.hidden_rows;
+    let field = stacked(&engine, hidden_rows..=engine.tile_map.size.y-2);
+    engine.set_field(field);
+
+    // The O spawns on the stack without dropping into the visible field
+    engine.apply(Input::Hold);
+    assert_eq!(engine.block.block_type, O_BLOCK);
+    assert!(engine.block.cells().iter().all(|c| c.y < hidden_rows));
+    assert_eq!(engine.top_out(), None);
+
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.top_out(), Some(TopOut::Lock));
+}
+
+#[test]
+fn garbage_pushing_the_stack_out_is_a_garbage_out() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[I_BLOCK, I_BLOCK]);
+    // A column next to the wall reaching the top of the buffer zone
+    let mut field = engine.field();
+    for y in 0..field.size.y-1 {
+        field.set_cell(1, y, GARBAGE_TILE);
+    }
+    engine.set_field(field);
+    engine.receive_garbage(1);
+
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.top_out(), Some(TopOut::Garbage));
+
+    // Nothing afterwards changes how the game ended
+    engine.apply(Input::HardDrop);
+    engine.update(Duration::from_secs(1));
+    assert_eq!(engine.top_out(), Some(TopOut::Garbage));
+}
+
+#[test]
+fn walls_and_spawn_follow_the_board_size() {
+    let rules = Rules { board_width: 6, board_height: 12, ..Rules::default() };
+    let engine = Engine::with_seed(rules, 0);
+    let field = engine.field();
+    assert_eq!((field.size.x, field.size.y), (8, BUFFER_ROWS + 13));
+
+    // Walls on both sides of the 6 columns, the bottom row under the 12 visible ones
+    for y in 0..field.size.y {
+        assert_eq!(field._get_cell(0, y), WALL_TILE);
+        assert_eq!(field._get_cell(7, y), WALL_TILE);
+    }
+    let bottom = BUFFER_ROWS + 12;
+    assert!((0..8).all(|x| field._get_cell(x, bottom) == WALL_TILE));
+    assert!((1..7).all(|x| field._get_cell(x, bottom - 1) == 0));
+
+    // The 5x5 shape of new blocks is in the middle, so the block is inside the walls
+    assert_eq!(field.spawn_column(), 2);
+    assert!(engine.block.cells().iter().all(|c| c.x >= 1 && c.x <= 6));
+}
+
+#[test]
+fn blocks_land_on_the_bottom_row_of_a_taller_board() {
+    let rules = Rules { board_height: 30, ..Rules::default() };
+    let mut engine = Engine::with_seed(rules, 0);
+    engine.apply(Input::HardDrop);
+    let lowest = engine.drain_events().into_iter()
+        .find_map(|event| match event {
+            Event::Locked(_, cells) => cells.iter().map(|c| c.y).max(),
+            _ => None,
+        });
+    assert_eq!(lowest, Some(BUFFER_ROWS + 29));
+}
+
+#[test]
+fn board_must_fit_the_widest_piece() {
+    let pentominoes = PieceSet::load(Path::new("pieces/pentominoes.json")).unwrap();
+    let mut rules = Rules { board_width: MIN_BOARD_WIDTH, pieces: pentominoes, ..Rules::default() };
+    assert_eq!(rules.min_board_width(), 5);
+    assert!(rules.validate().is_err());
+
+    rules.clamp_board();
+    assert_eq!(rules.board_width, 5);
+    assert!(rules.validate().is_ok());
+
+    let mut huge = Rules { board_width: i16::MAX, board_height: 0, ..Rules::default() };
+    assert!(huge.validate().is_err());
+    huge.clamp_board();
+    assert_eq!((huge.board_width, huge.board_height), (MAX_BOARD_WIDTH, 1));
+    assert!(Rules::default().validate().is_ok());
+}
+
+// Engine with the bottom four rows full but for column 6, where the first I drops in
+fn tetris_ready(rules: Rules) -> Engine {
+    let mut engine = Engine::with_seed(rules, 0);
+    engine.play_sequence(&[I_BLOCK, I_BLOCK]);
+    let mut field = engine.field();
+    let bottom = field.size.y - 2;
+    for y in bottom-3..=bottom {
+        for x in (1..field.size.x-1).filter(|&x| x != 6) {
+            field.set_cell(x, y, GARBAGE_TILE);
+        }
+    }
+    engine.set_field(field);
+    assert!(engine.block.cells().iter().all(|c| c.x == 6));
+    engine
+}
+
+#[test]
+fn cleared_rows_stay_until_the_delay_is_over() {
+    let rules = Rules { line_clear_delay: Duration::from_millis(400), ..Rules::default() };
+    let mut engine = tetris_ready(rules);
+    let bottom = engine.tile_map.size.y - 2;
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.cleared_rows(), &[bottom-3, bottom-2, bottom-1, bottom][..]);
+    assert_eq!(engine.clear_progress(), 0.0);
+    let dealt = engine.blocks_dealt();
+    engine.drain_events();
+
+    // Halfway the rows are still on the field and no block has spawned
+    engine.update(Duration::from_millis(200));
+    assert!((engine.clear_progress() - 0.5).abs() < 1e-6);
+    assert_eq!(engine.cleared_rows().len(), 4);
+    assert!((1..11).all(|x| engine.field()._get_cell(x, bottom) > 0));
+    assert_eq!(engine.blocks_dealt(), dealt);
+    assert!(!engine.can_move());
+    engine.apply(Input::Left);
+    engine.apply(Input::HardDrop);
+    assert!(engine.drain_events().iter().all(|event| !matches!(event, Event::Locked(..))));
+
+    // Once the delay is over the rows collapse and the next block spawns
+    engine.update(Duration::from_millis(200));
+    assert!(engine.cleared_rows().is_empty());
+    assert!((1..11).all(|x| engine.field()._get_cell(x, bottom) == 0));
+    assert_eq!(engine.blocks_dealt(), dealt + 1);
+    assert!(engine.can_move());
+}
+
+#[test]
+fn rows_collapse_right_away_without_a_delay() {
+    let rules = Rules { line_clear_delay: Duration::from_millis(0), ..Rules::default() };
+    let mut engine = tetris_ready(rules);
+    let dealt = engine.blocks_dealt();
+    engine.apply(Input::HardDrop);
+    assert!(engine.cleared_rows().is_empty());
+    assert_eq!(engine.clear_progress(), 1.0);
+    assert_eq!(engine.blocks_dealt(), dealt + 1);
+}
+
+#[test]
+fn playing_a_sequence_mid_clear_keeps_the_stack() {
+    let rules = Rules { line_clear_delay: Duration::from_millis(400), ..Rules::default() };
+    let mut engine = tetris_ready(rules);
+    let bottom = engine.tile_map.size.y - 2;
+    // A cell above the four rows, which ends up on the bottom row once they collapse
+    let mut field = engine.field();
+    field.set_cell(2, bottom - 4, GARBAGE_TILE);
+    engine.set_field(field);
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.cleared_rows().len(), 4);
+
+    engine.play_sequence(&[O_BLOCK]);
+    assert!(engine.cleared_rows().is_empty());
+    assert_eq!(engine.block.block_type, O_BLOCK);
+    let field = engine.field();
+    assert_eq!(field._get_cell(2, bottom), GARBAGE_TILE);
+    assert!((1..11).filter(|&x| x != 2).all(|x| field._get_cell(x, bottom) == 0));
+    assert!((1..11).all(|x| field._get_cell(x, bottom - 1) == 0));
+}
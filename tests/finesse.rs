@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use jetris::engine::{self, Engine, Event, Input, Rules};
+use jetris::finesse::{self, Finesse, Trainer};
+use jetris::pieces::PieceSet;
+use jetris::placement;
+use jetris::Vector2;
+
+const O_BLOCK: u8 = 0;
+const I_BLOCK: u8 = 1;
+
+// Cells a new block of 'block_type' lands on when dropped 'dx' columns from where it spawns
+fn dropped_at(engine: &Engine, block_type: u8, dx: i16) -> Vec<Vector2> {
+    let field = engine.field();
+    let (mut block, _) = engine::spawn(&field, engine.pieces(), block_type);
+    block.position.x += dx;
+    placement::dropped(&field, &block).cells()
+}
+
+// Plays 'inputs' with the finesse of the player watching, returns the faults of each lock
+fn play(engine: &mut Engine, finesse: &mut Finesse, inputs: &[Input]) -> Vec<u32> {
+    let mut faults = Vec::new();
+    finesse.watch(engine);
+    for &input in inputs {
+        finesse.input(engine, input);
+        engine.apply(input);
+        for event in engine.drain_events() {
+            if let Event::Locked(block_type, cells) = event {
+                faults.extend(finesse.lock(engine.pieces(), block_type, &cells).map(|j| j.faults));
+            }
+        }
+        finesse.watch(engine);
+    }
+    faults
+}
+
+#[test]
+fn fewest_inputs_to_places_of_an_o() {
+    let engine = Engine::with_seed(Rules::default(), 0);
+    let field = engine.field();
+    let fewest = |dx| finesse::fewest_inputs(&field, engine.pieces(), O_BLOCK, &dropped_at(&engine, O_BLOCK, dx));
+
+    assert_eq!(fewest(0), Some(vec![Input::HardDrop]));
+    assert_eq!(fewest(-1), Some(vec![Input::Left, Input::HardDrop]));
+    assert_eq!(fewest(3), Some(vec![Input::Right, Input::Right, Input::Right, Input::HardDrop]));
+
+    // In the air is no place a block can lock on
+    let floating: Vec<Vector2> = dropped_at(&engine, O_BLOCK, 0).iter().map(|c| c - Vector2::new(0, 5)).collect();
+    assert_eq!(finesse::fewest_inputs(&field, engine.pieces(), O_BLOCK, &floating), None);
+}
+
+#[test]
+fn extra_moves_are_faults() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[O_BLOCK, O_BLOCK, O_BLOCK]);
+    let mut finesse = Finesse::default();
+
+    // Back and forth ends where a hard drop alone would have put it
+    let faults = play(&mut engine, &mut finesse, &[Input::Left, Input::Right, Input::Left, Input::Right, Input::HardDrop]);
+    assert_eq!(faults, vec![4]);
+    assert_eq!(finesse.last.as_ref().map(|j| j.best.clone()), Some(vec![Input::HardDrop]));
+
+    // The shortest way has no faults
+    let faults = play(&mut engine, &mut finesse, &[Input::Left, Input::Left, Input::HardDrop]);
+    assert_eq!(faults, vec![0]);
+
+    assert_eq!(finesse.total_pieces(), 2);
+    assert_eq!(finesse.total_faults(), 4);
+    assert_eq!(finesse.faults[O_BLOCK as usize], 4);
+}
+
+#[test]
+fn soft_drops_and_holds_are_no_faults() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[O_BLOCK, I_BLOCK, O_BLOCK]);
+    let mut finesse = Finesse::default();
+
+    let faults = play(&mut engine, &mut finesse, &[Input::SoftDrop, Input::SoftDrop, Input::HardDrop]);
+    assert_eq!(faults, vec![0]);
+
+    // The I is put on hold, only the inputs of the O which comes instead count
+    let faults = play(&mut engine, &mut finesse, &[Input::Left, Input::Hold, Input::Right, Input::HardDrop]);
+    assert_eq!(faults, vec![0]);
+    assert_eq!(finesse.last.as_ref().map(|j| j.block_type), Some(O_BLOCK));
+    assert_eq!(finesse.total_faults(), 0);
+}
+
+#[test]
+fn faults_are_counted_per_piece_of_the_set() {
+    let pieces = PieceSet::load(Path::new("pieces/pentominoes.json")).unwrap();
+    let x = pieces.pieces.iter().position(|piece| piece.name == "X").unwrap() as u8;
+    let mut engine = Engine::with_seed(Rules { pieces, ..Rules::default() }, 0);
+    engine.play_sequence(&[x, x]);
+    let mut finesse = Finesse::default();
+
+    let faults = play(&mut engine, &mut finesse, &[Input::Left, Input::Right, Input::HardDrop]);
+    assert_eq!(faults, vec![2]);
+    assert_eq!(finesse.pieces.len(), 18);
+    assert_eq!(finesse.faults[x as usize], 2);
+    assert_eq!(finesse.summary(engine.pieces()), "Finesse faults per block: X 2/1");
+}
+
+#[test]
+fn trainer_counts_blocks_placed_on_their_target() {
+    let engine = Engine::with_seed(Rules::default(), 0);
+    let mut trainer = Trainer::default();
+    trainer.watch(&engine);
+    let target = trainer.target.clone().expect("no target for a block which can move");
+    assert!(!target.inputs.contains(&Input::SoftDrop));
+    assert!(!trainer.too_many(finesse_count(&target.inputs)));
+    assert!(trainer.too_many(finesse_count(&target.inputs) + 1));
+
+    trainer.lock(&target.block.cells());
+    assert_eq!((trainer.placed, trainer.hits, trainer.last_hit), (1, 1, Some(true)));
+
+    // Somewhere else, and a lock without a target, are misses
+    trainer.watch(&engine);
+    let elsewhere: Vec<Vector2> = target.block.cells().iter().map(|c| c - Vector2::new(0, 1)).collect();
+    trainer.lock(&elsewhere);
+    trainer.lock(&target.block.cells());
+    assert_eq!((trainer.placed, trainer.hits, trainer.last_hit), (3, 1, Some(false)));
+    assert!(!trainer.is_done());
+}
+
+// Inputs which count for finesse
+fn finesse_count(inputs: &[Input]) -> u32 {
+    inputs.iter().filter(|&&input| input != Input::SoftDrop && input != Input::Hold).count() as u32
+}
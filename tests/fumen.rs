@@ -0,0 +1,143 @@
+use jetris::fumen::{self, Page};
+use jetris::tbp::{Orientation, Piece, PieceLocation};
+use jetris::tile_map::{TileMap, GARBAGE_TILE};
+use jetris::Vector2;
+
+// Tile at x from the left and y from the bottom of the field
+fn tile(tile_map: &TileMap, x: i16, y: i16) -> i16 {
+    tile_map._get_cell(x + 1, tile_map.size.y - 2 - y)
+}
+
+fn filled(tile_map: &TileMap) -> usize {
+    (0..10).flat_map(|x| (0..23).map(move |y| (x, y)))
+        .filter(|&(x, y)| tile(tile_map, x, y) != 0)
+        .count()
+}
+
+#[test]
+fn empty_field() {
+    let pages = fumen::decode("v115@vhAAgH").unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(filled(&pages[0].field), 0);
+    assert!(pages[0].piece.is_none());
+    assert_eq!(fumen::encode(&pages).unwrap(), "v115@vhAAgH");
+}
+
+#[test]
+fn gray_field_from_a_fumen_address() {
+    let text = "https://harddrop.com/fumen/?v115@9gF8DeF8DeF8DeF8NeAgH";
+    let pages = fumen::decode(text).unwrap();
+    let field = &pages[0].field;
+    assert_eq!(filled(field), 24);
+    for y in 0..4 {
+        assert!((0..6).all(|x| tile(field, x, y) == GARBAGE_TILE));
+        assert!((6..10).all(|x| tile(field, x, y) == 0));
+    }
+    assert_eq!(fumen::encode(&pages).unwrap(), "v115@9gF8DeF8DeF8DeF8NeAgH");
+}
+
+#[test]
+fn placed_piece_is_on_the_next_page() {
+    let pages = fumen::decode("v115@vhBVQJAAA").unwrap();
+    assert_eq!(pages.len(), 2);
+    let t = PieceLocation { piece: Piece::T, orientation: Orientation::North, x: 4, y: 0 };
+    assert_eq!(pages[0].piece, Some(t));
+    assert!(pages[1].piece.is_none());
+
+    let t_tile = Piece::T.block_type() as i16 + 1;
+    for (x, y) in t.cells() {
+        assert_eq!(tile(&pages[1].field, x, y), t_tile);
+    }
+    assert_eq!(filled(&pages[1].field), 4);
+    assert_eq!(fumen::encode(&pages).unwrap(), "v115@vhBVQJAAA");
+}
+
+#[test]
+fn full_rows_clear_between_pages() {
+    let mut first = fumen::decode("v115@vhAAgH").unwrap().remove(0);
+    for x in 0..9 {
+        first.field.set_cell(x + 1, first.field.size.y - 2, GARBAGE_TILE);
+    }
+    first.piece = Some(PieceLocation { piece: Piece::I, orientation: Orientation::East, x: 9, y: 2 });
+
+    // Second page without changes to the field and without a piece
+    let text = fumen::encode(&[first]).unwrap() + "vhAAAA";
+    let pages = fumen::decode(&text).unwrap();
+    assert_eq!(pages.len(), 2);
+    let i_tile = Piece::I.block_type() as i16 + 1;
+    assert_eq!((0..3).map(|y| tile(&pages[1].field, 9, y)).collect::<Vec<_>>(), vec![i_tile; 3]);
+    assert_eq!(filled(&pages[1].field), 3);
+}
+
+#[test]
+fn full_field_after_the_first_page_round_trips() {
+    let empty = fumen::decode("v115@vhAAgH").unwrap().remove(0);
+    let mut full = Page { lock: false, ..empty.clone() };
+    for x in 0..10 {
+        for y in 0..23 {
+            full.field.set_cell(x + 1, full.field.size.y - 2 - y, GARBAGE_TILE);
+        }
+    }
+    let pages = vec![empty.clone(), full.clone(), full.clone(), full, empty];
+
+    let text = fumen::encode(&pages).unwrap();
+    let decoded = fumen::decode(&text).unwrap();
+    assert_eq!(decoded.len(), pages.len());
+    let filled: Vec<usize> = decoded.iter().map(|page| filled(&page.field)).collect();
+    assert_eq!(filled, vec![0, 230, 230, 230, 0]);
+    assert_eq!(fumen::encode(&decoded).unwrap(), text);
+}
+
+#[test]
+fn fields_must_be_ten_wide() {
+    let page = Page::new(TileMap::new(Vector2::new(6, 41), 20));
+    assert!(fumen::encode(&[page]).is_err());
+}
+
+#[test]
+fn comments_round_trip() {
+    let mut pages = fumen::decode("v115@vhAAgH").unwrap();
+    pages[0].comment = "PCO, 100% (ä)".to_string();
+    pages.push(pages[0].clone());
+    pages[1].field = fumen::decode("v115@9gF8DeF8DeF8DeF8NeAgH").unwrap().remove(0).field;
+    pages.push(pages[1].clone());
+    pages[2].comment = "Done".to_string();
+
+    let text = fumen::encode(&pages).unwrap();
+    assert!(text.starts_with("v115@"));
+    let decoded = fumen::decode(&text).unwrap();
+    assert_eq!(decoded.len(), 3);
+    let comments: Vec<&str> = decoded.iter().map(|page| page.comment.as_str()).collect();
+    assert_eq!(comments, vec!["PCO, 100% (ä)", "PCO, 100% (ä)", "Done"]);
+    assert_eq!(decoded[2].field.cells(), pages[2].field.cells());
+    assert_eq!(fumen::encode(&decoded).unwrap(), text);
+}
+
+// Pieces whose fumen position isn't their rotation center, encoded the way the reference
+// implementation (knewjade's tetris-fumen) does
+#[test]
+fn off_center_pieces_decode_to_their_cells() {
+    let cases = [
+        ("v115@vhBbLJAAA", Piece::O, Orientation::West, 5, 0),
+        ("v115@vhBDLJAAA", Piece::O, Orientation::South, 5, 1),
+        ("v115@vhBBQJAAA", Piece::I, Orientation::South, 5, 0),
+        ("v115@vhBZEJAAA", Piece::I, Orientation::West, 0, 1),
+        ("v115@vhBPKJAAA", Piece::S, Orientation::East, 1, 1),
+        ("v115@vhB8MJAAA", Piece::Z, Orientation::West, 8, 1),
+        ("v115@vhBXLJAAA", Piece::S, Orientation::North, 4, 0),
+        ("v115@vhBULJAAA", Piece::Z, Orientation::North, 4, 0),
+    ];
+    for &(text, piece, orientation, x, y) in &cases {
+        let pages = fumen::decode(text).unwrap();
+        let location = PieceLocation { piece, orientation, x, y };
+        assert_eq!(pages[0].piece, Some(location), "{}", text);
+
+        // Placed on the bottom rows of the next page
+        let piece_tile = piece.block_type() as i16 + 1;
+        for (x, y) in location.cells() {
+            assert_eq!(tile(&pages[1].field, x, y), piece_tile, "{}", text);
+        }
+        assert_eq!(filled(&pages[1].field), 4, "{}", text);
+        assert_eq!(fumen::encode(&pages).unwrap(), text);
+    }
+}
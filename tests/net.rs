@@ -0,0 +1,168 @@
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use jetris::engine::{Engine, Rules};
+use jetris::net::client::Client;
+use jetris::net::protocol::{BoardState, ClientMessage, ServerMessage};
+use jetris::net::server::{Server, SPECTATOR_DELAY};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+// Starts a server on a free localhost port and returns its address
+fn start_server(reconnect_timeout: Duration, spectator_delay: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Server::new(reconnect_timeout, spectator_delay);
+    thread::spawn(move || server.run(listener));
+    address
+}
+
+fn connect(address: &str, name: &str) -> Client {
+    let mut client = Client::new(address, name);
+    client.connect().unwrap();
+    client
+}
+
+// Two players in a started match
+fn start_match(address: &str) -> (Client, Client) {
+    let mut a = connect(address, "a");
+    assert!(matches!(a.wait(WAIT), Some(ServerMessage::Welcome { player: 0, .. })));
+    let mut b = connect(address, "b");
+    assert!(matches!(b.wait(WAIT), Some(ServerMessage::Welcome { player: 1, .. })));
+
+    let names = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::MatchStart { names: names.clone() }));
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::MatchStart { names }));
+    (a, b)
+}
+
+#[test]
+fn boards_and_attacks_are_relayed() {
+    let address = start_server(Duration::from_secs(30), SPECTATOR_DELAY);
+    let (mut a, mut b) = start_match(&address);
+
+    let state = BoardState::from_engine(&Engine::new(Rules::default()));
+    a.send(&ClientMessage::Board { state: state.clone() }).unwrap();
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::Board { player: 0, state: state.clone() }));
+    assert_eq!(state.to_tile_map().unwrap().cells(), state.cells);
+
+    b.send(&ClientMessage::Attack { rows: 4 }).unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Attack { rows: 4 }));
+}
+
+#[test]
+fn top_out_ends_the_match() {
+    let address = start_server(Duration::from_secs(30), SPECTATOR_DELAY);
+    let (mut a, mut b) = start_match(&address);
+
+    a.send(&ClientMessage::TopOut).unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::MatchOver { winner: Some(1) }));
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::MatchOver { winner: Some(1) }));
+}
+
+#[test]
+fn other_protocol_version_is_rejected() {
+    use jetris::net::protocol::{read_message, write_message};
+    use std::io::BufReader;
+    use std::net::TcpStream;
+
+    let address = start_server(Duration::from_secs(30), SPECTATOR_DELAY);
+    let mut stream = TcpStream::connect(&address).unwrap();
+    let hello = ClientMessage::Hello { version: 0, name: "old".to_string(), session: None, spectator: false };
+    write_message(&mut stream, &hello).unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let message: Option<ServerMessage> = read_message(&mut reader).unwrap();
+    assert!(matches!(message, Some(ServerMessage::Rejected { .. })));
+}
+
+#[test]
+fn player_can_reconnect() {
+    let address = start_server(Duration::from_secs(30), SPECTATOR_DELAY);
+    let (mut a, mut b) = start_match(&address);
+    let session = a.session();
+
+    a.disconnect();
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::OpponentDisconnected));
+
+    a.connect().unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Welcome { player: 0, session: session.unwrap() }));
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Resume));
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::OpponentReconnected));
+
+    // The match goes on with the new connection
+    b.send(&ClientMessage::Attack { rows: 2 }).unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Attack { rows: 2 }));
+}
+
+#[test]
+fn player_reconnecting_while_the_opponent_is_away_waits() {
+    let address = start_server(Duration::from_secs(30), SPECTATOR_DELAY);
+    let (mut a, mut b) = start_match(&address);
+    let session = a.session().unwrap();
+
+    b.disconnect();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::OpponentDisconnected));
+    a.disconnect();
+
+    a.connect().unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Welcome { player: 0, session }));
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::OpponentDisconnected));
+
+    // Both go on once the opponent is back
+    b.connect().unwrap();
+    assert!(matches!(b.wait(WAIT), Some(ServerMessage::Welcome { player: 1, .. })));
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::Resume));
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::OpponentReconnected));
+}
+
+#[test]
+fn player_who_doesnt_reconnect_loses() {
+    let address = start_server(Duration::from_millis(200), SPECTATOR_DELAY);
+    let (mut a, mut b) = start_match(&address);
+
+    a.disconnect();
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::OpponentDisconnected));
+    assert_eq!(b.wait(WAIT), Some(ServerMessage::MatchOver { winner: Some(1) }));
+
+    // Too late, but the result is still told
+    a.connect().unwrap();
+    assert!(matches!(a.wait(WAIT), Some(ServerMessage::Welcome { player: 0, .. })));
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::MatchOver { winner: Some(1) }));
+}
+
+#[test]
+fn spectator_sees_boards_later() {
+    let delay = Duration::from_millis(300);
+    let address = start_server(Duration::from_secs(30), delay);
+    let (mut a, mut b) = start_match(&address);
+
+    let mut spectator = Client::spectator(&address, "c");
+    spectator.connect().unwrap();
+    let names = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(spectator.wait(WAIT), Some(ServerMessage::MatchStart { names }));
+
+    let sent = Instant::now();
+    let state = BoardState::from_engine(&Engine::new(Rules::default()));
+    b.send(&ClientMessage::Board { state: state.clone() }).unwrap();
+    assert_eq!(a.wait(WAIT), Some(ServerMessage::Board { player: 1, state: state.clone() }));
+    assert_eq!(spectator.wait(WAIT), Some(ServerMessage::Board { player: 1, state }));
+    assert!(sent.elapsed() >= delay);
+
+    a.send(&ClientMessage::TopOut).unwrap();
+    assert_eq!(spectator.wait(WAIT), Some(ServerMessage::MatchOver { winner: Some(1) }));
+}
+
+#[test]
+fn spectator_waits_for_match_to_start() {
+    let address = start_server(Duration::from_secs(30), Duration::from_millis(0));
+    let mut spectator = Client::spectator(&address, "c");
+    spectator.connect().unwrap();
+    // Give the server time to seat the spectator before the players come
+    thread::sleep(Duration::from_millis(100));
+
+    let _players = start_match(&address);
+    let names = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(spectator.wait(WAIT), Some(ServerMessage::MatchStart { names }));
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+use std::time::Duration;
+
+use jetris::engine::{Engine, Event, Input, TSpin};
+use jetris::finesse::same_cells;
+use jetris::opener::{Opener, Practice};
+use jetris::placement;
+
+// Inputs which put the current block on 'cells'
+fn inputs_to(engine: &Engine, cells: &[jetris::Vector2]) -> Option<Vec<Input>> {
+    placement::placements_from(&engine.field(), engine.pieces(), &engine.block).into_iter()
+        .find(|p| same_cells(&p.block.cells(), cells))
+        .map(|p| p.inputs)
+}
+
+// Places every block on its target like a player would, returns the clears
+fn play(practice: &mut Practice, engine: &mut Engine) -> Vec<Event> {
+    let mut events = Vec::new();
+    while let Some(target) = practice.target().map(|cells| cells.to_vec()) {
+        let inputs = inputs_to(engine, &target).unwrap_or_else(|| panic!("block {} can't be placed", practice.step + 1));
+        for input in inputs {
+            engine.apply(input);
+        }
+        for event in engine.drain_events() {
+            match &event {
+                Event::Locked(_, cells) => practice.lock(cells),
+                Event::LinesCleared(clear) => practice.clear(&clear.rows),
+                Event::HardDrop => {},
+            }
+            events.push(event);
+        }
+        if practice.is_over() {
+            break;
+        }
+        engine.update(Duration::from_secs(1));
+    }
+    events
+}
+
+fn clears(events: &[Event]) -> Vec<&jetris::engine::Clear> {
+    events.iter().filter_map(|event| match event {
+        Event::LinesCleared(clear) => Some(clear),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn tki_ends_with_a_t_spin_double() {
+    let mut practice = Practice::new(Opener::load(Path::new("openers/tki.json")).unwrap());
+    let mut engine = practice.start();
+    let events = play(&mut practice, &mut engine);
+    assert_eq!(practice.successes, 1);
+    let clears = clears(&events);
+    assert_eq!(clears.len(), 1);
+    assert_eq!(clears[0].t_spin, TSpin::Full);
+    assert_eq!(clears[0].lines(), 2);
+}
+
+#[test]
+fn pco_is_a_perfect_clear() {
+    let mut practice = Practice::new(Opener::load(Path::new("openers/pco.json")).unwrap());
+    let mut engine = practice.start();
+    let events = play(&mut practice, &mut engine);
+    assert_eq!((practice.successes, practice.attempts), (1, 1));
+    assert!(clears(&events).last().unwrap().perfect_clear);
+}
+
+#[test]
+fn a_missed_block_ends_the_attempt() {
+    let mut practice = Practice::new(Opener::load(Path::new("openers/tki.json")).unwrap());
+    let mut engine = practice.start();
+    // The first block dropped straight down misses its place in the corner
+    engine.apply(Input::HardDrop);
+    if let Some(Event::Locked(_, cells)) = engine.drain_events().iter().find(|e| matches!(e, Event::Locked(..))) {
+        practice.lock(cells);
+    }
+    assert!(practice.is_over());
+    assert_eq!(practice.last, Some(false));
+    assert_eq!((practice.successes, practice.attempts), (0, 1));
+    assert_eq!(practice.success_rate(), 0.0);
+
+    // The next attempt starts from the first block again
+    let mut engine = practice.start();
+    assert_eq!(practice.step, 0);
+    play(&mut practice, &mut engine);
+    assert_eq!((practice.successes, practice.attempts), (1, 2));
+    assert_eq!(practice.success_rate(), 0.5);
+}
+
+#[test]
+fn openers_need_four_cells_per_block() {
+    let opener = Opener {
+        name: "Broken".to_string(),
+        pieces: "IO".to_string(),
+        field: vec!["2.........".to_string(), "1111.22...".to_string()],
+    };
+    assert!(opener.validate().is_err());
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use jetris::engine::{Engine, Input, Rules};
+use jetris::pieces::{PieceSet, PieceShape};
+use jetris::tile_map::{block_tile, tile_block, GARBAGE_TILE, WALL_TILE};
+use jetris::Vector2;
+
+fn rules(pieces: PieceSet) -> Rules {
+    Rules { board_width: 12, pieces, ..Rules::default() }
+}
+
+#[test]
+fn bundled_sets_are_valid() {
+    assert!(PieceSet::standard().validate().is_ok());
+    let pentominoes = PieceSet::load(Path::new("pieces/pentominoes.json")).unwrap();
+    assert_eq!(pentominoes.len(), 18);
+    assert!(pentominoes.pieces.iter().all(|piece| piece.cell_count() == 5));
+    // The pentomino T doesn't do T-spins
+    assert_eq!(pentominoes.t_block(), None);
+    assert_eq!(PieceSet::standard().t_block(), Some(2));
+    assert!(PieceSet::load(Path::new("pieces/triminoes.json")).is_ok());
+}
+
+#[test]
+fn standard_set_deals_all_seven_tetrominoes() {
+    assert_eq!(PieceSet::standard().random_types(), vec![0, 1, 2, 3, 4, 5, 6]);
+    let mut engine = Engine::with_seed(Rules::default(), 2);
+    let mut dealt = [false; 7];
+    for _ in 0..100 {
+        dealt[engine.block.block_type as usize] = true;
+        engine.apply(Input::HardDrop);
+        engine.update(std::time::Duration::from_secs(1));
+        engine.clear_field();
+    }
+    assert_eq!(dealt, [true; 7]);
+}
+
+#[test]
+fn randomizer_deals_from_the_active_set() {
+    let pieces = PieceSet::load(Path::new("pieces/triminoes.json")).unwrap();
+    let mut engine = Engine::with_seed(rules(pieces), 1);
+    for _ in 0..30 {
+        assert!(engine.block.block_type < 3);
+        assert_eq!(engine.block.cells().len(), 3);
+        assert!(engine.preview().iter().all(|&block_type| block_type < 3));
+        engine.apply(Input::HardDrop);
+        engine.update(std::time::Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn pentominoes_lock_with_their_own_tiles() {
+    let pieces = PieceSet::load(Path::new("pieces/pentominoes.json")).unwrap();
+    let mut engine = Engine::with_seed(rules(pieces), 3);
+    let mut seen = Vec::new();
+    for _ in 0..20 {
+        let block_type = engine.block.block_type;
+        let cells = engine.block.cells();
+        engine.apply(Input::HardDrop);
+        let dropped = engine.tile_map.cells().into_iter()
+            .filter(|&tile| tile == block_tile(block_type))
+            .count();
+        assert!(dropped >= cells.len());
+        seen.push(block_type);
+        engine.update(std::time::Duration::from_secs(1));
+        if engine.top_out().is_some() {
+            break;
+        }
+    }
+    // Types past the standard seven skip the wall and garbage tiles
+    assert!(seen.iter().any(|&block_type| block_type >= 7));
+}
+
+#[test]
+fn spawn_rotation_and_center_place_the_cells() {
+    let piece = PieceShape {
+        name: "Bar".to_string(),
+        shape: vec!["###".to_string()],
+        center: Some([0, 0]),
+        color: [1.0, 1.0, 1.0],
+        spawn_rotation: 1,
+        random: true,
+    };
+    let set = PieceSet { name: "bars".to_string(), pieces: vec![piece.clone()] };
+    assert!(set.validate().is_ok());
+    // Rotating around the left end turns the bar to point down from the middle
+    let cells = set.block(Vector2::new(0, 0), 0).cells();
+    assert_eq!(cells, vec![Vector2::new(2, 2), Vector2::new(2, 3), Vector2::new(2, 4)]);
+
+    let too_far = PieceSet { pieces: vec![PieceShape { center: Some([5, 0]), ..piece }], ..set };
+    assert!(too_far.validate().is_err());
+}
+
+#[test]
+fn block_tiles_skip_walls_and_garbage() {
+    for block_type in 0..20 {
+        let tile = block_tile(block_type);
+        assert!(tile != WALL_TILE && tile != GARBAGE_TILE && tile > 0);
+        assert_eq!(tile_block(tile), Some(block_type));
+    }
+    assert_eq!(tile_block(0), None);
+    assert_eq!(tile_block(WALL_TILE), None);
+}
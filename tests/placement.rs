@@ -0,0 +1,78 @@
+use jetris::engine::{Engine, Event, Input, Rules};
+use jetris::pieces::PieceSet;
+use jetris::placement;
+use jetris::tile_map::GARBAGE_TILE;
+
+fn sorted(cells: &[jetris::Vector2]) -> Vec<(i16, i16)> {
+    let mut cells: Vec<(i16, i16)> = cells.iter().map(|c| (c.x, c.y)).collect();
+    cells.sort_unstable();
+    cells
+}
+
+#[test]
+fn every_position_of_an_i_block_on_an_empty_board() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.play_sequence(&[1]);
+    assert_eq!(engine.block.block_type, 1);
+    // 7 flat positions and 10 upright ones on a board 10 wide
+    assert_eq!(placement::placements(&engine.field(), engine.pieces(), 1).len(), 17);
+}
+
+#[test]
+fn dropping_where_the_block_spawns_takes_one_input() {
+    let engine = Engine::with_seed(Rules::default(), 0);
+    let placements = placement::placements_from(&engine.field(), engine.pieces(), &engine.block);
+    let lowest = placement::dropped(&engine.field(), &engine.block);
+    let straight = placements.iter()
+        .find(|p| sorted(&p.block.cells()) == sorted(&lowest.cells()))
+        .unwrap();
+    assert_eq!(straight.inputs, vec![Input::HardDrop]);
+    assert!(placements.iter().all(|p| p.inputs.last() == Some(&Input::HardDrop)));
+}
+
+#[test]
+fn inputs_lead_the_engine_to_the_placement() {
+    let engine = Engine::with_seed(Rules::default(), 0);
+    for placement in placement::placements_from(&engine.field(), engine.pieces(), &engine.block) {
+        let mut engine = Engine::with_seed(Rules::default(), 0);
+        for &input in &placement.inputs {
+            engine.apply(input);
+        }
+        let locked = engine.drain_events().into_iter()
+            .find_map(|event| match event {
+                Event::Locked(_, cells) => Some(cells),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(sorted(&locked), sorted(&placement.block.cells()), "{:?}", placement.inputs);
+    }
+}
+
+// Placements which land on the same cells
+fn same_cell_pairs(placements: &[placement::Placement]) -> usize {
+    let cells: Vec<Vec<(i16, i16)>> = placements.iter().map(|p| sorted(&p.block.cells())).collect();
+    (0..cells.len()).flat_map(|i| (i+1..cells.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| cells[i] == cells[j])
+        .count()
+}
+
+#[test]
+fn only_the_t_of_the_set_keeps_spins_apart() {
+    // The O is block type 2 here, the type of the T in the standard set
+    let mut pieces = PieceSet::standard();
+    pieces.pieces.swap(0, 2);
+    let engine = Engine::with_seed(Rules { pieces, ..Rules::default() }, 0);
+    assert_eq!(engine.pieces().t_block(), Some(0));
+
+    // Stack where the O also reaches a place by turning in it
+    let rows = ["X..X.XX..X", "XX..XXX...", "XXX.X.....", "X.X.XXXXXX"];
+    let mut field = engine.field();
+    let top = field.size.y - 1 - rows.len() as i16;
+    for (y, row) in rows.iter().enumerate() {
+        for (x, _) in row.chars().enumerate().filter(|&(_, c)| c == 'X') {
+            field.set_cell(x as i16 + 1, top + y as i16, GARBAGE_TILE);
+        }
+    }
+    let placements = placement::placements(&field, engine.pieces(), 2);
+    assert_eq!(same_cell_pairs(&placements), 0);
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::time::Duration;
+
+use jetris::engine::{Event, Input, TopOut};
+use jetris::placement;
+use jetris::puzzle::{self, Goal, Puzzle};
+
+fn puzzle(field: &[&str], pieces: &str, goal: Goal) -> Puzzle {
+    Puzzle {
+        name: "Test".to_string(),
+        field: field.iter().map(|row| row.to_string()).collect(),
+        pieces: pieces.to_string(),
+        hold: true,
+        goal,
+    }
+}
+
+// Plays the puzzle with the inputs of each block, returns whether the goal was reached
+// and the inputs for every place the next block can go
+fn play(puzzle: &Puzzle, moves: &[Vec<Input>]) -> (bool, Vec<Vec<Input>>) {
+    let mut engine = puzzle.engine();
+    let mut lines = 0;
+    for inputs in moves {
+        for &input in inputs {
+            engine.apply(input);
+        }
+        for event in engine.drain_events() {
+            if let Event::LinesCleared(clear) = event {
+                lines += clear.lines() as u32;
+                if puzzle.goal.is_reached(&clear, lines) {
+                    return (true, Vec::new());
+                }
+            }
+        }
+        engine.update(Duration::from_secs(1));
+    }
+    if engine.top_out().is_some() {
+        return (false, Vec::new());
+    }
+    let next = placement::placements_from(&engine.field(), engine.pieces(), &engine.block);
+    (false, next.into_iter().map(|p| p.inputs).collect())
+}
+
+// Tries every sequence of placements without hold
+fn solvable(puzzle: &Puzzle, moves: &mut Vec<Vec<Input>>) -> bool {
+    let (solved, next) = play(puzzle, moves);
+    solved || next.into_iter().any(|inputs| {
+        moves.push(inputs);
+        let solved = solvable(puzzle, moves);
+        moves.pop();
+        solved
+    })
+}
+
+#[test]
+fn bundled_puzzles_are_valid_and_solvable() {
+    let loaded = puzzle::load(Path::new("puzzles")).unwrap();
+    assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+    assert!(!loaded.puzzles.is_empty());
+    for (id, puzzle) in &loaded.puzzles {
+        assert!(solvable(puzzle, &mut Vec::new()), "{} can't be solved", id);
+    }
+}
+
+#[test]
+fn invalid_puzzles_are_rejected() {
+    let lines = Goal::Lines { lines: 1 };
+    assert!(puzzle(&["XXXX.X"], "I", lines).validate().is_ok());
+    assert!(puzzle(&["XXXX.X", "XX.X"], "I", lines).validate().is_err());
+    assert!(puzzle(&["XXXXXX"], "I", lines).validate().is_err());
+    assert!(puzzle(&["XXXX.?"], "I", lines).validate().is_err());
+    assert!(puzzle(&["XXXX.X"], "IQ", lines).validate().is_err());
+    assert!(puzzle(&["XXXX.X"], "", lines).validate().is_err());
+    assert!(puzzle(&["XXXX.X"], "T", Goal::TSpin { lines: 4 }).validate().is_err());
+}
+
+#[test]
+fn game_ends_when_the_sequence_runs_out() {
+    let puzzle = puzzle(&["..........", ".........."], "IT", Goal::PerfectClear);
+    let mut engine = puzzle.engine();
+    assert_eq!(engine.block.block_type, 1);
+    assert_eq!(engine.preview().len(), 1);
+
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.block.block_type, 2);
+    assert!(engine.preview().is_empty());
+    // Nothing to swap with
+    assert!(!engine.can_hold());
+
+    engine.apply(Input::HardDrop);
+    assert!(matches!(engine.top_out(), Some(TopOut::OutOfBlocks)));
+}
+
+#[test]
+fn hold_can_be_turned_off() {
+    let mut puzzle = puzzle(&[".........."], "ITT", Goal::Lines { lines: 1 });
+    assert!(puzzle.engine().can_hold());
+    puzzle.hold = false;
+    assert!(!puzzle.engine().can_hold());
+}
@@ -0,0 +1,31 @@
+use jetris::screen::{Screen, DEFAULT_SCREEN_SIZE, MIN_SCREEN_SIZE};
+use jetris::Vector2;
+
+#[test]
+fn texts_wider_than_the_smallest_window_start_at_its_edge() {
+    let (width, height) = MIN_SCREEN_SIZE;
+    let screen = Screen::new(width, height);
+    assert_eq!(screen.center, Vector2::new(320, 180));
+    assert!((screen.scale() - 640.0 / 1800.0).abs() < 1e-6);
+
+    // Narrower texts are centered, wider ones start at the left or top edge
+    assert_eq!(screen.centered_x(100.0), 270.0);
+    assert_eq!(screen.centered_x(width + 200.0), 0.0);
+    assert_eq!(screen.centered_y(40.0), 160.0);
+    assert_eq!(screen.centered_y(height * 2.0), 0.0);
+    assert_eq!(screen.right_aligned_x(140.0), 500.0);
+    assert_eq!(screen.right_aligned_x(width + 1.0), 0.0);
+}
+
+#[test]
+fn boards_share_the_width_of_the_window() {
+    let (width, height) = MIN_SCREEN_SIZE;
+    let screen = Screen::new(width, height);
+    let areas = screen.board_areas(2);
+    assert_eq!(areas, vec![
+        (Vector2::new(160, 180), Vector2::new(320, 360)),
+        (Vector2::new(480, 180), Vector2::new(320, 360)),
+    ]);
+    assert_eq!(Screen::default().scale(), 1.0);
+    assert_eq!(Screen::default(), Screen::new(DEFAULT_SCREEN_SIZE.0, DEFAULT_SCREEN_SIZE.1));
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+use std::time::Duration;
+
+use jetris::bot::{Bot, Controller, Weights};
+use jetris::engine::{Engine, Rules};
+use jetris::pieces::PieceSet;
+use jetris::sim::{self, GameStats, Summary};
+use jetris::tbp::ExternalBot;
+
+// Block types an engine with 'seed' deals first, the current block then the preview
+fn deal(seed: u64) -> Vec<u8> {
+    let engine = Engine::with_seed(Rules::default(), seed);
+    std::iter::once(engine.block.block_type).chain(engine.preview().iter().copied()).collect()
+}
+
+#[test]
+fn same_seed_plays_the_same_game() {
+    let play = |seed| {
+        let mut bot = Bot::new(Weights::default(), 2.0);
+        sim::run_game(Rules::default(), &mut bot, seed, 6, false)
+    };
+    let first = play(7);
+    assert_eq!(first.pieces, 6);
+    assert_eq!(play(7), first);
+    assert_eq!(deal(7), deal(7));
+    assert_ne!(deal(8), deal(7));
+}
+
+#[test]
+fn games_before_dont_change_a_seed() {
+    let mock_bot = || ExternalBot::launch(env!("CARGO_BIN_EXE_jetris-mock-bot"), &[], 1000.0).unwrap();
+    // Blocks wait for the bot instead of falling while it thinks
+    let rules = Rules { gravity_interval: Duration::from_secs(60), ..Rules::default() };
+    let play = |bot: &mut ExternalBot, seed| {
+        GameStats { time: 0.0, ..sim::run_game(rules.clone(), bot, seed, 6, false) }
+    };
+    let alone = play(&mut mock_bot(), 8);
+
+    // The bot is still thinking about a block of another game when this one starts
+    let mut reused = mock_bot();
+    let mut engine = Engine::with_seed(rules.clone(), 7);
+    reused.update(&mut engine, Duration::from_millis(1));
+    assert_eq!(play(&mut reused, 8), alone);
+}
+
+#[test]
+fn pieces_are_counted_by_the_names_of_the_set() {
+    let pieces = PieceSet::load(Path::new("pieces/triminoes.json")).unwrap();
+    let game = GameStats {
+        seed: 0, pieces: 4, lines: 0, score: 0, attack: 0, time: 1.0, topped_out: false,
+        piece_counts: vec![1, 3, 0],
+    };
+    let summary = Summary::new(&[game], &pieces);
+    let expected = vec![("I".to_string(), 0.25), ("L".to_string(), 0.75), ("J".to_string(), 0.0)];
+    assert_eq!(summary.piece_distribution, expected);
+}
@@ -0,0 +1,72 @@
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+use jetris::engine::{Engine, Rules};
+use jetris::fumen::Page;
+use jetris::pieces::PieceSet;
+use jetris::snapshot::{self, Snapshot, SnapshotRenderer};
+use jetris::tbp::{Orientation, Piece, PieceLocation};
+use jetris::theme::{self, Theme};
+use jetris::tile_map::{block_tile, GARBAGE_TILE, WALL_TILE};
+
+fn renderer() -> SnapshotRenderer {
+    let theme = Theme::default();
+    let pieces = PieceSet::standard();
+    SnapshotRenderer::new(theme.tile_set(&pieces), pieces, theme::rgb(theme.background))
+}
+
+#[test]
+fn cells_are_drawn_in_tile_colors() {
+    let renderer = renderer();
+    let mut engine = Engine::new(Rules::default());
+    engine.clear_field();
+    // Bottom left cell of the field, right of the wall
+    let bottom = engine.tile_map.size.y - 2;
+    engine.tile_map.set_cell(1, bottom, GARBAGE_TILE);
+    let image = renderer.render(&Snapshot::from_engine(&engine));
+
+    let cell = renderer.cell_size;
+    let small = (0.6 * cell as f32) as u32;
+    let (board_x, top) = (cell + 5 * small, cell / 2);
+    assert_eq!(image.width(), board_x + 13 * cell + 5 * small + cell / 2);
+    let pixel = |x: u32, y: u32| {
+        let p = image.get_pixel(board_x + x * cell + cell / 2, top + y * cell + cell / 2);
+        (p[0], p[1], p[2])
+    };
+    let color = |tile: i16| renderer.tile_set.tiles[tile as usize].color.to_rgb();
+    assert_eq!(pixel(1, 19), color(GARBAGE_TILE));
+    assert_eq!(pixel(2, 19), color(0));
+    assert_eq!(pixel(0, 0), color(WALL_TILE));
+}
+
+#[test]
+fn fumen_pages_show_their_piece() {
+    let mut page = Page::new(Engine::new(Rules::default()).field());
+    page.piece = Some(PieceLocation { piece: Piece::I, orientation: Orientation::North, x: 4, y: 0 });
+    let field = page.placed_field();
+    let bottom = field.size.y - 2;
+    let tiles: Vec<i16> = (1..=10).map(|x| field._get_cell(x, bottom)).collect();
+    let i = block_tile(Piece::I.block_type());
+    assert_eq!(tiles, [0, 0, 0, i, i, i, i, 0, 0, 0]);
+}
+
+#[test]
+fn frames_are_saved_as_png_and_gif() {
+    let renderer = renderer();
+    let frames: Vec<_> = (0..3)
+        .map(|_| renderer.render(&Snapshot::from_engine(&Engine::new(Rules::default()))))
+        .collect();
+    let dir = env::temp_dir().join(format!("jetris-snapshot-{}", std::process::id()));
+
+    let files = snapshot::save_frames(&frames, &dir).unwrap();
+    assert_eq!(files.len(), 3);
+    let png = image::open(&files[0]).unwrap().to_rgba();
+    assert_eq!(png.dimensions(), frames[0].dimensions());
+
+    let gif = dir.join("game.gif");
+    snapshot::save_gif(&frames, Duration::from_millis(100), &gif).unwrap();
+    assert!(fs::read(&gif).unwrap().starts_with(b"GIF89a"));
+    assert!(snapshot::save_gif(&[], Duration::from_millis(100), &gif).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
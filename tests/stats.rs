@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use jetris::engine::{Engine, Event, Input, Rules};
+use jetris::finesse::Finesse;
+use jetris::stats::{Stat, Stats};
+use jetris::tile_map::GARBAGE_TILE;
+
+const O_BLOCK: u8 = 0;
+const I_BLOCK: u8 = 1;
+
+// Plays an O to the left wall with two extra moves, then an I into the gap of the
+// four full bottom rows, counting the way the game does
+fn play() -> (Stats, Finesse) {
+    let rules = Rules { line_clear_delay: Duration::from_millis(0), ..Rules::default() };
+    let mut engine = Engine::with_seed(rules, 0);
+    engine.play_sequence(&[O_BLOCK, I_BLOCK, O_BLOCK]);
+    let mut field = engine.field();
+    let bottom = field.size.y - 2;
+    for y in bottom-3..=bottom {
+        for x in (1..field.size.x-1).filter(|&x| x != 6) {
+            field.set_cell(x, y, GARBAGE_TILE);
+        }
+    }
+    engine.set_field(field);
+
+    let mut stats = Stats::default();
+    let mut finesse = Finesse::default();
+    let mut inputs = vec![Input::Left; 5];
+    inputs.extend([Input::Right, Input::Left, Input::HardDrop, Input::HardDrop]);
+    finesse.watch(&engine);
+    for input in inputs {
+        stats.keys += 1;
+        finesse.input(&engine, input);
+        engine.apply(input);
+        for event in engine.drain_events() {
+            stats.record(&event);
+            if let Event::Locked(block_type, cells) = &event {
+                finesse.lock(engine.pieces(), *block_type, cells);
+            }
+        }
+        finesse.watch(&engine);
+    }
+    stats.time = Duration::from_secs(4);
+    (stats, finesse)
+}
+
+#[test]
+fn stats_count_pieces_clears_and_attack() {
+    let (stats, finesse) = play();
+    assert_eq!(stats.pieces, 2);
+    assert_eq!(stats.lines, 4);
+    assert_eq!(stats.clears, [0, 0, 0, 1]);
+    assert_eq!(stats.attack, 4);
+    assert_eq!(stats.max_combo, 1);
+    assert_eq!(stats.perfect_clears, 0);
+    assert_eq!(stats.keys, 9);
+
+    assert_eq!(finesse.total_pieces(), 2);
+    assert_eq!(finesse.total_faults(), 2);
+}
+
+#[test]
+fn rates_are_per_time_and_piece() {
+    let (stats, finesse) = play();
+    assert_eq!(stats.pps(), 0.5);
+    assert_eq!(stats.apm(), 60.0);
+    assert_eq!(stats.kpp(), 4.5);
+    assert_eq!(Stats::default().pps(), 0.0);
+    assert_eq!(Stats::default().kpp(), 0.0);
+
+    assert_eq!(Stat::Pps.text(&stats, &finesse), "PPS 0.50");
+    assert_eq!(Stat::Kpp.text(&stats, &finesse), "KPP 4.50");
+    assert_eq!(Stat::Clears.text(&stats, &finesse), "1/2/3/4 0/0/0/1 PC 0");
+    assert_eq!(Stat::Finesse.text(&stats, &finesse), "Finesse faults 2");
+    assert_eq!(Stat::from_name("max-combo"), Some(Stat::MaxCombo));
+}
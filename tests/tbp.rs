@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use jetris::block::T_BLOCK;
+use jetris::bot::Controller;
+use jetris::engine::{Engine, Input, Rules};
+use jetris::tbp::{self, BotMessage, ExternalBot, FrontendMessage, Move, Orientation, Piece, PieceLocation, Spin};
+
+fn mock_bot() -> ExternalBot {
+    ExternalBot::launch(env!("CARGO_BIN_EXE_jetris-mock-bot"), &[], 1000.0).unwrap()
+}
+
+fn rules() -> Rules {
+    Rules {
+        line_clear_delay: Duration::from_millis(0),
+        gravity_interval: Duration::from_secs(60),
+        ..Rules::default()
+    }
+}
+
+// Runs the bot until 'blocks' more blocks have been dealt or the game ends
+fn play(bot: &mut ExternalBot, engine: &mut Engine, blocks: u32) {
+    let target = engine.blocks_dealt() + blocks;
+    let started = Instant::now();
+    while engine.blocks_dealt() < target && engine.top_out().is_none() {
+        assert!(started.elapsed() < Duration::from_secs(10), "bot is too slow");
+        bot.update(engine, Duration::from_millis(1));
+        engine.update(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn messages_follow_the_spec() {
+    let mv = Move {
+        location: PieceLocation { piece: Piece::T, orientation: Orientation::South, x: 4, y: 1 },
+        spin: Spin::Full,
+    };
+    let json = serde_json::to_value(&FrontendMessage::Play { mv }).unwrap();
+    assert_eq!(json, serde_json::json!({
+        "type": "play",
+        "move": { "location": { "type": "T", "orientation": "south", "x": 4, "y": 1 }, "spin": "full" },
+    }));
+    assert_eq!(serde_json::to_value(&FrontendMessage::NewPiece { piece: Piece::I }).unwrap(),
+               serde_json::json!({ "type": "new_piece", "piece": "I" }));
+
+    let suggestion = r#"{"type":"suggestion","moves":[{"location":{"type":"I","orientation":"east","x":9,"y":1},"spin":"none"}],"move_info":{"nodes":10}}"#;
+    let message: BotMessage = serde_json::from_str(suggestion).unwrap();
+    let location = PieceLocation { piece: Piece::I, orientation: Orientation::East, x: 9, y: 1 };
+    assert_eq!(message, BotMessage::Suggestion { moves: vec![Move { location, spin: Spin::None }] });
+    assert_eq!(location.cells(), vec![(9, 2), (9, 1), (9, 0), (9, -1)]);
+}
+
+#[test]
+fn start_describes_the_engine() {
+    let engine = Engine::new(rules());
+    match tbp::start(&engine) {
+        FrontendMessage::Start { hold, queue, board, combo, back_to_back } => {
+            assert_eq!(hold, None);
+            assert_eq!(queue.len(), 1 + engine.preview().len());
+            assert_eq!(queue[0], Piece::from_block_type(engine.block.block_type));
+            assert_eq!(board.len(), 40);
+            assert!(board.iter().all(|row| row.len() == 10 && row.iter().all(|cell| cell.is_none())));
+            assert_eq!((combo, back_to_back), (0, false));
+        },
+        message => panic!("unexpected {:?}", message),
+    }
+}
+
+#[test]
+fn move_for_the_next_block_holds_first() {
+    let mut engine = Engine::new(rules());
+    // Next block differs from the current one, or it could be placed without holding
+    engine.set_queue(&[T_BLOCK, T_BLOCK], None);
+    let next = Piece::from_block_type(engine.preview()[0]);
+    // Flat on the floor at the left wall, in whichever orientation fits
+    let placement = [Orientation::North, Orientation::East, Orientation::South, Orientation::West].iter()
+        .flat_map(|&orientation| (0..10).map(move |x| (orientation, x)))
+        .find_map(|(orientation, x)| {
+            let location = PieceLocation { piece: next, orientation, x, y: 1 };
+            tbp::find_placement(&engine, &Move { location, spin: Spin::None })
+        })
+        .unwrap();
+    assert_eq!(placement.inputs.first(), Some(&Input::Hold));
+    assert_eq!(placement.inputs.last(), Some(&Input::HardDrop));
+}
+
+#[test]
+fn mock_bot_plays_a_board() {
+    let mut bot = mock_bot();
+    assert_eq!(bot.name, "jetris-mock-bot");
+
+    let mut engine = Engine::new(rules());
+    play(&mut bot, &mut engine, 20);
+    assert_eq!(bot.error, None);
+    assert!(engine.blocks_dealt() >= 20 || engine.top_out().is_some());
+    assert!(engine.field().cells() != Engine::new(rules()).field().cells());
+}
+
+#[test]
+fn mock_bot_starts_over_after_garbage() {
+    let mut bot = mock_bot();
+    let mut engine = Engine::new(rules());
+    play(&mut bot, &mut engine, 3);
+
+    engine.receive_garbage(2);
+    play(&mut bot, &mut engine, 5);
+    assert_eq!(bot.error, None);
+
+    // A new game is a new position too
+    let mut engine = Engine::new(rules());
+    play(&mut bot, &mut engine, 5);
+    assert_eq!(bot.error, None);
+}
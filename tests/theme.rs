@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use jetris::pieces::PieceSet;
+use jetris::theme::{self, Palette, Theme, THEMES_DIR};
+use jetris::tile_map::{block_tile, GARBAGE_TILE};
+
+#[test]
+fn bundled_themes_load_with_their_files() {
+    let themes = theme::list(Path::new(THEMES_DIR));
+    assert!(!themes.is_empty());
+    for dir in themes {
+        let theme = Theme::load(&dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e));
+        let files = [&theme.background_image, &theme.tile_image, &theme.atlas, &theme.font];
+        for file in files.iter().filter_map(|file| file.as_ref()) {
+            assert!(dir.join(file).is_file(), "{} is missing {}", theme.name, file);
+        }
+        if let Some(atlas) = &theme.atlas {
+            let image = image::open(dir.join(atlas)).unwrap().to_rgba();
+            let (width, height) = image.dimensions();
+            assert_eq!(width % height, 0, "{} has tiles which are not square", atlas);
+        }
+    }
+}
+
+#[test]
+fn themes_color_pieces_they_know() {
+    let theme = Theme {
+        garbage: [0.5, 0.5, 0.5],
+        pieces: vec![[1.0, 0.0, 0.0]],
+        ..Theme::default()
+    };
+    let pieces = PieceSet::standard();
+    let tile_set = theme.tile_set(&pieces);
+    assert_eq!(tile_set.tiles[block_tile(0) as usize].color.r, 1.0);
+    // Pieces past the theme's colors keep the color of their set
+    assert_eq!(tile_set.tiles[block_tile(1) as usize].color.g, pieces.pieces[1].color[1]);
+    assert_eq!(tile_set.tiles[GARBAGE_TILE as usize].color.r, 0.5);
+}
+
+// Relative luminance, how light a color looks whatever its hue
+fn luminance(color: [f32; 3]) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+#[test]
+fn palettes_keep_pieces_apart() {
+    for &palette in Palette::ALL.iter().filter(|&&palette| palette != Palette::Theme) {
+        assert_eq!(Palette::from_name(palette.name()), Some(palette));
+        let mut theme = Theme::default();
+        palette.apply(&mut theme);
+        let colors = &theme.pieces;
+        assert_eq!(colors.len(), 7);
+        for (i, a) in colors.iter().enumerate() {
+            for b in &colors[i + 1..] {
+                assert_ne!(a, b, "{} has a color twice", palette.name());
+            }
+        }
+        // S and Z, which are easiest to mix up, differ in lightness too
+        let (s, z) = (colors[3], colors[4]);
+        assert!((luminance(s) - luminance(z)).abs() >= 0.15, "{} S and Z look alike", palette.name());
+    }
+}
+
+#[test]
+fn palettes_leave_the_theme_alone_when_off() {
+    let mut theme = Theme::default();
+    Palette::Theme.apply(&mut theme);
+    assert!(theme.pieces.is_empty());
+    assert_eq!(Palette::HighContrast.next(), Palette::Theme);
+}
@@ -0,0 +1,46 @@
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::Terminal;
+
+use jetris::engine::{Input, Rules};
+use jetris::pieces::PieceSet;
+use jetris::theme::Theme;
+use jetris::tile_map::WALL_TILE;
+use jetris::tui::{self, TerminalGame};
+
+fn new_game() -> TerminalGame {
+    let tile_set = Theme::default().tile_set(&PieceSet::standard());
+    TerminalGame::new(Rules::default(), tile_set)
+}
+
+#[test]
+fn board_and_queue_are_drawn_in_tile_colors() {
+    let game = new_game();
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal.draw(|frame| game.draw(frame)).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+    assert!(text.contains("Next"));
+    assert!(text.contains("Hold"));
+    // The bottom row of the 12 cell wide board is wall from side to side
+    let wall = tui::terminal_color(game.tile_set.tiles[WALL_TILE as usize].color);
+    let walls: Vec<u16> = (0..100).filter(|&x| buffer[(x, 23)].bg == wall).collect();
+    assert_eq!(walls.len(), 24);
+}
+
+#[test]
+fn keys_play_until_paused() {
+    let mut game = new_game();
+    assert_eq!(tui::key_input(KeyCode::Char(' ')), Some(Input::HardDrop));
+    assert_eq!(tui::key_input(KeyCode::Char('x')), None);
+
+    game.input(Input::HardDrop);
+    assert_eq!(game.stats.pieces, 1);
+    game.toggle_pause();
+    game.input(Input::HardDrop);
+    assert_eq!(game.stats.pieces, 1);
+    game.toggle_pause();
+    game.input(Input::Hold);
+    assert!(game.engine.held().is_some());
+}
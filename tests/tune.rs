@@ -0,0 +1,55 @@
+use std::env;
+use std::fs;
+
+use jetris::tune::{Checkpoint, Config, ELITES, MIN_POPULATION};
+
+// Small run on a narrow board, where a few pieces already clear lines so the
+// individuals reach different fitness
+fn config() -> Config {
+    Config { population: 4, games: 1, pieces: 10, seed: 5, board_width: 5, board_height: 8, ..Config::default() }
+}
+
+#[test]
+fn checkpoint_survives_saving_and_loading() {
+    let mut checkpoint = Checkpoint::new(config());
+    checkpoint.step(1);
+
+    let path = env::temp_dir().join(format!("jetris-tune-{}.json", std::process::id()));
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&checkpoint).unwrap());
+    assert_eq!(loaded.generation, 1);
+    assert_eq!(loaded.history.len(), 1);
+}
+
+#[test]
+fn step_keeps_the_best_weights() {
+    let mut checkpoint = Checkpoint::new(config());
+    let individuals = checkpoint.step(2);
+
+    assert_eq!(individuals.len(), 4);
+    assert!(individuals.windows(2).all(|pair| pair[0].fitness >= pair[1].fitness));
+    assert!(individuals[ELITES - 1].fitness > individuals[ELITES].fitness);
+    assert_eq!(checkpoint.population.len(), 4);
+    for (elite, next) in individuals.iter().zip(&checkpoint.population).take(ELITES) {
+        assert_eq!(&elite.weights, next);
+    }
+    assert_eq!(checkpoint.best().map(|best| best.fitness), Some(individuals[0].fitness));
+}
+
+#[test]
+fn population_too_small_to_breed_is_an_error() {
+    assert!(Config { population: 0, ..config() }.validate().is_err());
+    assert!(Config { population: MIN_POPULATION, ..config() }.validate().is_ok());
+
+    let mut checkpoint = Checkpoint::new(config());
+    checkpoint.population.clear();
+    let path = env::temp_dir().join(format!("jetris-tune-empty-{}.json", std::process::id()));
+    checkpoint.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(loaded.is_err());
+}
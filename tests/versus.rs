@@ -0,0 +1,76 @@
+use jetris::engine::{Clear, Engine, Input, Rules, TSpin};
+use jetris::tile_map::GARBAGE_TILE;
+use jetris::versus;
+
+fn clear(lines: i16, t_spin: TSpin, combo: u32, back_to_back: bool) -> Clear {
+    Clear { rows: (0..lines).collect(), t_spin, combo, back_to_back, perfect_clear: false }
+}
+
+#[test]
+fn attack_follows_the_table() {
+    // (lines, t-spin, combo, back-to-back, attack)
+    let table = [
+        (1, TSpin::None, 0, false, 0),
+        (2, TSpin::None, 0, false, 1),
+        (3, TSpin::None, 0, false, 2),
+        (4, TSpin::None, 0, false, 4),
+        (4, TSpin::None, 0, true, 5),
+        (1, TSpin::Full, 0, false, 2),
+        (2, TSpin::Full, 0, false, 4),
+        (3, TSpin::Full, 0, false, 6),
+        (2, TSpin::Full, 0, true, 5),
+        (1, TSpin::Mini, 0, false, 0),
+        (2, TSpin::Mini, 0, false, 1),
+        (2, TSpin::Mini, 0, true, 2),
+    ];
+    for &(lines, t_spin, combo, back_to_back, attack) in &table {
+        let clear = clear(lines, t_spin, combo, back_to_back);
+        assert_eq!(versus::attack(&clear), attack, "{:?}", clear);
+    }
+}
+
+#[test]
+fn combo_bonus_starts_with_the_second_clear_in_a_row() {
+    // (combo, attack of a single)
+    let table = [(0, 0), (1, 0), (2, 1), (4, 1), (5, 2), (7, 3), (9, 4), (12, 5), (20, 5)];
+    for &(combo, attack) in &table {
+        let clear = clear(1, TSpin::None, combo, false);
+        assert_eq!(versus::attack(&clear), attack, "combo {}", combo);
+    }
+    // Bonuses add up
+    assert_eq!(versus::attack(&clear(4, TSpin::None, 5, true)), 4 + 1 + 2);
+}
+
+#[test]
+fn perfect_clear_adds_ten() {
+    let clear = Clear { perfect_clear: true, ..clear(4, TSpin::None, 0, false) };
+    assert_eq!(versus::attack(&clear), 14);
+}
+
+// Rows of garbage at the bottom of the field
+fn garbage_rows(engine: &Engine) -> usize {
+    let field = engine.field();
+    (0..field.size.y-1)
+        .filter(|&y| (1..field.size.x-1).any(|x| field._get_cell(x, y) == GARBAGE_TILE))
+        .count()
+}
+
+#[test]
+fn attack_cancels_pending_garbage_before_it_is_added() {
+    let mut engine = Engine::with_seed(Rules::default(), 0);
+    engine.receive_garbage(3);
+
+    // A larger attack cancels all of it and sends the rest
+    assert_eq!(engine.cancel_garbage(5), 2);
+    assert_eq!(engine.pending_garbage, 0);
+    engine.apply(Input::HardDrop);
+    assert_eq!(garbage_rows(&engine), 0);
+
+    // A smaller one cancels part of it, the rest comes up with the next lock
+    engine.receive_garbage(3);
+    assert_eq!(engine.cancel_garbage(1), 0);
+    assert_eq!(engine.pending_garbage, 2);
+    engine.apply(Input::HardDrop);
+    assert_eq!(engine.pending_garbage, 0);
+    assert_eq!(garbage_rows(&engine), 2);
+}